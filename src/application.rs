@@ -1,25 +1,46 @@
-use std::path::PathBuf;
+use std::{os::unix::net::UnixListener, path::PathBuf, sync::Arc};
 
 use iced::{
     alignment::Vertical,
     widget::{self, center, container, mouse_area, opaque},
     Element,
     Length::{self, Fill},
-    Padding, Task,
+    Padding, Subscription, Task,
 };
 
 use crate::{
+    assets, bisect,
     config::{self, SBIConfig},
-    executable::{Executable, ExecutableVariant},
+    executable::Executable,
     game_launcher::{self, SBILaunchStatus},
+    history::{self, LaunchHistory},
+    ipc::{self, SBIRequest},
+    launch_plan::{self, LaunchMode, LaunchPlan, LaunchPlanError},
+    lint,
     menus::{
+        asset_browser::{AssetBrowserSubmenuData, AssetBrowserSubmenuMessage},
+        bisect::{BisectSubmenuData, BisectSubmenuMessage},
         configure_profile::{ConfigureProfileSubmenuData, ConfigureProfileSubmenuMessage},
+        delete_profile::{DeleteProfileSubmenuData, DeleteProfileSubmenuMessage},
         duplicate_profile::{DuplicateData, DuplicateSubmenuData, DuplicateSubmenuMessage},
+        launch_history::{LaunchHistorySubmenuData, LaunchHistorySubmenuMessage},
+        launch_plan::{LaunchPlanSubmenuData, LaunchPlanSubmenuMessage},
+        lint::{LintSubmenuData, LintSubmenuMessage},
         new_profile::{NewProfileSubmenuData, NewProfileSubmenuMessage},
+        onboarding::{OnboardingSubmenuData, OnboardingSubmenuMessage},
         rename_profile::{RenameSubmenuData, RenameSubmenuMessage},
         settings::{SettingsSubmenuData, SettingsSubmenuMessage},
+        trash::{TrashSubmenuData, TrashSubmenuMessage},
+        vanilla_setup::{VanillaSetupSubmenuData, VanillaSetupSubmenuMessage},
     },
-    profile::{self, Profile, ProfileJson},
+    profile::{self, Profile, ProfileId, ProfileJson, ProfileSort},
+    profile_lock::{self, ProfileLockError},
+    shortcuts,
+    steam_command::SteamCommand,
+    steam_shortcuts,
+    trash::{self, TrashedProfile},
+    vanilla::{self, VanillaInstall, VanillaPaths},
+    watcher::{self, WatchEvent},
     SBIDirectories,
 };
 
@@ -29,9 +50,18 @@ use crate::{
 enum SubMenu {
     NewProfile(NewProfileSubmenuData),
     ConfigureProfile(ConfigureProfileSubmenuData),
-    Settings(SettingsSubmenuData),
+    Settings(Box<SettingsSubmenuData>),
     RenameProfile(RenameSubmenuData),
     DuplicateProfile(DuplicateSubmenuData),
+    LaunchHistory(LaunchHistorySubmenuData),
+    LaunchPlan(LaunchPlanSubmenuData),
+    Bisect(BisectSubmenuData),
+    AssetBrowser(AssetBrowserSubmenuData),
+    Lint(LintSubmenuData),
+    DeleteProfile(DeleteProfileSubmenuData),
+    Trash(TrashSubmenuData),
+    VanillaSetup(VanillaSetupSubmenuData),
+    Onboarding(OnboardingSubmenuData),
 }
 
 #[derive(Debug, Clone)]
@@ -40,31 +70,71 @@ pub enum Message {
     Dummy(()),
     FetchedProfiles(Vec<Profile>),
     FetchedConfig(SBIConfig),
+    FetchedHistory(LaunchHistory),
+    DetectedGameVersions(Vec<(Option<String>, String)>),
+    FilesystemChanged(WatchEvent),
+    HandleRequest(SBIRequest),
     LaunchedGame(SBILaunchStatus),
+    LaunchBisectRound(ProfileId, Vec<PathBuf>),
+    /// Launches a profile once its mods were linted, or the lint's issues were dismissed
+    LaunchWithoutLint(ProfileId),
     CreateProfile(ProfileJson),
-    ModifyCurrentProfile(ProfileJson),
-    RenameCurrentProfile(String),
-    DuplicateCurrentProfile(DuplicateData),
-    DeleteCurrentProfile,
+    ModifyProfile(ProfileJson),
+    RenameProfile(ProfileId, String),
+    DuplicateProfile(ProfileId, DuplicateData),
+    DeleteProfile(ProfileId),
+    RestoreProfile(TrashedProfile),
+    EmptyTrash,
+    CreateDesktopEntry,
+    ExportLaunchScript,
+    AddSteamShortcut,
     WriteExecutable(String, Executable),
     RemoveExecutable(String),
+    RenameExecutable(String, String),
+    WriteVanillaInstall(String, VanillaInstall),
+    RemoveVanillaInstall(String),
+    WriteVanillaPaths(VanillaPaths),
+    FinishOnboarding,
     SelectExecutable(String),
     ButtonSettingsPressed,
     ButtonConfigureProfilePressed,
     ButtonLaunchPressed,
+    ButtonSafeModeLaunchPressed,
     ButtonExitSubmenuPressed,
     ButtonNewProfilePressed,
     ButtonRenamePressed,
     ButtonDuplicatePressed,
+    ButtonHistoryPressed,
+    ButtonLaunchPlanPressed,
+    ButtonBisectPressed,
+    ButtonAssetBrowserPressed,
+    ButtonLintPressed,
+    ButtonDeletePressed(ProfileId),
+    ButtonTrashPressed,
+    ButtonVanillaSetupPressed,
     ToggleDebug(bool),
     ToggleCloseOnLaunch(bool),
-    SelectProfile(usize),
+    ToggleLintBeforeLaunch(bool),
+    ToggleFavorite,
+    SelectProfile(ProfileId),
+    SearchProfiles(String),
+    SortProfiles(ProfileSort),
+    FilterProfileGroup(Option<String>),
     // Submenu messages
     NewProfileMessage(NewProfileSubmenuMessage),
     SettingsMessage(SettingsSubmenuMessage),
     ConfigureProfileMessage(ConfigureProfileSubmenuMessage),
     RenameProfileMessage(RenameSubmenuMessage),
     DuplicateProfileMessage(DuplicateSubmenuMessage),
+    LaunchHistoryMessage(LaunchHistorySubmenuMessage),
+    LaunchPlanMessage(LaunchPlanSubmenuMessage),
+    BisectMessage(BisectSubmenuMessage),
+    AssetBrowserMessage(AssetBrowserSubmenuMessage),
+    LintMessage(LintSubmenuMessage),
+    DeleteProfileMessage(DeleteProfileSubmenuMessage),
+    TrashMessage(TrashSubmenuMessage),
+    VanillaSetupMessage(VanillaSetupSubmenuMessage),
+    OnboardingMessage(OnboardingSubmenuMessage),
 }
 
 impl From<NewProfileSubmenuMessage> for Message {
@@ -92,42 +162,201 @@ impl From<DuplicateSubmenuMessage> for Message {
         Message::DuplicateProfileMessage(val)
     }
 }
+impl From<LaunchHistorySubmenuMessage> for Message {
+    fn from(val: LaunchHistorySubmenuMessage) -> Message {
+        Message::LaunchHistoryMessage(val)
+    }
+}
+impl From<LaunchPlanSubmenuMessage> for Message {
+    fn from(val: LaunchPlanSubmenuMessage) -> Message {
+        Message::LaunchPlanMessage(val)
+    }
+}
+impl From<BisectSubmenuMessage> for Message {
+    fn from(val: BisectSubmenuMessage) -> Message {
+        Message::BisectMessage(val)
+    }
+}
+impl From<AssetBrowserSubmenuMessage> for Message {
+    fn from(val: AssetBrowserSubmenuMessage) -> Message {
+        Message::AssetBrowserMessage(val)
+    }
+}
+impl From<LintSubmenuMessage> for Message {
+    fn from(val: LintSubmenuMessage) -> Message {
+        Message::LintMessage(val)
+    }
+}
+impl From<DeleteProfileSubmenuMessage> for Message {
+    fn from(val: DeleteProfileSubmenuMessage) -> Message {
+        Message::DeleteProfileMessage(val)
+    }
+}
+impl From<TrashSubmenuMessage> for Message {
+    fn from(val: TrashSubmenuMessage) -> Message {
+        Message::TrashMessage(val)
+    }
+}
+impl From<VanillaSetupSubmenuMessage> for Message {
+    fn from(val: VanillaSetupSubmenuMessage) -> Message {
+        Message::VanillaSetupMessage(val)
+    }
+}
+impl From<OnboardingSubmenuMessage> for Message {
+    fn from(val: OnboardingSubmenuMessage) -> Message {
+        Message::OnboardingMessage(val)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Application {
     dirs: SBIDirectories,
     profiles: Vec<Profile>,
     config: SBIConfig,
+    history: LaunchHistory,
+    /// Game versions of the vanilla installs by name, None for the default install
+    game_versions: rustc_hash::FxHashMap<Option<String>, String>,
     debug: bool,
     submenu: Option<SubMenu>,
-    selected_profile: Option<usize>,
+    selected_profile: Option<ProfileId>,
+    profile_search: String,
+    profile_group_filter: Option<String>,
+    ipc_listener: Option<Arc<UnixListener>>,
+    steam_command: Option<SteamCommand>,
 }
 
 impl Application {
-    pub fn new(dirs: SBIDirectories) -> Self {
+    pub fn new(
+        dirs: SBIDirectories,
+        ipc_listener: Option<Arc<UnixListener>>,
+        steam_command: Option<SteamCommand>,
+    ) -> Self {
         Self {
             dirs,
             profiles: vec![],
             config: SBIConfig::default(),
+            history: LaunchHistory::default(),
+            game_versions: Default::default(),
             debug: false,
             submenu: None,
             selected_profile: None,
+            profile_search: String::new(),
+            profile_group_filter: None,
+            ipc_listener,
+            steam_command,
         }
     }
     pub fn executables(&self) -> &rustc_hash::FxHashMap<String, Executable> {
         &self.config.executables
     }
-    pub fn executables_mut(&mut self) -> &mut rustc_hash::FxHashMap<String, Executable> {
-        &mut self.config.executables
-    }
     pub fn config(&self) -> &SBIConfig {
         &self.config
     }
+    /// Game version of a vanilla install, None for the default install
+    pub fn game_version(&self, install: Option<&str>) -> Option<&str> {
+        self.game_versions
+            .get(&install.map(str::to_string))
+            .map(String::as_str)
+    }
+    /// Works out the launch plan of `profile` off the UI thread
+    fn launch_plan(
+        &self,
+        profile: &Profile,
+        mode: LaunchMode,
+    ) -> impl std::future::Future<Output = Result<LaunchPlan, LaunchPlanError>> + 'static {
+        // Bisect rounds are test launches, steam has no need to track them
+        let steam_command = self
+            .steam_command
+            .clone()
+            .filter(|_| !matches!(mode, LaunchMode::Bisect(_)));
+        LaunchPlan::new_task(
+            profile.clone(),
+            self.config.clone(),
+            self.dirs().clone(),
+            self.game_version(profile.vanilla_install())
+                .map(str::to_string),
+            steam_command,
+            mode,
+        )
+    }
+    fn detect_game_versions_task(&self) -> Task<Message> {
+        let installs: Vec<(Option<String>, PathBuf)> = self
+            .dirs()
+            .vanilla_directory()
+            .map(|dir| (None, dir.to_path_buf()))
+            .into_iter()
+            .chain(
+                self.config
+                    .vanilla_installs
+                    .iter()
+                    .map(|(name, install)| (Some(name.clone()), install.directory.clone())),
+            )
+            .collect();
+        async fn detect(installs: Vec<(Option<String>, PathBuf)>) -> Vec<(Option<String>, String)> {
+            installs
+                .into_iter()
+                .filter_map(|(name, dir)| vanilla::detect_version(&dir).map(|v| (name, v)))
+                .collect()
+        }
+        Task::perform(detect(installs), Message::DetectedGameVersions)
+    }
+    pub fn history(&self) -> &LaunchHistory {
+        &self.history
+    }
+    pub fn profile(&self, id: ProfileId) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.id() == id)
+    }
+    pub fn profile_mut(&mut self, id: ProfileId) -> Option<&mut Profile> {
+        self.profiles.iter_mut().find(|p| p.id() == id)
+    }
     pub fn current_profile(&self) -> Option<&Profile> {
-        self.selected_profile.and_then(|p| self.profiles.get(p))
+        self.selected_profile.and_then(|id| self.profile(id))
     }
     pub fn current_profile_mut(&mut self) -> Option<&mut Profile> {
-        self.selected_profile.and_then(|p| self.profiles.get_mut(p))
+        self.selected_profile.and_then(|id| self.profile_mut(id))
+    }
+    /// Finds a profile by its id, falling back to its name
+    fn find_profile(&self, id_or_name: &str) -> Option<&Profile> {
+        profile::find_by_id_or_name(&self.profiles, id_or_name)
+    }
+
+    /// Profiles matching the search and group filter, favorites first, then in the configured order
+    fn visible_profiles(&self) -> Vec<&Profile> {
+        let mut profiles: Vec<&Profile> = self
+            .profiles
+            .iter()
+            .filter(|p| p.matches_search(&self.profile_search))
+            .filter(|p| {
+                self.profile_group_filter
+                    .as_ref()
+                    .is_none_or(|group| p.groups().contains(group))
+            })
+            .collect();
+        profiles.sort_by(|a, b| {
+            let order = match self.config.profile_sort {
+                ProfileSort::Name => a.name().to_lowercase().cmp(&b.name().to_lowercase()),
+                // Most recent first, profiles without a time last
+                ProfileSort::LastPlayed => self
+                    .history
+                    .last_played(b.id())
+                    .cmp(&self.history.last_played(a.id())),
+                ProfileSort::Created => b.created().cmp(&a.created()),
+            };
+            b.is_favorite().cmp(&a.is_favorite()).then(order)
+        });
+        profiles
+    }
+
+    /// Every group used by a profile, sorted and deduplicated
+    fn profile_groups(&self) -> Vec<String> {
+        let mut groups: Vec<String> = self
+            .profiles
+            .iter()
+            .flat_map(|p| p.groups().iter().cloned())
+            .collect();
+        groups.sort();
+        groups.dedup();
+        groups
     }
 
     fn find_profiles_with_invalidated_executables(
@@ -143,6 +372,21 @@ impl Application {
             .cloned()
     }
 
+    /// Re-fetches the profiles if the default install's storage, the vanilla profile, changed
+    fn refetch_profiles_if_vanilla_moved(
+        &self,
+        previous_storage: Option<PathBuf>,
+    ) -> Task<Message> {
+        let vanilla_profile_dir = self.dirs().vanilla_storage().map(PathBuf::from);
+        if vanilla_profile_dir == previous_storage {
+            return Task::none();
+        }
+        let profiles_dir = self.dirs().profiles().to_path_buf();
+        Task::perform(
+            profile::find_profiles(profiles_dir, vanilla_profile_dir),
+            Message::FetchedProfiles,
+        )
+    }
     fn write_config_task(&self) -> Task<Message> {
         let config = self.config.clone();
         let dir = self.dirs().data().to_path_buf();
@@ -153,13 +397,74 @@ impl Application {
     fn write_profile_task(&self, profile: Profile) -> Task<Message> {
         Task::perform(profile::write_profile(profile), |_| Message::Dummy(()))
     }
+    fn launch_profile_task(&self, profile: Profile, mode: LaunchMode) -> Task<Message> {
+        let bisect_round = matches!(mode, LaunchMode::Bisect(_));
+        let plan = self.launch_plan(&profile, mode);
+        let launch_settings = game_launcher::SBILaunchSettings {
+            close_on_launch: self.config.close_on_launch && !bisect_round,
+            // The window stays, a steam-wrapped launch is spawned under it like any other
+            replace_process: false,
+        };
+        let launch = async move {
+            match plan.await {
+                Ok(plan) => {
+                    log::info!("Launching {} with plan:\n{plan}", profile.name());
+                    game_launcher::launch_game(plan, launch_settings).await
+                }
+                Err(e) => {
+                    log::error!("Cannot launch: {e}");
+                    SBILaunchStatus::Failure
+                }
+            }
+        };
+        Task::perform(launch, Message::LaunchedGame)
+    }
+    /// Opens the lint submenu for `profile` and lints the mods its launch would load
+    fn lint_task(&mut self, profile: &Profile, launch_after: bool) -> Task<Message> {
+        let plan = self.launch_plan(profile, LaunchMode::Normal);
+        let profile_name = profile.name().to_string();
+        self.submenu = Some(SubMenu::Lint(LintSubmenuData::new(
+            profile.id(),
+            launch_after,
+        )));
+        let lint = async move {
+            match plan.await {
+                Ok(plan) => {
+                    let sources = plan
+                        .mod_directories()
+                        .iter()
+                        .flat_map(|dir| assets::source_paths(dir))
+                        .collect();
+                    lint::lint_task(plan.asset_directories(), sources).await
+                }
+                Err(e) => vec![lint::LintIssue {
+                    file: profile_name,
+                    line: None,
+                    severity: lint::Severity::Error,
+                    message: e.to_string(),
+                }],
+            }
+        };
+        Task::perform(lint, |issues| LintSubmenuMessage::Linted(issues).into())
+    }
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Dummy(()) => Task::none(),
             Message::FetchedProfiles(profiles) => {
                 log::info!("Fetched profiles using async tasks! ({})", profiles.len());
                 self.profiles = profiles;
-                self.selected_profile = None;
+                // Profiles can be re-fetched at any time by the filesystem watcher, only keep the
+                // selection if the previously selected profile still exists.
+                if self.current_profile().is_none() {
+                    self.selected_profile = None;
+                }
+                if self
+                    .profile_group_filter
+                    .as_ref()
+                    .is_some_and(|group| !self.profile_groups().contains(group))
+                {
+                    self.profile_group_filter = None;
+                }
                 let write_tasks = self
                     .find_profiles_with_invalidated_executables()
                     .map(|mut p| {
@@ -168,11 +473,6 @@ impl Application {
                     })
                     .map(|p| self.write_profile_task(p))
                     .collect::<Vec<_>>();
-                // Remove selected profile. There's no gurantee the previously selected profile
-                // will be in the same place nor still exist after a fetch.
-                // Unlike a tui environment, the user can just easily
-                // re-select a profile.
-
                 let profiles_dir = self.dirs().profiles().to_path_buf();
                 let vanilla_profile_dir = self.dirs().vanilla_storage().map(PathBuf::from);
                 if !write_tasks.is_empty() {
@@ -185,13 +485,121 @@ impl Application {
                 }
             }
             Message::FetchedConfig(config) => {
+                let previous_storage = self.dirs().vanilla_storage().map(PathBuf::from);
+                self.dirs.set_configured_vanilla(config.vanilla.clone());
                 self.config = config;
+                if self.submenu.is_none() {
+                    // Existing users that never finished onboarding already have executables
+                    if !self.config.onboarded && self.config.executables.is_empty() {
+                        log::info!("First run, opening the onboarding guide");
+                        self.submenu = Some(SubMenu::Onboarding(OnboardingSubmenuData::new()));
+                    } else if self.dirs().vanilla().is_none() {
+                        log::info!("No vanilla install, opening the setup wizard");
+                        self.submenu =
+                            Some(SubMenu::VanillaSetup(VanillaSetupSubmenuData::new(None)));
+                    }
+                }
+                self.detect_game_versions_task()
+                    .chain(self.refetch_profiles_if_vanilla_moved(previous_storage))
+            }
+            Message::DetectedGameVersions(versions) => {
+                for (name, version) in &versions {
+                    log::info!(
+                        "Vanilla install {} is version {version}",
+                        name.as_deref().unwrap_or("default")
+                    );
+                }
+                self.game_versions = versions.into_iter().collect();
                 Task::none()
             }
-            Message::LaunchedGame(status) => {
-                log::info!("Launched Game: {status:?}");
+            Message::FetchedHistory(history) => {
+                self.history = history;
                 Task::none()
             }
+            Message::FilesystemChanged(WatchEvent::ProfilesChanged) => {
+                log::info!("Profiles changed on disk, re-fetching");
+                let profiles_dir = self.dirs().profiles().to_path_buf();
+                let vanilla_profile_dir = self.dirs().vanilla_storage().map(PathBuf::from);
+                Task::perform(
+                    profile::find_profiles(profiles_dir, vanilla_profile_dir),
+                    Message::FetchedProfiles,
+                )
+            }
+            Message::FilesystemChanged(WatchEvent::ConfigChanged) => {
+                log::info!("Config changed on disk, re-loading");
+                let dir = self.dirs().data().to_path_buf();
+                Task::perform(config::reload_config(dir), |config| {
+                    config.map_or(Message::Dummy(()), Message::FetchedConfig)
+                })
+            }
+            Message::HandleRequest(SBIRequest::Focus) => {
+                iced::window::get_latest().and_then(iced::window::gain_focus)
+            }
+            Message::HandleRequest(SBIRequest::Launch { profile, safe_mode }) => {
+                match self.find_profile(&profile).cloned() {
+                    Some(profile) => {
+                        self.selected_profile = Some(profile.id());
+                        let mode = if safe_mode {
+                            LaunchMode::Safe
+                        } else {
+                            LaunchMode::Normal
+                        };
+                        self.launch_profile_task(profile, mode)
+                    }
+                    None => {
+                        log::error!("Requested to launch unknown profile {profile}");
+                        Task::none()
+                    }
+                }
+            }
+            Message::LaunchedGame(SBILaunchStatus::Exited {
+                profile,
+                record,
+                test_launch,
+            }) => {
+                log::info!("Game exited: {record:?}");
+                let bisect_task = match &self.submenu {
+                    Some(SubMenu::Bisect(s)) if s.profile() == profile => {
+                        Task::done(BisectSubmenuMessage::RoundFinished(Some(record.clone())).into())
+                    }
+                    _ => Task::none(),
+                };
+                // Test launches don't count as playing the profile
+                if test_launch {
+                    return bisect_task;
+                }
+                self.history.add(profile, record);
+                let dir = self.dirs().data().to_path_buf();
+                Task::perform(history::write_history(dir, self.history.clone()), |r| {
+                    if let Err(e) = r {
+                        log::error!("Error writing launch history: {e}");
+                    }
+                    Message::Dummy(())
+                })
+                .chain(bisect_task)
+            }
+            Message::LaunchedGame(status @ SBILaunchStatus::Failure) => {
+                log::info!("Launched Game: {status:?}");
+                match &self.submenu {
+                    Some(SubMenu::Bisect(_)) => {
+                        Task::done(BisectSubmenuMessage::RoundFinished(None).into())
+                    }
+                    _ => Task::none(),
+                }
+            }
+            Message::LaunchBisectRound(id, mods) => match self.profile(id).cloned() {
+                Some(profile) => self.launch_profile_task(profile, LaunchMode::Bisect(mods)),
+                None => Task::done(Message::LaunchedGame(SBILaunchStatus::Failure)),
+            },
+            Message::LaunchWithoutLint(id) => {
+                let _ = self
+                    .submenu
+                    .take_if(|submenu| matches!(submenu, SubMenu::Lint(_)));
+                match self.profile(id).cloned() {
+                    Some(profile) => self.launch_profile_task(profile, LaunchMode::Normal),
+                    None => Task::none(),
+                }
+            }
             Message::CreateProfile(profile) => {
                 log::info!(
                     "Creating new profile - {} : {:?}",
@@ -212,35 +620,35 @@ impl Application {
                     Message::FetchedProfiles,
                 )
             }
-            Message::ModifyCurrentProfile(json) => {
-                log::info!("Modifying current profile...");
-                if let Some(profile) = self.current_profile_mut() {
+            Message::ModifyProfile(json) => {
+                log::info!("Modifying profile {}...", json.id);
+                if let Some(profile) = self.profile_mut(json.id) {
                     profile.set_json(json);
                     Task::perform(profile::write_profile(profile.clone()), |_| {
                         Message::Dummy(())
                     })
                 } else {
-                    log::error!("Trying to write data without a selected profile!!");
+                    log::error!(
+                        "Trying to write data to profile {} which does not exist!!",
+                        json.id
+                    );
                     Task::none()
                 }
             }
-            Message::RenameCurrentProfile(name) => {
-                if let Some(profile) = self.current_profile_mut() {
-                    if let Some(json) = profile.json_mut() {
-                        json.name = name;
+            Message::RenameProfile(id, name) => {
+                match self.profile_mut(id) {
+                    // The vanilla profile is always called Default
+                    Some(profile) if !profile.is_vanilla() => {
+                        profile.json_mut().name = name;
                         Task::perform(profile::write_profile(profile.clone()), |_| {
                             Message::Dummy(())
                         })
-                    } else {
-                        // non-vanilla/default profiles do not have json
-                        Task::none()
                     }
-                } else {
-                    Task::none()
+                    _ => Task::none(),
                 }
             }
-            Message::DuplicateCurrentProfile(data) => {
-                if let Some(current_profile) = self.current_profile().cloned() {
+            Message::DuplicateProfile(id, data) => {
+                if let Some(current_profile) = self.profile(id).cloned() {
                     let profiles_dir = self.dirs().profiles().to_path_buf();
                     let maybe_vanilla_profile_dir =
                         self.dirs().vanilla_storage().map(PathBuf::from);
@@ -257,19 +665,119 @@ impl Application {
                     Task::none()
                 }
             }
-            Message::DeleteCurrentProfile => {
-                if let Some(profile) = self.current_profile() {
-                    log::warn!("Deleting profile {}", profile.path().display());
-                    let delete_path = profile.path().to_owned();
-                    if let Some(p) = self.selected_profile.as_ref() {
-                        self.profiles.remove(*p);
+            Message::DeleteProfile(id) => {
+                if let Some(profile) = self.profile(id).cloned() {
+                    // Checked here too, so a running profile isn't dropped from the list
+                    if let Some(pid) = profile_lock::holder(profile.path()) {
+                        log::error!(
+                            "Cannot delete profile {}: {}",
+                            profile.name(),
+                            ProfileLockError::AlreadyRunning(profile.name().to_string(), pid)
+                        );
+                        return Task::none();
+                    }
+                    log::warn!("Moving profile {} to the trash", profile.path().display());
+                    self.profiles.retain(|p| p.id() != id);
+                    let _ = self.selected_profile.take_if(|selected| *selected == id);
+                    let trash_dir = self.dirs().trash().to_path_buf();
+                    Task::perform(trash::trash_profile(profile, trash_dir), |r| {
+                        if let Err(e) = r {
+                            log::error!("Error moving profile to the trash: {e}");
+                        }
+                        Message::Dummy(())
+                    })
+                } else {
+                    log::error!("Attempting to delete profile {id} which does not exist!!");
+                    Task::none()
+                }
+            }
+            Message::RestoreProfile(trashed) => {
+                async fn restore_then_list(
+                    trashed: TrashedProfile,
+                    profiles_dir: PathBuf,
+                    trash_dir: PathBuf,
+                ) -> Vec<TrashedProfile> {
+                    if let Err(e) = trash::restore_profile(trashed, profiles_dir).await {
+                        log::error!("Error restoring profile: {e}");
+                    }
+                    trash::list_trash(trash_dir).await
+                }
+                let profiles_dir = self.dirs().profiles().to_path_buf();
+                let vanilla_profile_dir = self.dirs().vanilla_storage().map(PathBuf::from);
+                let trash_dir = self.dirs().trash().to_path_buf();
+                Task::perform(
+                    restore_then_list(trashed, profiles_dir.clone(), trash_dir),
+                    |trashed| TrashSubmenuMessage::Loaded(trashed).into(),
+                )
+                .chain(Task::perform(
+                    profile::find_profiles(profiles_dir, vanilla_profile_dir),
+                    Message::FetchedProfiles,
+                ))
+            }
+            Message::EmptyTrash => {
+                log::warn!("Emptying the trash");
+                let trash_dir = self.dirs().trash().to_path_buf();
+                Task::perform(trash::empty_trash(trash_dir), |r| {
+                    if let Err(e) = r {
+                        log::error!("Error emptying the trash: {e}");
+                    }
+                    TrashSubmenuMessage::Loaded(vec![]).into()
+                })
+            }
+            Message::CreateDesktopEntry => {
+                if let Some(profile) = self.current_profile().cloned() {
+                    Task::perform(shortcuts::write_desktop_entry(profile), |r| {
+                        match r {
+                            Ok(path) => log::info!("Wrote desktop entry {}", path.display()),
+                            Err(e) => log::error!("Error writing desktop entry: {e}"),
+                        }
+                        Message::Dummy(())
+                    })
+                } else {
+                    log::error!(
+                        "Attempting to create a desktop entry without having a profile selected!!"
+                    );
+                    Task::none()
+                }
+            }
+            Message::ExportLaunchScript => {
+                if let Some(profile) = self.current_profile().cloned() {
+                    async fn pick_and_write_script(profile: Profile) -> anyhow::Result<()> {
+                        let file_name = profile.name().replace(['/', '\\'], "_");
+                        let Some(file) = rfd::AsyncFileDialog::new()
+                            .set_file_name(format!("{file_name}.sh"))
+                            .save_file()
+                            .await
+                        else {
+                            return Ok(());
+                        };
+                        shortcuts::write_launch_script(profile, file.path().to_path_buf()).await
                     }
-                    Task::perform(tokio::fs::remove_dir_all(delete_path), |_| {
+                    Task::perform(pick_and_write_script(profile), |r| {
+                        if let Err(e) = r {
+                            log::error!("Error writing launch script: {e}");
+                        }
                         Message::Dummy(())
                     })
                 } else {
                     log::error!(
-                        "Attempting to delete a profile without having a profile selected!!"
+                        "Attempting to export a launch script without having a profile selected!!"
+                    );
+                    Task::none()
+                }
+            }
+            Message::AddSteamShortcut => {
+                if let Some(profile) = self.current_profile().cloned() {
+                    Task::perform(steam_shortcuts::add_profile_to_steam(profile), |r| {
+                        match r {
+                            Ok(users) => log::info!("Added steam shortcut for {users} user(s)"),
+                            Err(e) => log::error!("Error adding steam shortcut: {e}"),
+                        }
+                        Message::Dummy(())
+                    })
+                } else {
+                    log::error!(
+                        "Attempting to add a steam shortcut without having a profile selected!!"
                     );
                     Task::none()
                 }
@@ -299,6 +807,75 @@ impl Application {
                     .map(|p| self.write_profile_task(p));
                 Task::batch(profile_write_tasks).chain(self.write_config_task())
             }
+            Message::RenameExecutable(old, new) => {
+                let Some(executable) = self.config.executables.remove(&old) else {
+                    log::error!("Attempting to rename executable {old} which does not exist!!");
+                    return Task::none();
+                };
+                log::info!("Renaming executable {old} to {new}");
+                self.config.executables.insert(new.clone(), executable);
+                if self.config.default_executable.as_ref() == Some(&old) {
+                    self.config.default_executable = Some(new.clone());
+                }
+                let renamed_profiles: Vec<Profile> = self
+                    .profiles
+                    .iter_mut()
+                    .filter(|p| p.selected_executable() == Some(old.as_str()))
+                    .map(|p| {
+                        p.json_mut().selected_executable = Some(new.clone());
+                        p.clone()
+                    })
+                    .collect();
+                let profile_write_tasks = renamed_profiles
+                    .into_iter()
+                    .map(|p| self.write_profile_task(p))
+                    .collect::<Vec<_>>();
+                Task::batch(profile_write_tasks).chain(self.write_config_task())
+            }
+            Message::WriteVanillaInstall(name, install) => {
+                log::info!(
+                    "Registering vanilla install {name}: {}",
+                    install.directory.display()
+                );
+                self.config.vanilla_installs.insert(name, install);
+                self.write_config_task()
+                    .chain(self.detect_game_versions_task())
+            }
+            Message::RemoveVanillaInstall(name) => {
+                self.config.vanilla_installs.remove(&name);
+                let profile_write_tasks = self
+                    .profiles
+                    .iter()
+                    .filter(|p| p.vanilla_install() == Some(name.as_str()))
+                    .cloned()
+                    .map(|mut p| {
+                        p.clear_vanilla_install();
+                        p
+                    })
+                    .map(|p| self.write_profile_task(p))
+                    .collect::<Vec<_>>();
+                Task::batch(profile_write_tasks).chain(self.write_config_task())
+            }
+            Message::WriteVanillaPaths(paths) => {
+                log::info!("Using vanilla assets {}", paths.assets.display());
+                if self.dirs.vanilla_override.is_some() {
+                    log::warn!("--assets or SBI_VANILLA_ASSETS_DIR is set and takes priority over the configured vanilla install");
+                }
+                let previous_storage = self.dirs().vanilla_storage().map(PathBuf::from);
+                self.dirs.set_configured_vanilla(Some(paths.clone()));
+                self.config.vanilla = Some(paths);
+                self.write_config_task()
+                    .chain(self.detect_game_versions_task())
+                    .chain(self.refetch_profiles_if_vanilla_moved(previous_storage))
+            }
+            Message::FinishOnboarding => {
+                log::info!("Onboarding finished");
+                self.config.onboarded = true;
+                let _ = self
+                    .submenu
+                    .take_if(|submenu| matches!(submenu, SubMenu::Onboarding(_)));
+                self.write_config_task()
+            }
             Message::ToggleDebug(state) => {
                 log::info!("Toggling debug: {}", state);
                 self.debug = state;
@@ -308,13 +885,37 @@ impl Application {
                 self.config.close_on_launch = state;
                 self.write_config_task()
             }
+            Message::ToggleLintBeforeLaunch(state) => {
+                self.config.lint_before_launch = state;
+                self.write_config_task()
+            }
+            Message::ToggleFavorite => {
+                if let Some(profile) = self.current_profile_mut() {
+                    let json = profile.json_mut();
+                    json.favorite = !json.favorite;
+                    let profile = profile.clone();
+                    self.write_profile_task(profile)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::SearchProfiles(search) => {
+                self.profile_search = search;
+                Task::none()
+            }
+            Message::SortProfiles(sort) => {
+                self.config.profile_sort = sort;
+                self.write_config_task()
+            }
+            Message::FilterProfileGroup(group) => {
+                self.profile_group_filter = group;
+                Task::none()
+            }
             Message::SelectExecutable(executable) => {
                 log::info!("Selecting executable: {}", executable);
                 self.config.default_executable = Some(executable.clone());
                 if let Some(profile) = self.current_profile_mut() {
-                    if let Some(json) = profile.json_mut() {
-                        json.selected_executable = Some(executable);
-                    }
+                    profile.json_mut().selected_executable = Some(executable);
                     let profile = profile.clone();
                     self.write_profile_task(profile)
                 } else {
@@ -323,16 +924,12 @@ impl Application {
             }
             Message::ButtonSettingsPressed => {
                 log::info!("Settings was pressed");
-                self.submenu = Some(SubMenu::Settings(SettingsSubmenuData::new()));
+                self.submenu = Some(SubMenu::Settings(Box::new(SettingsSubmenuData::new())));
                 Task::none()
             }
             Message::ButtonConfigureProfilePressed => {
                 log::info!("Configure Profile was pressed");
-                if let Some(profile) = self
-                    .selected_profile
-                    .and_then(|p| self.profiles.get(p))
-                    .and_then(|p| p.json())
-                {
+                if let Some(profile) = self.current_profile().map(|p| p.json()) {
                     self.submenu = Some(SubMenu::ConfigureProfile(
                         ConfigureProfileSubmenuData::new(profile),
                     ));
@@ -348,31 +945,19 @@ impl Application {
             }
             Message::ButtonLaunchPressed => {
                 let profile = self
-                    .selected_profile
-                    .and_then(|p| self.profiles.get(p))
+                    .current_profile()
                     .cloned()
                     .expect("No profile selected?!");
-                let executable = profile
-                    .selected_executable()
-                    .and_then(|name| self.executables().get(name))
-                    .cloned()
-                    .expect("NEED_EXECUTABLE_SELECTED_TO_LAUNCH_GAME");
-                let vanilla_assets = self.dirs().vanilla_assets().to_path_buf();
-                let vanilla_mods = self.dirs().vanilla_mods().map(|p| p.to_path_buf());
-                let launch_settings = game_launcher::SBILaunchSettings {
-                    close_on_launch: self.config.close_on_launch,
+                if self.config.lint_before_launch {
+                    return self.lint_task(&profile, true);
+                }
+                self.launch_profile_task(profile, LaunchMode::Normal)
+            }
+            Message::ButtonSafeModeLaunchPressed => {
+                let Some(profile) = self.current_profile().cloned() else {
+                    return Task::none();
                 };
-                log::info!("Launching {} with {:?}", profile.name(), executable);
-                Task::perform(
-                    game_launcher::launch_game(
-                        executable,
-                        profile,
-                        vanilla_mods,
-                        vanilla_assets,
-                        launch_settings,
-                    ),
-                    Message::LaunchedGame,
-                )
+                self.launch_profile_task(profile, LaunchMode::Safe)
             }
             Message::ButtonNewProfilePressed => {
                 log::info!("New profile empty");
@@ -383,6 +968,7 @@ impl Application {
                 log::info!("Rename profile");
                 if let Some(profile) = self.current_profile() {
                     self.submenu = Some(SubMenu::RenameProfile(RenameSubmenuData::new(
+                        profile.id(),
                         profile.name(),
                     )));
                 }
@@ -392,19 +978,99 @@ impl Application {
                 log::info!("Duplicate profile");
                 if let Some(profile) = self.current_profile() {
                     self.submenu = Some(SubMenu::DuplicateProfile(DuplicateSubmenuData::new(
+                        profile.id(),
                         profile.name(),
                     )));
                 }
                 Task::none()
             }
-            Message::SelectProfile(i) => {
-                match self.profiles.get(i) {
-                    Some(name) => {
-                        log::info!("Selecting profile {} - {:?}", i, name);
-                        self.selected_profile = Some(i);
+            Message::ButtonHistoryPressed => {
+                if let Some(profile) = self.current_profile() {
+                    self.submenu = Some(SubMenu::LaunchHistory(LaunchHistorySubmenuData::new(
+                        profile.id(),
+                    )));
+                }
+                Task::none()
+            }
+            Message::ButtonLaunchPlanPressed => {
+                let Some(profile) = self.current_profile() else {
+                    return Task::none();
+                };
+                let plan = self.launch_plan(profile, LaunchMode::Normal);
+                self.submenu = Some(SubMenu::LaunchPlan(LaunchPlanSubmenuData::new()));
+                Task::perform(plan, |plan| LaunchPlanSubmenuMessage::loaded(plan).into())
+            }
+            Message::ButtonBisectPressed => {
+                if let Some(profile) = self.current_profile() {
+                    let vanilla_mods =
+                        launch_plan::vanilla_paths(profile, &self.config, self.dirs())
+                            .and_then(|(_, mods)| mods);
+                    self.submenu = Some(SubMenu::Bisect(BisectSubmenuData::new(
+                        profile.id(),
+                        self.dirs().safe_mode().join(profile.id().to_string()),
+                        bisect::mod_sources(profile, vanilla_mods.as_deref()),
+                    )));
+                }
+                Task::none()
+            }
+            Message::ButtonAssetBrowserPressed => {
+                let Some(profile) = self.current_profile() else {
+                    return Task::none();
+                };
+                // The sources are the ones a launch writes into sbinit.config, in the same order
+                let plan = self.launch_plan(profile, LaunchMode::Normal);
+                self.submenu = Some(SubMenu::AssetBrowser(AssetBrowserSubmenuData::new(
+                    profile.id(),
+                )));
+                let load = async move {
+                    match plan.await {
+                        Ok(plan) => assets::load_tree(plan.asset_directories()).await,
+                        Err(e) => (Arc::default(), vec![e.to_string()]),
+                    }
+                };
+                Task::perform(load, |(tree, errors)| {
+                    AssetBrowserSubmenuMessage::Loaded(tree, errors).into()
+                })
+            }
+            Message::ButtonLintPressed => match self.current_profile().cloned() {
+                Some(profile) => self.lint_task(&profile, false),
+                None => Task::none(),
+            },
+            Message::ButtonDeletePressed(id) => {
+                if let Some(profile) = self.profile(id) {
+                    let path = profile.path().to_path_buf();
+                    self.submenu = Some(SubMenu::DeleteProfile(DeleteProfileSubmenuData::new(
+                        id,
+                        profile.name(),
+                    )));
+                    Task::perform(trash::summarize(path), |contents| {
+                        DeleteProfileSubmenuMessage::LoadedContents(contents).into()
+                    })
+                } else {
+                    Task::none()
+                }
+            }
+            Message::ButtonTrashPressed => {
+                self.submenu = Some(SubMenu::Trash(TrashSubmenuData::new()));
+                let trash_dir = self.dirs().trash().to_path_buf();
+                Task::perform(trash::list_trash(trash_dir), |trashed| {
+                    TrashSubmenuMessage::Loaded(trashed).into()
+                })
+            }
+            Message::ButtonVanillaSetupPressed => {
+                self.submenu = Some(SubMenu::VanillaSetup(VanillaSetupSubmenuData::new(
+                    self.dirs().vanilla(),
+                )));
+                Task::none()
+            }
+            Message::SelectProfile(id) => {
+                match self.profile(id) {
+                    Some(profile) => {
+                        log::info!("Selecting profile {id} - {:?}", profile);
+                        self.selected_profile = Some(id);
                     }
                     None => {
-                        log::error!("Selected profile {i} is out of bounds of the profile list of length {}!", self.profiles.len());
+                        log::error!("Selected profile {id} is not in the profile list!");
                     }
                 }
                 Task::none()
@@ -418,9 +1084,8 @@ impl Application {
                 }
             }
             Message::SettingsMessage(m) => {
-                let dirs = self.dirs().clone();
                 if let Some(SubMenu::Settings(s)) = self.submenu.as_mut() {
-                    s.update(m, &mut self.config, &dirs)
+                    s.update(m, &self.config)
                 } else {
                     Task::none()
                 }
@@ -446,6 +1111,69 @@ impl Application {
                     Task::none()
                 }
             }
+            Message::LaunchHistoryMessage(m) => {
+                if let Some(SubMenu::LaunchHistory(s)) = self.submenu.as_mut() {
+                    s.update(m)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::LaunchPlanMessage(m) => {
+                if let Some(SubMenu::LaunchPlan(s)) = self.submenu.as_mut() {
+                    s.update(m)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::BisectMessage(m) => {
+                if let Some(SubMenu::Bisect(s)) = self.submenu.as_mut() {
+                    s.update(m)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::AssetBrowserMessage(m) => {
+                if let Some(SubMenu::AssetBrowser(s)) = self.submenu.as_mut() {
+                    s.update(m)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::LintMessage(m) => {
+                if let Some(SubMenu::Lint(s)) = self.submenu.as_mut() {
+                    s.update(m)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::DeleteProfileMessage(m) => {
+                if let Some(SubMenu::DeleteProfile(s)) = self.submenu.as_mut() {
+                    s.update(m)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::TrashMessage(m) => {
+                if let Some(SubMenu::Trash(s)) = self.submenu.as_mut() {
+                    s.update(m)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::OnboardingMessage(m) => {
+                if let Some(SubMenu::Onboarding(s)) = self.submenu.as_mut() {
+                    s.update(m, &self.config, &self.dirs)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::VanillaSetupMessage(m) => {
+                if let Some(SubMenu::VanillaSetup(s)) = self.submenu.as_mut() {
+                    s.update(m)
+                } else {
+                    Task::none()
+                }
+            }
         }
     }
 
@@ -453,6 +1181,23 @@ impl Application {
         &self.dirs
     }
 
+    /// Watches the profiles directory and config.json for changes made outside of the GUI, and
+    /// listens for requests forwarded by other sbi invocations.
+    pub fn subscription(&self) -> Subscription<Message> {
+        let data_dir = self.dirs().data().to_path_buf();
+        let profiles_dir = self.dirs().profiles().to_path_buf();
+        let watcher = Subscription::run_with_id(
+            "sbi-filesystem-watcher",
+            watcher::watch(data_dir, profiles_dir),
+        )
+        .map(Message::FilesystemChanged);
+        let ipc = self.ipc_listener.clone().map(|listener| {
+            Subscription::run_with_id("sbi-ipc-listener", ipc::listen(listener))
+                .map(Message::HandleRequest)
+        });
+        Subscription::batch(std::iter::once(watcher).chain(ipc))
+    }
+
     pub fn theme(&self) -> iced::Theme {
         iced::Theme::TokyoNight
     }
@@ -465,14 +1210,22 @@ impl Application {
         let new_profile_button =
             widget::button("New Profile").on_press(Message::ButtonNewProfilePressed);
 
+        // Trash Button
+        let trash_button = widget::button("Trash").on_press(Message::ButtonTrashPressed);
+
         // Debug Checkbox
         let debug_checkbox = widget::checkbox("Debug", self.debug).on_toggle(Message::ToggleDebug);
 
         // Top Bar
-        let controls = widget::row![settings_button, new_profile_button, debug_checkbox,]
-            .spacing(5)
-            .height(40)
-            .align_y(Vertical::Center);
+        let controls = widget::row![
+            settings_button,
+            new_profile_button,
+            trash_button,
+            debug_checkbox,
+        ]
+        .spacing(5)
+        .height(40)
+        .align_y(Vertical::Center);
 
         let maybe_profile_controls = if let Some(profile) = self.current_profile() {
             let selected_executable: Option<String> =
@@ -487,12 +1240,18 @@ impl Application {
                 .on_press_maybe(launch_button_message)
                 .width(Length::Fill);
 
-            // Configure Profile Button
-            let configure_profile_buttton_message =
-                (!profile.is_vanilla()).then_some(Message::ButtonConfigureProfilePressed);
+            // Safe Mode Button
+            let safe_mode_button = widget::button("Launch in Safe Mode")
+                .on_press_maybe(
+                    selected_executable
+                        .is_some()
+                        .then_some(Message::ButtonSafeModeLaunchPressed),
+                )
+                .width(Length::Fill);
 
+            // Configure Profile Button
             let configure_profile_button = widget::button("Configure Profile")
-                .on_press_maybe(configure_profile_buttton_message)
+                .on_press(Message::ButtonConfigureProfilePressed)
                 .width(Length::Fill);
 
             // Executable Picker
@@ -504,26 +1263,68 @@ impl Application {
             )
             .placeholder("Select an executable...");
 
+            // Version Mismatch Warning
+            let game_version = self.game_version(profile.vanilla_install());
+            let version_warning = selected_executable
+                .as_ref()
+                .and_then(|name| self.executables().get(name))
+                .and_then(Executable::target_version)
+                .zip(game_version)
+                .filter(|(target, version)| !vanilla::same_release(target, version))
+                .map(|(target, version)| {
+                    widget::text!("Executable targets {target}, the game is {version}")
+                        .size(12)
+                        .color(iced::Color::from_rgb(0.9, 0.6, 0.2))
+                });
+
             // Rename button
-            let rename_profile_button =
-                widget::button("Rename").on_press(Message::ButtonRenamePressed);
+            let rename_profile_button = widget::button("Rename")
+                .on_press_maybe((!profile.is_vanilla()).then_some(Message::ButtonRenamePressed));
             // Duplicate button
             let duplicate_profile_button =
                 widget::button("Duplicate").on_press(Message::ButtonDuplicatePressed);
+            // History button
+            let history_button = widget::button("History").on_press(Message::ButtonHistoryPressed);
+            // Launch Plan button
+            let launch_plan_button =
+                widget::button("Show Launch Command").on_press(Message::ButtonLaunchPlanPressed);
+            // Bisect button
+            let bisect_button = widget::button("Find Broken Mod").on_press_maybe(
+                selected_executable
+                    .is_some()
+                    .then_some(Message::ButtonBisectPressed),
+            );
+            // Asset Browser button
+            let asset_browser_button =
+                widget::button("Browse Assets").on_press(Message::ButtonAssetBrowserPressed);
+            // Lint button
+            let lint_button = widget::button("Lint Mods").on_press(Message::ButtonLintPressed);
+            // Favorite button
+            let favorite_button = widget::button(if profile.is_favorite() {
+                "Unfavorite"
+            } else {
+                "Favorite"
+            })
+            .on_press(Message::ToggleFavorite);
 
             // Profile Configuration Panel
-            let profile_controls = widget::column![
-                launch_button,
-                configure_profile_button,
-                executable_picker,
-                rename_profile_button,
-                duplicate_profile_button
-            ]
-            .width(250)
-            .spacing(3)
-            .padding(Padding::new(5.0));
-
-            self.selected_profile.map(|_i| profile_controls)
+            let profile_controls =
+                widget::column![launch_button, configure_profile_button, executable_picker,]
+                    .push_maybe(version_warning)
+                    .push(rename_profile_button)
+                    .push(duplicate_profile_button)
+                    .push(safe_mode_button)
+                    .push(history_button)
+                    .push(launch_plan_button)
+                    .push(bisect_button)
+                    .push(asset_browser_button)
+                    .push(lint_button)
+                    .push(favorite_button)
+                    .width(250)
+                    .spacing(3)
+                    .padding(Padding::new(5.0));
+
+            Some(profile_controls)
         } else {
             None
         };
@@ -538,6 +1339,15 @@ impl Application {
                 SubMenu::Settings(m) => m.view(self).map(|m| m.into()),
                 SubMenu::RenameProfile(m) => m.view(self).map(|m| m.into()),
                 SubMenu::DuplicateProfile(m) => m.view(self).map(|m| m.into()),
+                SubMenu::LaunchHistory(m) => m.view(self).map(|m| m.into()),
+                SubMenu::LaunchPlan(m) => m.view(self).map(|m| m.into()),
+                SubMenu::Bisect(m) => m.view(self).map(|m| m.into()),
+                SubMenu::AssetBrowser(m) => m.view(self).map(|m| m.into()),
+                SubMenu::Lint(m) => m.view(self).map(|m| m.into()),
+                SubMenu::DeleteProfile(m) => m.view(self).map(|m| m.into()),
+                SubMenu::Trash(m) => m.view(self).map(|m| m.into()),
+                SubMenu::VanillaSetup(m) => m.view(self).map(|m| m.into()),
+                SubMenu::Onboarding(m) => m.view(self).map(|m| m.into()),
             })
         });
         let stacked_content = widget::stack(std::iter::once(content.into())).push_maybe(popup);
@@ -552,28 +1362,69 @@ impl Application {
     }
 
     fn view_select_profile(&self) -> Element<'_, Message> {
-        let profile_to_widget = |(i, p)| -> (usize, Element<'_, Message>) {
+        let profile_to_widget = |p: &Profile| -> (ProfileId, Element<'_, Message>) {
             let text_color = self
                 .selected_profile
-                .is_some_and(|p_i| p_i == i)
+                .is_some_and(|selected| selected == p.id())
                 .then(|| iced::Color::from_rgba(0.3, 0.7, 0.2, 1.0));
-            let raw_text = widget::text!("{}", p)
+            let favorite = if p.is_favorite() { "★ " } else { "" };
+            let raw_text = widget::text!("{favorite}{}", p.name())
                 .width(Fill)
                 .color_maybe(text_color)
                 .size(20);
-            let text = widget::column![raw_text, widget::horizontal_rule(2),];
+            let played = self.history.last_played(p.id()).map(|last_played| {
+                widget::text!(
+                    "Last played {} · {} total",
+                    history::format_time_ago(last_played),
+                    history::format_duration(self.history.total_playtime(p.id()))
+                )
+                .size(12)
+            });
+            let groups = (!p.groups().is_empty())
+                .then(|| widget::text!("{}", p.groups().join(", ")).size(12));
+            let text = widget::column![raw_text]
+                .push_maybe(played)
+                .push_maybe(groups)
+                .push(widget::horizontal_rule(2));
             (
-                i,
-                mouse_area(text).on_press(Message::SelectProfile(i)).into(),
+                p.id(),
+                mouse_area(text)
+                    .on_press(Message::SelectProfile(p.id()))
+                    .into(),
             )
         };
-        let profiles = self.profiles.iter().map(Profile::name);
-        let profiles = widget::keyed_column(profiles.enumerate().map(profile_to_widget))
-            .width(Length::Fill)
-            .align_items(iced::Alignment::Start)
-            .spacing(8);
+
+        // Search, Sort and Group Filter
+        let search = widget::text_input("Search...", &self.profile_search)
+            .on_input(Message::SearchProfiles)
+            .width(Length::Fill);
+        let sort_picker = widget::pick_list(
+            ProfileSort::options(),
+            Some(self.config.profile_sort),
+            Message::SortProfiles,
+        );
+        let group_picker = widget::pick_list(
+            self.profile_groups(),
+            self.profile_group_filter.clone(),
+            |group| Message::FilterProfileGroup(Some(group)),
+        )
+        .placeholder("All groups");
+        let clear_group_button = self
+            .profile_group_filter
+            .is_some()
+            .then(|| widget::button("×").on_press(Message::FilterProfileGroup(None)));
+        let filters = widget::row![search, sort_picker, group_picker]
+            .push_maybe(clear_group_button)
+            .spacing(5)
+            .align_y(Vertical::Center);
+
+        let profiles =
+            widget::keyed_column(self.visible_profiles().into_iter().map(profile_to_widget))
+                .width(Length::Fill)
+                .align_items(iced::Alignment::Start)
+                .spacing(8);
         let scrolling_profiles = widget::scrollable(profiles).height(Length::Fill).spacing(3);
-        widget::container(scrolling_profiles)
+        widget::container(widget::column![filters, scrolling_profiles].spacing(5))
             .width(Length::FillPortion(4))
             .into()
     }
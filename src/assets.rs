@@ -0,0 +1,627 @@
+//! The asset tree the game sees.
+//! Every asset folder of `sbinit.config` either is an asset source itself, if it has a `_metadata`
+//! file, or holds one source per subfolder and packed mod. Sources are loaded in that order, sorted
+//! by the `priority` of their metadata, and a file of a later source overrides the same file of an
+//! earlier one. Sources ordered through their metadata's `includes` and `requires` are not
+//! reordered here.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    ops::Bound,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rustc_hash::FxHashMap;
+
+use crate::lenient_json;
+
+/// Extensions of packed sources, anything else in an asset folder has to be a directory to be loaded
+const PACKED_SOURCE_EXTENSIONS: [&str; 2] = ["pak", "modpak"];
+/// Files describing a source, they are not assets themselves
+const METADATA_NAMES: [&str; 2] = ["_metadata", ".metadata"];
+/// Magic bytes at the start of a packed source
+const PAK_SIGNATURE: &[u8; 8] = b"SBAsset6";
+/// Magic bytes at the start of a packed source's index
+const PAK_INDEX_SIGNATURE: &[u8; 5] = b"INDEX";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AssetError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("{0} is not a valid pak: {1}")]
+    InvalidPak(PathBuf, String),
+    #[error("No source provides {0}")]
+    NotFound(String),
+}
+
+/// Offset and size of every file in a pak
+type PakIndex = FxHashMap<String, (u64, u64)>;
+
+#[derive(Debug, Clone)]
+enum SourceKind {
+    /// Asset paths, relative to the folder, of every file in it
+    Directory(Vec<String>),
+    Packed(PakIndex),
+}
+
+/// A folder or pak the game loads assets from
+#[derive(Debug, Clone)]
+pub struct AssetSource {
+    pub path: PathBuf,
+    pub metadata: serde_json::Value,
+    kind: SourceKind,
+}
+
+impl AssetSource {
+    /// Reads the metadata and file list of the source at `path`
+    pub fn open(path: &Path) -> Result<Self, AssetError> {
+        if path.is_dir() {
+            let metadata = metadata_file(path)
+                .and_then(|file| std::fs::read(file).ok())
+                .and_then(|bytes| lenient_json::from_slice(&bytes).ok())
+                .unwrap_or_default();
+            let mut files = Vec::new();
+            list_directory(path, "", &mut files)
+                .map_err(|e| AssetError::Io(path.to_path_buf(), e))?;
+            Ok(Self {
+                path: path.to_path_buf(),
+                metadata,
+                kind: SourceKind::Directory(files),
+            })
+        } else {
+            let (metadata, index) = read_pak_index(path)?;
+            Ok(Self {
+                path: path.to_path_buf(),
+                metadata,
+                kind: SourceKind::Packed(index),
+            })
+        }
+    }
+
+    /// The metadata file of a folder source, paks keep their metadata in the index
+    pub fn metadata_file(&self) -> Option<PathBuf> {
+        match self.kind {
+            SourceKind::Directory(_) => metadata_file(&self.path),
+            SourceKind::Packed(_) => None,
+        }
+    }
+
+    pub fn is_packed(&self) -> bool {
+        matches!(self.kind, SourceKind::Packed(_))
+    }
+
+    /// Name the source gives itself in its metadata, its file name otherwise
+    pub fn name(&self) -> String {
+        ["friendlyName", "name"]
+            .iter()
+            .find_map(|key| self.metadata.get(key).and_then(|name| name.as_str()))
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                self.path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| self.path.display().to_string())
+            })
+    }
+
+    pub fn priority(&self) -> f64 {
+        self.metadata
+            .get("priority")
+            .and_then(serde_json::Value::as_f64)
+            .unwrap_or_default()
+    }
+
+    /// Asset paths of the files in the source, e.g. `/player.config`
+    pub fn files(&self) -> Vec<&str> {
+        match &self.kind {
+            SourceKind::Directory(files) => files.iter().map(String::as_str).collect(),
+            SourceKind::Packed(index) => index.keys().map(String::as_str).collect(),
+        }
+    }
+
+    pub fn read(&self, asset: &str) -> Result<Vec<u8>, AssetError> {
+        match &self.kind {
+            SourceKind::Directory(_) => {
+                let path = self.path.join(asset.trim_start_matches('/'));
+                std::fs::read(&path).map_err(|e| AssetError::Io(path, e))
+            }
+            SourceKind::Packed(index) => {
+                let &(offset, size) = index
+                    .get(asset)
+                    .ok_or_else(|| AssetError::NotFound(asset.to_string()))?;
+                let io_error = |e| AssetError::Io(self.path.clone(), e);
+                let mut file = File::open(&self.path).map_err(io_error)?;
+                file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+                let mut bytes = Vec::new();
+                file.take(size).read_to_end(&mut bytes).map_err(io_error)?;
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+/// The sources of a launch and, for every asset path, which of them provide it
+#[derive(Debug, Clone, Default)]
+pub struct AssetTree {
+    sources: Vec<AssetSource>,
+    /// Indices into `sources` in load order, the last one is the file the game uses
+    files: BTreeMap<String, Vec<usize>>,
+}
+
+impl AssetTree {
+    /// Finds and opens the sources of `asset_directories`, in the order the game loads them.
+    /// Sources that can't be read are left out and returned with the tree.
+    pub fn load(asset_directories: &[PathBuf]) -> (Self, Vec<AssetError>) {
+        let mut errors = Vec::new();
+        let mut sources: Vec<AssetSource> = asset_directories
+            .iter()
+            .flat_map(|dir| source_paths(dir))
+            .filter_map(|path| match AssetSource::open(&path) {
+                Ok(source) => Some(source),
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            })
+            .collect();
+        sources.sort_by(|a, b| a.priority().total_cmp(&b.priority()));
+
+        let mut files: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, source) in sources.iter().enumerate() {
+            for file in source.files() {
+                files.entry(file.to_string()).or_default().push(i);
+            }
+        }
+        (Self { sources, files }, errors)
+    }
+
+    pub fn sources(&self) -> &[AssetSource] {
+        &self.sources
+    }
+
+    pub fn source(&self, index: usize) -> &AssetSource {
+        &self.sources[index]
+    }
+
+    /// Every asset path, including patch files, in sorted order
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    pub fn contains(&self, asset: &str) -> bool {
+        self.files.contains_key(asset)
+    }
+
+    /// Indices of the sources providing `asset` in load order, the last one overrides the others
+    pub fn providers(&self, asset: &str) -> &[usize] {
+        self.files.get(asset).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Names of the folders and files directly inside the folder `dir`, `""` being the root
+    pub fn list(&self, dir: &str) -> (Vec<String>, Vec<String>) {
+        let prefix = format!("{dir}/");
+        let mut folders: Vec<String> = Vec::new();
+        let mut files = Vec::new();
+        let inside = self
+            .files
+            .range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+            .map(|(path, _)| &path[..])
+            .take_while(|path| path.starts_with(&prefix));
+        // Paths inside the same folder are next to each other in sorted order
+        for path in inside {
+            match path[prefix.len()..].split_once('/') {
+                Some((folder, _)) if folders.last().is_some_and(|last| last == folder) => {}
+                Some((folder, _)) => folders.push(folder.to_string()),
+                None => files.push(path[prefix.len()..].to_string()),
+            }
+        }
+        (folders, files)
+    }
+
+    /// The file the game loads for `asset`
+    pub fn read(&self, asset: &str) -> Result<Vec<u8>, AssetError> {
+        let source = self
+            .providers(asset)
+            .last()
+            .ok_or_else(|| AssetError::NotFound(asset.to_string()))?;
+        self.sources[*source].read(asset)
+    }
+}
+
+/// [AssetTree::load] on a blocking thread, reading the index of the vanilla pak takes a while
+pub async fn load_tree(asset_directories: Vec<PathBuf>) -> (Arc<AssetTree>, Vec<String>) {
+    match tokio::task::spawn_blocking(move || AssetTree::load(&asset_directories)).await {
+        Ok((tree, errors)) => (
+            Arc::new(tree),
+            errors.iter().map(ToString::to_string).collect(),
+        ),
+        Err(e) => (
+            Arc::default(),
+            vec![format!("Failed to load the assets: {e}")],
+        ),
+    }
+}
+
+/// Sources of an asset folder, in the order the game finds them
+pub fn source_paths(dir: &Path) -> Vec<PathBuf> {
+    if dir.is_file() {
+        return vec![dir.to_path_buf()];
+    }
+    if metadata_file(dir).is_some() {
+        return vec![dir.to_path_buf()];
+    }
+    let Ok(entries) = dir.read_dir() else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() || is_packed_source(path))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn metadata_file(dir: &Path) -> Option<PathBuf> {
+    METADATA_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|file| file.is_file())
+}
+
+fn is_packed_source(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| PACKED_SOURCE_EXTENSIONS.iter().any(|e| ext == *e))
+}
+
+fn list_directory(dir: &Path, prefix: &str, files: &mut Vec<String>) -> std::io::Result<()> {
+    for entry in dir.read_dir()? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let asset = format!("{prefix}/{name}");
+        if entry.path().is_dir() {
+            list_directory(&entry.path(), &asset, files)?;
+        } else if prefix.is_empty() && METADATA_NAMES.contains(&name.as_str()) {
+            continue;
+        } else {
+            files.push(asset);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the metadata and the file index at the end of a pak
+fn read_pak_index(path: &Path) -> Result<(serde_json::Value, PakIndex), AssetError> {
+    let invalid = |reason: &str| AssetError::InvalidPak(path.to_path_buf(), reason.to_string());
+    let io_error = |e| AssetError::Io(path.to_path_buf(), e);
+    let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+
+    let mut signature = [0; 8];
+    reader.read_exact(&mut signature).map_err(io_error)?;
+    if &signature != PAK_SIGNATURE {
+        return Err(invalid("unknown signature"));
+    }
+    let index_offset = read_u64(&mut reader).map_err(io_error)?;
+    reader
+        .seek(SeekFrom::Start(index_offset))
+        .map_err(io_error)?;
+    let mut signature = [0; 5];
+    reader.read_exact(&mut signature).map_err(io_error)?;
+    if &signature != PAK_INDEX_SIGNATURE {
+        return Err(invalid("index not found"));
+    }
+
+    let mut metadata = serde_json::Map::new();
+    for _ in 0..read_vlq(&mut reader).map_err(io_error)? {
+        let key = read_string(&mut reader).map_err(io_error)?;
+        let value = read_variant(&mut reader).map_err(io_error)?;
+        metadata.insert(key, value);
+    }
+    let count = read_vlq(&mut reader).map_err(io_error)?;
+    let mut index = PakIndex::default();
+    for _ in 0..count {
+        let asset = read_string(&mut reader).map_err(io_error)?;
+        let offset = read_u64(&mut reader).map_err(io_error)?;
+        let size = read_u64(&mut reader).map_err(io_error)?;
+        index.insert(asset, (offset, size));
+    }
+    Ok((serde_json::Value::Object(metadata), index))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// Unsigned variable length integer, 7 bits per byte with the highest bit set on all but the last
+fn read_vlq(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut value: u64 = 0;
+    loop {
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte)?;
+        value = (value << 7) | u64::from(byte[0] & 0x7f);
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+fn read_string(reader: &mut impl Read) -> std::io::Result<String> {
+    let len = read_vlq(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// The game's binary encoding of a JSON value, used for the metadata of paks
+fn read_variant(reader: &mut impl Read) -> std::io::Result<serde_json::Value> {
+    use serde_json::Value;
+    let mut kind = [0; 1];
+    reader.read_exact(&mut kind)?;
+    Ok(match kind[0] {
+        1 => Value::Null,
+        2 => {
+            let mut bytes = [0; 8];
+            reader.read_exact(&mut bytes)?;
+            serde_json::Number::from_f64(f64::from_be_bytes(bytes))
+                .map_or(Value::Null, Value::Number)
+        }
+        3 => {
+            let mut byte = [0; 1];
+            reader.read_exact(&mut byte)?;
+            Value::Bool(byte[0] != 0)
+        }
+        4 => {
+            // Zigzag encoded, the lowest bit is the sign
+            let value = read_vlq(reader)?;
+            let value = if value & 1 == 1 {
+                -((value >> 1) as i64) - 1
+            } else {
+                (value >> 1) as i64
+            };
+            Value::from(value)
+        }
+        5 => Value::String(read_string(reader)?),
+        6 => {
+            let len = read_vlq(reader)?;
+            Value::Array(
+                (0..len)
+                    .map(|_| read_variant(reader))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        7 => {
+            let len = read_vlq(reader)?;
+            let mut map = serde_json::Map::new();
+            for _ in 0..len {
+                let key = read_string(reader)?;
+                map.insert(key, read_variant(reader)?);
+            }
+            Value::Object(map)
+        }
+        kind => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unknown value type {kind}"),
+            ))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn vlq(mut value: u64) -> Vec<u8> {
+        let mut bytes = vec![(value & 0x7f) as u8];
+        value >>= 7;
+        while value > 0 {
+            bytes.insert(0, (value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        [vlq(s.len() as u64), s.as_bytes().to_vec()].concat()
+    }
+
+    /// A pak the way the game's asset packer writes it: signature, index offset, the files, then
+    /// the index with the metadata
+    fn pak(metadata: &[(&str, Vec<u8>)], files: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = PAK_SIGNATURE.to_vec();
+        bytes.extend([0; 8]);
+        let mut index = PAK_INDEX_SIGNATURE.to_vec();
+        index.extend(vlq(metadata.len() as u64));
+        for (key, variant) in metadata {
+            index.extend(string(key));
+            index.extend(variant);
+        }
+        index.extend(vlq(files.len() as u64));
+        for (asset, contents) in files {
+            index.extend(string(asset));
+            index.extend((bytes.len() as u64).to_be_bytes());
+            index.extend((contents.len() as u64).to_be_bytes());
+            bytes.extend(contents.as_bytes());
+        }
+        let index_offset = bytes.len() as u64;
+        bytes[8..16].copy_from_slice(&index_offset.to_be_bytes());
+        bytes.extend(index);
+        bytes
+    }
+
+    fn write_folder_source(dir: &Path, metadata: &str, files: &[(&str, &str)]) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("_metadata"), metadata).unwrap();
+        for (asset, contents) in files {
+            let path = dir.join(asset.trim_start_matches('/'));
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+
+    #[test]
+    fn decodes_vlq() {
+        let decode = |bytes: &[u8]| read_vlq(&mut &bytes[..]).unwrap();
+        assert_eq!(decode(&[0x00]), 0);
+        assert_eq!(decode(&[0x7f]), 127);
+        assert_eq!(decode(&[0x81, 0x00]), 128);
+        assert_eq!(decode(&[0x82, 0x80, 0x00]), 1 << 15);
+        // Only the first integer is read
+        assert_eq!(decode(&[0x05, 0x81]), 5);
+        for value in [0, 1, 300, 16384, u32::MAX as u64] {
+            assert_eq!(decode(&vlq(value)), value);
+        }
+        // The continuation bit promises another byte
+        assert!(read_vlq(&mut &[0x81][..]).is_err());
+    }
+
+    #[test]
+    fn decodes_variants() {
+        let decode = |bytes: &[u8]| read_variant(&mut &bytes[..]).unwrap();
+        assert_eq!(decode(&[1]), json!(null));
+        assert_eq!(decode(&[3, 1]), json!(true));
+        assert_eq!(decode(&[4, 2]), json!(1));
+        assert_eq!(decode(&[4, 3]), json!(-2));
+        assert_eq!(
+            decode(&[[2].as_slice(), &1.5f64.to_be_bytes()].concat()),
+            json!(1.5)
+        );
+        assert_eq!(
+            decode(&[[5].as_slice(), &string("mod")].concat()),
+            json!("mod")
+        );
+        assert_eq!(
+            decode(&[[6, 2, 3, 0, 4, 1].as_slice()].concat()),
+            json!([false, -1])
+        );
+        assert_eq!(
+            decode(&[[7, 1].as_slice(), &string("key"), &[1]].concat()),
+            json!({"key": null})
+        );
+        assert!(read_variant(&mut &[9][..]).is_err());
+    }
+
+    #[test]
+    fn reads_pak_index_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mod.pak");
+        let name = [[5].as_slice(), &string("Packed Mod")].concat();
+        std::fs::write(
+            &path,
+            pak(
+                &[("friendlyName", name), ("priority", vec![4, 20])],
+                &[
+                    ("/items/apple.item", r#"{"itemName": "apple"}"#),
+                    ("/player.config.patch", "[]"),
+                ],
+            ),
+        )
+        .unwrap();
+
+        let source = AssetSource::open(&path).unwrap();
+        assert!(source.is_packed());
+        assert_eq!(source.name(), "Packed Mod");
+        assert_eq!(source.priority(), 10.0);
+        let mut files = source.files();
+        files.sort();
+        assert_eq!(files, ["/items/apple.item", "/player.config.patch"]);
+        assert_eq!(
+            source.read("/items/apple.item").unwrap(),
+            br#"{"itemName": "apple"}"#
+        );
+        assert!(matches!(
+            source.read("/missing"),
+            Err(AssetError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_broken_paks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.pak");
+        std::fs::write(&path, b"SBAsset5\0\0\0\0\0\0\0\0").unwrap();
+        assert!(matches!(
+            AssetSource::open(&path),
+            Err(AssetError::InvalidPak(_, reason)) if reason == "unknown signature"
+        ));
+        let mut bytes = pak(&[], &[("/a", "a")]);
+        let index = bytes
+            .windows(PAK_INDEX_SIGNATURE.len())
+            .rposition(|w| w == PAK_INDEX_SIGNATURE)
+            .unwrap();
+        bytes[index] = b'X';
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            AssetSource::open(&path),
+            Err(AssetError::InvalidPak(_, reason)) if reason == "index not found"
+        ));
+        // The index claims more files than there are
+        let mut bytes = pak(&[], &[]);
+        *bytes.last_mut().unwrap() = 3;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(AssetSource::open(&path), Err(AssetError::Io(..))));
+    }
+
+    #[test]
+    fn later_sources_override_earlier_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let vanilla = dir.path().join("assets");
+        let mods = dir.path().join("mods");
+        write_folder_source(
+            &vanilla,
+            r#"{"name": "base", "priority": -9999}"#,
+            &[("/player.config", "vanilla"), ("/items/apple.item", "{}")],
+        );
+        write_folder_source(
+            &mods.join("b_late"),
+            r#"{"name": "late", "priority": 5}"#,
+            &[("/player.config", "late")],
+        );
+        std::fs::write(
+            mods.join("a_early.pak"),
+            pak(
+                &[],
+                &[("/player.config", "early"), ("/items/pear.item", "{}")],
+            ),
+        )
+        .unwrap();
+        std::fs::write(mods.join("notes.txt"), "not a source").unwrap();
+
+        assert_eq!(
+            source_paths(&mods),
+            [mods.join("a_early.pak"), mods.join("b_late")]
+        );
+        let (tree, errors) = AssetTree::load(&[vanilla, mods]);
+        assert!(errors.is_empty());
+        let names: Vec<String> = tree.sources().iter().map(AssetSource::name).collect();
+        assert_eq!(names, ["base", "a_early.pak", "late"]);
+        assert_eq!(tree.providers("/player.config"), [0, 1, 2]);
+        assert_eq!(tree.read("/player.config").unwrap(), b"late");
+        assert!(!tree.contains("/_metadata"));
+        assert!(matches!(
+            tree.read("/missing"),
+            Err(AssetError::NotFound(_))
+        ));
+        assert_eq!(
+            tree.list(""),
+            (
+                vec![String::from("items")],
+                vec![String::from("player.config")]
+            )
+        );
+        assert_eq!(
+            tree.list("/items"),
+            (
+                vec![],
+                vec![String::from("apple.item"), String::from("pear.item")]
+            )
+        );
+    }
+}
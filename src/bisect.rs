@@ -0,0 +1,322 @@
+//! Finding the mod that breaks a profile.
+//! The first round loads every mod, to see that they do break the game. Then the mods are split in halves and each half is launched on its own, keeping whichever
+//! half still breaks the game. If neither half breaks it on its own, the problem comes from mods of
+//! both halves together: the second half is kept loaded while the first is narrowed down, then the
+//! culprit found there is kept loaded while the second is, which ends with a pair. Culprits that
+//! were not the last round to break the game get a round of their own before they are reported.
+
+use std::path::{Path, PathBuf};
+
+use crate::{assets, profile::Profile};
+
+/// Log the game writes into its storage folder
+const STARBOUND_LOG_NAME: &str = "starbound.log";
+/// Log lines the game writes when it crashes or fails to load its assets
+const CRASH_LOG_MARKERS: [&str; 3] = ["[Error] Fatal Error", "Fatal Exception", "Segfault"];
+
+/// The mods the profile loads on top of the vanilla and executable assets: every asset source
+/// inside its additional asset folders and, if linked, the vanilla mods folder.
+pub fn mod_sources(profile: &Profile, vanilla_mods: Option<&Path>) -> Vec<PathBuf> {
+    let linked_mods = vanilla_mods
+        .filter(|_| profile.link_mods())
+        .map(Path::to_path_buf);
+    profile
+        .additional_assets()
+        .chain(linked_mods)
+        .flat_map(|dir| assets::source_paths(&dir))
+        .collect()
+}
+
+/// The first line of the game's log in `storage` that shows it crashed, if any
+pub async fn crash_in_log(storage: PathBuf) -> Option<String> {
+    let log = tokio::fs::read_to_string(storage.join(STARBOUND_LOG_NAME))
+        .await
+        .ok()?;
+    log.lines()
+        .find(|line| CRASH_LOG_MARKERS.iter().any(|marker| line.contains(marker)))
+        .map(str::to_string)
+}
+
+/// Which half of the suspects the current round loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Half {
+    First,
+    Second,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    /// Loading every suspect, to see that they break the game
+    Reproduce,
+    Narrow(Half),
+    /// Loading only the culprits, to see that they break the game on their own
+    Confirm,
+    Found,
+    /// The game worked with every mod loaded
+    NotBroken,
+    /// The game worked with only the culprits loaded
+    Unconfirmed,
+}
+
+/// How a bisection ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BisectionResult<'a> {
+    /// The mods that break the game: one, or several that only break it together
+    Culprits(&'a [PathBuf]),
+    /// The game works with every mod loaded, there is nothing to narrow down
+    NotBroken,
+    /// The mods narrowed down to did not break the game on their own, the game breaks
+    /// inconsistently
+    Inconclusive(&'a [PathBuf]),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bisection {
+    /// Mods that may still be the culprit
+    suspects: Vec<PathBuf>,
+    /// Mods loaded in every round, because the problem only shows up together with them
+    required: Vec<PathBuf>,
+    /// Groups to narrow down once the current suspects are, each loaded until then
+    pending: Vec<Vec<PathBuf>>,
+    /// Culprits found so far
+    culprits: Vec<PathBuf>,
+    stage: Stage,
+    round: usize,
+}
+
+impl Bisection {
+    /// Starts narrowing down `mods`, which break the game when loaded together
+    pub fn new(mods: Vec<PathBuf>) -> Self {
+        let mut bisection = Self {
+            suspects: mods,
+            required: Vec::new(),
+            pending: Vec::new(),
+            culprits: Vec::new(),
+            stage: Stage::Reproduce,
+            round: 1,
+        };
+        if bisection.suspects.is_empty() {
+            bisection.stage = Stage::Found;
+        }
+        bisection
+    }
+
+    /// How the bisection ended, None while there are rounds left
+    pub fn result(&self) -> Option<BisectionResult<'_>> {
+        match self.stage {
+            Stage::Found => Some(BisectionResult::Culprits(&self.culprits)),
+            Stage::NotBroken => Some(BisectionResult::NotBroken),
+            Stage::Unconfirmed => Some(BisectionResult::Inconclusive(&self.culprits)),
+            Stage::Reproduce | Stage::Narrow(_) | Stage::Confirm => None,
+        }
+    }
+
+    /// What the current round checks
+    pub fn round_purpose(&self) -> &'static str {
+        match self.stage {
+            Stage::Reproduce => "checking that the game breaks with every mod loaded",
+            Stage::Narrow(_) => "narrowing down the mods",
+            Stage::Confirm => "checking that the mods found break the game on their own",
+            Stage::Found | Stage::NotBroken | Stage::Unconfirmed => "done",
+        }
+    }
+
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    fn halves(&self) -> (&[PathBuf], &[PathBuf]) {
+        self.suspects.split_at(self.suspects.len() / 2)
+    }
+
+    /// Mods to launch the current round with
+    pub fn round_mods(&self) -> Vec<PathBuf> {
+        let (first, second) = self.halves();
+        match self.stage {
+            Stage::Reproduce => self.suspects.clone(),
+            Stage::Narrow(Half::First) => self.required.iter().chain(first).cloned().collect(),
+            Stage::Narrow(Half::Second) => self.required.iter().chain(second).cloned().collect(),
+            Stage::Confirm => self.culprits.clone(),
+            Stage::Found | Stage::NotBroken | Stage::Unconfirmed => Vec::new(),
+        }
+    }
+
+    /// Records whether the game broke with the current round's mods and moves on to the next round
+    pub fn answer(&mut self, broken: bool) {
+        let loaded = self.round_mods();
+        let (first, second) = self.halves();
+        let (first, second) = (first.to_vec(), second.to_vec());
+        match (self.stage, broken) {
+            (Stage::Found | Stage::NotBroken | Stage::Unconfirmed, _) => return,
+            (Stage::Reproduce, true) => self.stage = Stage::Narrow(Half::First),
+            (Stage::Reproduce, false) => self.stage = Stage::NotBroken,
+            (Stage::Narrow(Half::First), true) => self.suspects = first,
+            (Stage::Narrow(Half::First), false) => self.stage = Stage::Narrow(Half::Second),
+            (Stage::Narrow(Half::Second), true) => {
+                self.suspects = second;
+                self.stage = Stage::Narrow(Half::First);
+            }
+            // Neither half breaks the game on its own, keep the second loaded while narrowing
+            // down the first
+            (Stage::Narrow(Half::Second), false) => {
+                self.required.extend(second.iter().cloned());
+                self.pending.push(second);
+                self.suspects = first;
+                self.stage = Stage::Narrow(Half::First);
+            }
+            (Stage::Confirm, true) => self.stage = Stage::Found,
+            (Stage::Confirm, false) => self.stage = Stage::Unconfirmed,
+        }
+        self.round += 1;
+        if !matches!(self.stage, Stage::Narrow(_)) {
+            return;
+        }
+        self.settle();
+        if self.suspects.is_empty() {
+            // The culprits need a round of their own unless they are exactly what just broke
+            let mut culprits = self.culprits.clone();
+            let mut loaded = loaded;
+            culprits.sort();
+            loaded.sort();
+            self.stage = if broken && culprits == loaded {
+                Stage::Found
+            } else {
+                Stage::Confirm
+            };
+        }
+    }
+
+    /// Moves a single remaining suspect to the culprits, then continues with the pending groups
+    fn settle(&mut self) {
+        while self.suspects.len() == 1 {
+            let culprit = self.suspects.remove(0);
+            self.culprits.push(culprit.clone());
+            if let Some(group) = self.pending.pop() {
+                self.required.retain(|m| !group.contains(m));
+                self.required.push(culprit);
+                self.suspects = group;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mods(count: usize) -> Vec<PathBuf> {
+        (0..count)
+            .map(|i| PathBuf::from(format!("mod{i}")))
+            .collect()
+    }
+
+    /// Answers every round with `broken` until the bisection is done, returns the culprits and the
+    /// number of rounds it took
+    fn run(mods: Vec<PathBuf>, broken: impl Fn(&[PathBuf]) -> bool) -> (Vec<PathBuf>, usize) {
+        let mut bisection = Bisection::new(mods);
+        while bisection.result().is_none() {
+            assert!(bisection.round() < 100, "bisection does not end");
+            let round_mods = bisection.round_mods();
+            bisection.answer(broken(&round_mods));
+        }
+        match bisection.result() {
+            Some(BisectionResult::Culprits(culprits)) => (culprits.to_vec(), bisection.round()),
+            result => panic!("no culprits found: {result:?}"),
+        }
+    }
+
+    #[test]
+    fn finds_a_single_culprit_anywhere() {
+        for count in 1..=9 {
+            for culprit in mods(count) {
+                let (found, rounds) = run(mods(count), |loaded| loaded.contains(&culprit));
+                assert_eq!(found, std::slice::from_ref(&culprit), "{count} mods");
+                // The first round loads every mod, then at most two launches per halving, the
+                // round after the last launch is never run
+                let halvings = count.next_power_of_two().ilog2() as usize;
+                assert!(rounds <= 2 * halvings + 2, "{count} mods, {rounds} rounds");
+            }
+        }
+    }
+
+    #[test]
+    fn finds_a_pair_that_only_breaks_together() {
+        for count in 2..=8 {
+            let all = mods(count);
+            for (i, a) in all.iter().enumerate() {
+                for b in &all[i + 1..] {
+                    let (mut found, _) = run(all.clone(), |loaded| {
+                        loaded.contains(a) && loaded.contains(b)
+                    });
+                    found.sort();
+                    assert_eq!(found, [a.clone(), b.clone()], "{count} mods");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rounds_load_half_the_suspects() {
+        let mut bisection = Bisection::new(mods(4));
+        assert_eq!(bisection.round(), 1);
+        assert_eq!(bisection.round_mods(), mods(4));
+        bisection.answer(true);
+        assert_eq!(bisection.round(), 2);
+        assert_eq!(bisection.round_mods(), &mods(4)[..2]);
+        bisection.answer(false);
+        assert_eq!(bisection.round(), 3);
+        assert_eq!(bisection.round_mods(), &mods(4)[2..]);
+        // Neither half breaks alone: the second half stays loaded
+        bisection.answer(false);
+        assert_eq!(
+            bisection.round_mods(),
+            [&mods(4)[2..], &mods(4)[..1]].concat()
+        );
+    }
+
+    #[test]
+    fn nothing_to_bisect() {
+        let bisection = Bisection::new(Vec::new());
+        assert_eq!(bisection.result(), Some(BisectionResult::Culprits(&[])));
+        let mut bisection = Bisection::new(mods(1));
+        assert_eq!(bisection.result(), None);
+        bisection.answer(true);
+        assert_eq!(
+            bisection.result(),
+            Some(BisectionResult::Culprits(&mods(1)))
+        );
+    }
+
+    #[test]
+    fn working_mods_are_not_narrowed_down() {
+        let mut bisection = Bisection::new(mods(4));
+        bisection.answer(false);
+        assert_eq!(bisection.result(), Some(BisectionResult::NotBroken));
+    }
+
+    #[test]
+    fn culprits_get_a_confirming_round() {
+        // Only the first round breaks, which narrows down to the pair of the last two rounds
+        let mut bisection = Bisection::new(mods(2));
+        bisection.answer(true);
+        bisection.answer(false);
+        bisection.answer(false);
+        assert_eq!(bisection.result(), None);
+        assert_eq!(bisection.round_mods(), mods(2));
+        bisection.answer(false);
+        assert_eq!(
+            bisection.result(),
+            Some(BisectionResult::Inconclusive(&mods(2)))
+        );
+    }
+
+    #[test]
+    fn answers_after_the_end_are_ignored() {
+        let mut bisection = Bisection::new(mods(1));
+        bisection.answer(false);
+        assert_eq!(bisection.result(), Some(BisectionResult::NotBroken));
+        bisection.answer(true);
+        assert_eq!(bisection.result(), Some(BisectionResult::NotBroken));
+        assert_eq!(bisection.round(), 2);
+    }
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Debug, Parser)]
 #[command(version, about, long_about=None)]
@@ -8,6 +8,50 @@ pub struct CliArgs {
     #[arg(short, long, value_name = "DIR")]
     pub assets: Option<PathBuf>,
 
+    /// Launch profiles by rewriting the steam command given after `--` instead of spawning the
+    /// game directly, keeping steam's STOP button and overlay working.
+    #[arg(long)]
+    pub steam_wrap: bool,
+
+    #[command(subcommand)]
+    pub command: Option<SBICommand>,
+
     #[arg(last = true)]
     pub default_command: Option<Vec<String>>,
 }
+
+#[derive(Debug, Subcommand)]
+pub enum SBICommand {
+    /// Launch a profile by id or name. If sbi is already running, the running instance launches it,
+    /// with `--steam-wrap` sbi launches it itself without a window.
+    Launch {
+        profile: String,
+        /// Print how the profile would be launched (command, environment, sbinit.config) instead
+        /// of launching it
+        #[arg(long)]
+        dry_run: bool,
+        /// Print the launch plan as JSON
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+        /// Launch with only the vanilla and executable assets, on a throwaway copy of the
+        /// profile's storage, to tell whether a problem is caused by mods
+        #[arg(long)]
+        safe_mode: bool,
+    },
+    /// Apply the JSON patches of a profile's mods the way the game does and report the ones that
+    /// fail or are skipped
+    Patches {
+        profile: String,
+        /// Print this asset as the game sees it after every patch, e.g. /player.config
+        asset: Option<String>,
+        /// Also list the patches that applied
+        #[arg(long)]
+        all: bool,
+    },
+    /// Check a profile's mods, or a mod folder, for malformed JSON, invalid metadata, item names
+    /// used twice and missing images and scripts
+    Lint {
+        /// Id or name of a profile, or the path of a mod or mods folder
+        target: String,
+    },
+}
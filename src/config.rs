@@ -2,23 +2,58 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{executable::Executable, SBI_CONFIG_JSON_NAME};
+use crate::{
+    executable::Executable,
+    migration,
+    profile::ProfileSort,
+    vanilla::{VanillaInstall, VanillaPaths},
+    SBI_CONFIG_JSON_NAME,
+};
 
-#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SBIConfig {
+    pub schema_version: u32,
     pub executables: rustc_hash::FxHashMap<String, Executable>,
     pub default_executable: Option<String>,
     #[serde(default)]
     pub close_on_launch: bool,
+    /// Whether launching a profile lints its mods first, stopping if any of them is broken
+    #[serde(default)]
+    pub lint_before_launch: bool,
+    /// Whether the first-run onboarding guide was finished or skipped
+    #[serde(default)]
+    pub onboarded: bool,
+    #[serde(default)]
+    pub profile_sort: ProfileSort,
+    /// Vanilla install chosen in the setup wizard or kept from the TUI, used unless `--assets` or
+    /// `SBI_VANILLA_ASSETS_DIR` is given
+    #[serde(default)]
+    pub vanilla: Option<VanillaPaths>,
+    /// Vanilla installs registered in addition to the default one, by name
+    #[serde(default)]
+    pub vanilla_installs: rustc_hash::FxHashMap<String, VanillaInstall>,
+}
+
+impl Default for SBIConfig {
+    fn default() -> Self {
+        Self {
+            schema_version: migration::CONFIG_SCHEMA_VERSION,
+            executables: Default::default(),
+            default_executable: None,
+            close_on_launch: false,
+            lint_before_launch: false,
+            onboarded: false,
+            profile_sort: ProfileSort::default(),
+            vanilla: None,
+            vanilla_installs: Default::default(),
+        }
+    }
 }
 
 impl SBIConfig {
-    pub fn get_executable(&self, name: &String) -> Option<&Executable> {
+    pub fn get_executable(&self, name: &str) -> Option<&Executable> {
         self.executables.get(name)
     }
-    pub fn get_executable_mut(&mut self, name: &String) -> Option<&mut Executable> {
-        self.executables.get_mut(name)
-    }
 }
 
 /// Load config from disk at `dir/config.json`
@@ -26,6 +61,16 @@ impl SBIConfig {
 pub async fn load_config(dir: PathBuf) -> SBIConfig {
     match load_config_failable(dir.clone()).await {
         Ok(config) => config,
+        Err(e)
+            if matches!(
+                e.downcast_ref::<migration::MigrationError>(),
+                Some(migration::MigrationError::FromTheFuture { .. })
+            ) =>
+        {
+            // Written by a newer sbi, don't clobber it with defaults.
+            log::error!("Error reading config file: {e}. Using default (empty) values.");
+            SBIConfig::default()
+        }
         Err(e) => {
             // TODO: Handle write error.
             log::warn!("Error reading config file: {e}. Writing default (empty) values.");
@@ -37,10 +82,26 @@ pub async fn load_config(dir: PathBuf) -> SBIConfig {
     }
 }
 
+/// Re-load config from disk at `dir/config.json` after it was changed outside of sbi.
+/// Unlike [load_config], errors leave the file untouched since it may be mid-write.
+pub async fn reload_config(dir: PathBuf) -> Option<SBIConfig> {
+    load_config_failable(dir)
+        .await
+        .inspect_err(|e| log::warn!("Error re-loading config file: {e}"))
+        .ok()
+}
+
 /// Load config from disk at `dir/config.json`
+/// Older config layouts are migrated and written back to disk.
 async fn load_config_failable(dir: PathBuf) -> anyhow::Result<SBIConfig> {
     let bytes = tokio::fs::read(dir.join(SBI_CONFIG_JSON_NAME)).await?;
-    let config = serde_json::from_slice::<SBIConfig>(&bytes)?;
+    let mut json = serde_json::from_slice::<serde_json::Value>(&bytes)?;
+    let migrated = migration::migrate_config(&mut json)?;
+    let config = serde_json::from_value::<SBIConfig>(json)?;
+    if migrated {
+        log::info!("Writing migrated config to disk");
+        write_config_to_disk(dir, config.clone()).await?;
+    }
     Ok(config)
 }
 
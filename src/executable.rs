@@ -1,25 +1,42 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-/// All executables should be some variant of these:  
+use crate::launch_strategy::{self, CustomLaunch, LaunchStrategy};
+
+/// All executables should be some variant of these, each launched by its [LaunchStrategy]:  
 /// - XStarbound - will enable the removal of automatic UGC loading through the ```-noworkshop``` flag  
 /// - OpenStarbound - will enable the removal of automatic UGC loading through the ```"includeUGC": false``` field in sbinit.config
 /// - Vanilla - has no current method for disabling UGC content
-//TODO: Impement onto executables, this is currently unused
+/// - Custom - launched with the user-defined [CustomLaunch] of the executable
 #[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
 pub enum ExecutableVariant {
     XStarbound,
     OpenStarbound,
     Vanilla,
+    Custom,
 }
 
 impl ExecutableVariant {
-    pub fn options() -> [Self; 3] {
-        [Self::XStarbound, Self::OpenStarbound, Self::Vanilla]
+    pub fn options() -> [Self; 4] {
+        [
+            Self::XStarbound,
+            Self::OpenStarbound,
+            Self::Vanilla,
+            Self::Custom,
+        ]
     }
 }
 
+/// Client binary of vanilla and OpenStarbound releases, in their `linux` folder
+const STARBOUND_BINARY_NAME: &str = "starbound";
+/// Client binary of xStarbound releases, in their `linux` folder
+const XSTARBOUND_BINARY_NAME: &str = "xclient";
+/// Pak OpenStarbound ships in the assets folder next to its `linux` folder
+const OPENSTARBOUND_PAK_NAME: &str = "opensb.pak";
+/// The vanilla assets pak, which is not an executable's own assets
+const VANILLA_PAK_NAME: &str = "packed.pak";
+
 /// TODO: Decide whether to keep this or not.
 /// Only needed to handle edge-case of config missing a value but being necessary
 impl Default for ExecutableVariant {
@@ -34,23 +51,119 @@ impl std::fmt::Display for ExecutableVariant {
             Self::XStarbound => "XStarbound",
             Self::OpenStarbound => "OpenStarbound",
             Self::Vanilla => "Vanilla",
+            Self::Custom => "Custom",
         })
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Debug, thiserror::Error)]
+pub enum ExecutableError {
+    #[error("No binary selected")]
+    NoBinary,
+    #[error("Binary {0} does not exist")]
+    MissingBinary(PathBuf),
+    #[error("{0} is not a file")]
+    NotAFile(PathBuf),
+    #[error("{0} is not executable, try `chmod +x`")]
+    NotExecutable(PathBuf),
+    #[error("Asset folder {0} does not exist")]
+    MissingAssets(PathBuf),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Executable {
     /// Path to the starbound binary
     pub bin: PathBuf,
-    /// Assets to load in addition to vanilla assets
-    pub assets: Option<PathBuf>,
+    /// Asset folders to load in addition to vanilla assets, relative paths are resolved from the
+    /// binary's folder
+    #[serde(default)]
+    pub assets: Vec<PathBuf>,
     #[serde(default)]
     pub variant: ExecutableVariant,
+    /// Extra folders to search for shared libraries, after the executable's own folder
+    #[serde(default)]
+    pub library_dirs: Vec<PathBuf>,
+    /// Also search the libraries of the steam runtime shipped with steam
+    #[serde(default)]
+    pub use_steam_runtime: bool,
+    /// Game version the executable was built against, overrides the variant's known target
+    #[serde(default)]
+    pub target_version: Option<String>,
+    /// Release of the executable itself, e.g. `0.1.14` for an OpenStarbound build
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub notes: String,
+    /// How a Custom variant is launched, ignored by the other variants
+    #[serde(default)]
+    pub custom_launch: CustomLaunch,
 }
 
 impl Executable {
-    pub fn assets(&self) -> Option<PathBuf> {
-        self.assets.as_ref().map(|d| {
+    /// Guesses the variant and assets of a binary from the layout of the release it came from:
+    /// a folder holding `linux/<binary>` and an assets folder with the fork's pak.
+    pub fn from_binary(bin: PathBuf) -> Self {
+        let assets = release_assets(&bin);
+        let has_openstarbound_pak = assets
+            .as_ref()
+            .is_some_and(|dir| dir.join(OPENSTARBOUND_PAK_NAME).is_file());
+        let variant = if bin.file_name().is_some_and(|n| n == XSTARBOUND_BINARY_NAME) {
+            ExecutableVariant::XStarbound
+        } else if has_openstarbound_pak {
+            ExecutableVariant::OpenStarbound
+        } else {
+            ExecutableVariant::Vanilla
+        };
+        Self {
+            bin,
+            assets: assets
+                .filter(|_| variant != ExecutableVariant::Vanilla)
+                .into_iter()
+                .collect(),
+            variant,
+            ..Default::default()
+        }
+    }
+
+    /// Checks that the binary is an executable file and every asset folder exists
+    pub fn validate(&self) -> Result<(), ExecutableError> {
+        use std::os::unix::fs::PermissionsExt;
+        if self.bin.as_os_str().is_empty() {
+            return Err(ExecutableError::NoBinary);
+        }
+        let metadata = std::fs::metadata(&self.bin)
+            .map_err(|_| ExecutableError::MissingBinary(self.bin.clone()))?;
+        if !metadata.is_file() {
+            return Err(ExecutableError::NotAFile(self.bin.clone()));
+        }
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(ExecutableError::NotExecutable(self.bin.clone()));
+        }
+        match self.assets().find(|dir| !dir.is_dir()) {
+            Some(missing) => Err(ExecutableError::MissingAssets(missing)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn launch_strategy(&self) -> Box<dyn LaunchStrategy> {
+        match self.variant {
+            ExecutableVariant::XStarbound => Box::new(launch_strategy::XStarbound),
+            ExecutableVariant::OpenStarbound => Box::new(launch_strategy::OpenStarbound),
+            ExecutableVariant::Vanilla => Box::new(launch_strategy::Vanilla),
+            ExecutableVariant::Custom => Box::new(self.custom_launch.clone()),
+        }
+    }
+
+    /// Game version the executable is known to target, None for vanilla binaries which always
+    /// match their own install
+    pub fn target_version(&self) -> Option<&str> {
+        self.target_version
+            .as_deref()
+            .or(self.launch_strategy().target_version())
+    }
+
+    pub fn assets(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.assets.iter().map(|d| {
             if d.is_relative() {
                 self.bin
                     .parent()
@@ -62,3 +175,53 @@ impl Executable {
         })
     }
 }
+
+/// The folder next to a binary's `linux` folder holding paks other than the vanilla assets
+fn release_assets(bin: &Path) -> Option<PathBuf> {
+    let release = bin.parent()?.parent()?;
+    let has_own_pak = |dir: &Path| -> bool {
+        dir.read_dir().is_ok_and(|entries| {
+            entries.filter_map(Result::ok).any(|entry| {
+                let path = entry.path();
+                path.extension().is_some_and(|ext| ext == "pak")
+                    && path
+                        .file_name()
+                        .is_some_and(|name| name != VANILLA_PAK_NAME)
+            })
+        })
+    };
+    release
+        .read_dir()
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|dir| dir.is_dir() && has_own_pak(dir))
+}
+
+/// Looks for Starbound client binaries in the `linux` folder of each root, and of the folders up
+/// to two levels below it, e.g. `~/Downloads/OpenStarbound/client_distribution/linux/starbound`.
+pub async fn detect_executables(roots: Vec<PathBuf>) -> Vec<Executable> {
+    fn search(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+        let linux = dir.join("linux");
+        for name in [STARBOUND_BINARY_NAME, XSTARBOUND_BINARY_NAME] {
+            let bin = linux.join(name);
+            if bin.is_file() && !found.contains(&bin) {
+                found.push(bin);
+            }
+        }
+        let Some(entries) = dir.read_dir().ok().filter(|_| depth > 0) else {
+            return;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden && entry.path().is_dir() {
+                search(&entry.path(), depth - 1, found);
+            }
+        }
+    }
+    let mut binaries = Vec::new();
+    for root in roots {
+        search(&root, 2, &mut binaries);
+    }
+    binaries.into_iter().map(Executable::from_binary).collect()
+}
@@ -1,140 +1,251 @@
 use serde_json::json;
-use std::{path::PathBuf, process::Stdio};
-
-use crate::{executable::Executable, profile::Profile, STARBOUND_BOOT_CONFIG_NAME};
-
-const OS_LD_LIBRARY_NAME: &str = "LD_LIBRARY_PATH";
-
-#[derive(Debug, Copy, Clone)]
+use std::{
+    os::unix::process::ExitStatusExt,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Instant, SystemTime},
+};
+
+use crate::{
+    history::LaunchRecord,
+    launch_plan::{LaunchPlan, LinkedMods},
+    launch_strategy::{LaunchStrategy, UgcHandling},
+    profile::{self, Profile, ProfileId},
+    profile_lock::ProfileLock,
+    PROFILE_LOCK_NAME, STARBOUND_BOOT_CONFIG_NAME,
+};
+
+#[derive(Debug, Clone)]
 pub enum SBILaunchStatus {
-    Success,
+    /// The game ran and has exited
+    Exited {
+        profile: ProfileId,
+        record: LaunchRecord,
+        /// Whether it was a test launch (safe mode, a bisect round) rather than the profile
+        /// being played
+        test_launch: bool,
+    },
     Failure,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct SBILaunchSettings {
     pub close_on_launch: bool,
+    /// Replace sbi with a steam-wrapped launch instead of spawning it. Only for an sbi without a
+    /// window, started by steam to launch a single profile.
+    pub replace_process: bool,
 }
 
-pub async fn write_init_config(
+/// Asset folders of a launch in load order: the vanilla assets, the executable's own, then, with
+/// `profile_mods`, the profile's additional assets and the linked mods folder.
+pub fn asset_directories(
     profile: &Profile,
-    vanilla_mods: Option<PathBuf>,
+    vanilla_mods: Option<&Path>,
     vanilla_assets: PathBuf,
-    executable_assets: Option<PathBuf>,
-) -> anyhow::Result<()> {
-    let config_path = profile.path().join(STARBOUND_BOOT_CONFIG_NAME);
-    log::info!("Vanilla assets dir: {}", vanilla_assets.display());
-    log::info!("Vanilla mods dir: {:?}", vanilla_mods);
-    log::info!(
-        "Attempting to write sbinit.config to: {}",
-        config_path.display()
-    );
+    executable_assets: Vec<PathBuf>,
+    profile_mods: bool,
+) -> Vec<PathBuf> {
     let mut asset_directories: Vec<PathBuf> = vec![vanilla_assets];
     asset_directories.extend(executable_assets);
+    if !profile_mods {
+        return asset_directories;
+    }
     asset_directories.extend(profile.additional_assets());
     if let Some(p) = vanilla_mods.filter(|p| p.exists() && profile.link_mods()) {
-        asset_directories.push(p);
-    }
-    let storage_directory = profile.path();
-    if !storage_directory.exists() {
-        if let Err(e) = tokio::fs::create_dir_all(storage_directory).await {
-            log::error!("Failed to create missing storage directory: {e}");
-        }
+        asset_directories.push(p.to_path_buf());
     }
+    asset_directories
+}
 
+/// The `sbinit.config` of a launch, with whatever keys the executable's launch strategy adds
+pub fn boot_config(
+    asset_directories: Vec<PathBuf>,
+    storage_directory: &Path,
+    strategy: &dyn LaunchStrategy,
+) -> serde_json::Value {
     // TODO: Find a way to either configure these or determine a reasonable default
     let allow_admin_commands_from_anyone: bool = false;
     let anonymous_connections_are_admin: bool = false;
 
-    let json = json!({
+    let mut json = json!({
         "assetDirectories": asset_directories,
         "storageDirectory": storage_directory,
         "assetsSettings": {
             "pathIgnore": [],
             "digestIgnore": [ ".*" ]
         },
-        "includeUGC": false,
         "defaultConfiguration": {
             "allowAdminCommandsFromAnyone": allow_admin_commands_from_anyone,
             "anonymousConnectionsAreAdmin": anonymous_connections_are_admin,
         }
     });
+    if let Some(config) = json.as_object_mut() {
+        config.extend(strategy.boot_config());
+    }
+    json
+}
 
-    let bytes = serde_json::to_vec(&json)?;
-    tokio::fs::write(config_path, &bytes).await?;
+pub async fn write_init_config(plan: &LaunchPlan) -> anyhow::Result<()> {
+    log::info!(
+        "Attempting to write sbinit.config to: {}",
+        plan.boot_config_path.display()
+    );
+    let storage_directory = &plan.working_directory;
+    if !storage_directory.exists() {
+        if let Err(e) = tokio::fs::create_dir_all(storage_directory).await {
+            log::error!("Failed to create missing storage directory: {e}");
+        }
+    }
+    if let Some(source) = &plan.storage_copy_source {
+        log::info!(
+            "Copying {} to {}",
+            source.display(),
+            storage_directory.display()
+        );
+        copy_storage(source, storage_directory).await?;
+    }
+    if let Some(linked) = &plan.linked_mods {
+        link_mods(linked).await?;
+    }
+    let bytes = serde_json::to_vec(&plan.boot_config)?;
+    tokio::fs::write(&plan.boot_config_path, &bytes).await?;
     Ok(())
 }
 
-async fn lauch_game_inner(
-    executable: Executable,
-    profile: Profile,
-    launch_settings: SBILaunchSettings,
-) -> anyhow::Result<()> {
-    let executable_path = executable.bin;
-    let executable_folder = executable_path.parent().expect("").to_path_buf();
-    let instance_dir = profile.path();
-
-    let new_ld_path_var = {
-        let mut ld_paths = vec![executable_folder];
-        if let Ok(system_ld_path) = std::env::var(OS_LD_LIBRARY_NAME) {
-            ld_paths.extend(std::env::split_paths(&system_ld_path));
+/// Replaces the throwaway storage of a safe mode launch or bisect round with a fresh copy of the
+/// profile's storage. The copy is kept after the game exits, so its logs can be looked at.
+async fn copy_storage(source: &Path, copy: &Path) -> std::io::Result<()> {
+    // Our own lock file stays, the copy is already locked for this launch
+    let mut entries = tokio::fs::read_dir(copy).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_name() == PROFILE_LOCK_NAME {
+            continue;
         }
-        std::env::join_paths(ld_paths).ok()
-    };
+        if entry.file_type().await?.is_dir() {
+            tokio::fs::remove_dir_all(entry.path()).await?;
+        } else {
+            tokio::fs::remove_file(entry.path()).await?;
+        }
+    }
+    // A profile that was never launched has no storage yet
+    if !source.exists() {
+        return Ok(());
+    }
+    let mut entries = tokio::fs::read_dir(source).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        if name == PROFILE_LOCK_NAME || name == STARBOUND_BOOT_CONFIG_NAME {
+            continue;
+        }
+        if entry.file_type().await?.is_dir() {
+            profile::copy_dir_all(entry.path(), copy.join(name)).await?;
+        } else {
+            tokio::fs::copy(entry.path(), copy.join(name)).await?;
+        }
+    }
+    Ok(())
+}
 
-    std::env::set_current_dir(instance_dir)?;
+/// Fills the folder of a bisect round with links to its mods, numbered as two mods in different
+/// asset folders may share a name. The numbers are padded so the links sort in the mods' order.
+async fn link_mods(linked: &LinkedMods) -> std::io::Result<()> {
+    if linked.directory.exists() {
+        tokio::fs::remove_dir_all(&linked.directory).await?;
+    }
+    tokio::fs::create_dir_all(&linked.directory).await?;
+    for (i, source) in linked.mods.iter().enumerate() {
+        let name = source.file_name().unwrap_or_default().to_string_lossy();
+        tokio::fs::symlink(source, linked.directory.join(format!("{i:04}_{name}"))).await?;
+    }
+    Ok(())
+}
 
-    let mut command = tokio::process::Command::new(executable_path);
-    // command.current_dir(instance_dir);
-    let bootconfig = instance_dir
-        .join(STARBOUND_BOOT_CONFIG_NAME)
-        .display()
-        .to_string();
-    // let bootconfig = ["./", STARBOUND_BOOT_CONFIG_NAME].join("");
-    if let Some(path) = new_ld_path_var {
-        log::info!("Setting {OS_LD_LIBRARY_NAME} to {}", path.to_string_lossy());
-        command.env(OS_LD_LIBRARY_NAME, path);
-    }
-    command.args(["-bootconfig", &bootconfig]);
-    log::info!("Configuring launch parameters");
-    if let crate::executable::ExecutableVariant::XStarbound = executable.variant {
-        log::info!("Launching an XStarbound instance, adding `-noworkshop` to launch args");
-        command.arg("-noworkshop");
+async fn lauch_game_inner(
+    plan: LaunchPlan,
+    lock: ProfileLock,
+    launch_settings: SBILaunchSettings,
+) -> anyhow::Result<LaunchRecord> {
+    for warning in &plan.warnings {
+        log::warn!("{warning}");
+    }
+    match plan.ugc_handling {
+        UgcHandling::BootConfig => log::info!("Workshop content is disabled in sbinit.config"),
+        UgcHandling::Flag => log::info!("Workshop content is disabled by a launch argument"),
+        UgcHandling::Unsupported => {}
+    }
+    // The working directory is set on each child instead of sbi itself, so concurrent launches
+    // can't race each other
+    let mut command = plan.command();
+
+    if plan.is_steam_wrapped() && launch_settings.replace_process {
+        use std::os::unix::process::CommandExt;
+        // The game replaces sbi and keeps its pid, so the lock stays valid without a hand over
+        log::info!("Handing the launch over to steam's command: {command:?}");
+        // Only returns if the exec failed
+        let e = command.exec();
+        return Err(e.into());
     }
 
     // This little shit line caused me so
     // many issues with zombie processes.
     // Remember to unhook stdio for
     // children you give up
-
-    // This async thread is not necessary as we don't want to own children
-    // but this also causes no harm
     command.stdout(Stdio::null()).stderr(Stdio::null());
-    // tokio::task::spawn(async move {  });
-    let _child = command.spawn()?;
+    log::info!("Launching {command:?}");
+    let mut command = tokio::process::Command::from(command);
+    let started = SystemTime::now();
+    let timer = Instant::now();
+    let mut child = command.spawn()?;
+    if let Some(pid) = child.id() {
+        lock.hand_over(pid);
+    }
     if launch_settings.close_on_launch {
+        // Nobody is left to supervise the game, so the session won't show up in the history
         std::process::exit(0);
     }
-    Ok(())
+
+    // Supervise the game until it exits to record the session
+    let status = child.wait().await?;
+    log::info!("{} exited with {status}", plan.profile_name);
+    drop(lock);
+    Ok(LaunchRecord {
+        started: started
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        duration: timer.elapsed().as_secs(),
+        executable: plan.executable_name,
+        variant: plan.executable.variant,
+        exit_code: status.code(),
+        signal: status.signal(),
+    })
 }
 
-pub async fn launch_game(
-    executable: Executable,
-    profile: Profile,
-    vanilla_mods: Option<PathBuf>,
-    vanilla_assets: PathBuf,
-    launch_settings: SBILaunchSettings,
-) -> SBILaunchStatus {
-    if let Err(e) =
-        write_init_config(&profile, vanilla_mods, vanilla_assets, executable.assets()).await
-    {
+pub async fn launch_game(plan: LaunchPlan, launch_settings: SBILaunchSettings) -> SBILaunchStatus {
+    // Taken before writing sbinit.config, so a second launch can't rewrite it under a running game
+    let lock = match ProfileLock::acquire(&plan.working_directory, &plan.profile_name) {
+        Ok(lock) => lock,
+        Err(e) => {
+            log::error!("Cannot launch: {e}");
+            return SBILaunchStatus::Failure;
+        }
+    };
+    if let Err(e) = write_init_config(&plan).await {
         log::error!("Error writing sbinit.config: {e}");
         return SBILaunchStatus::Failure;
     }
 
-    if let Err(e) = lauch_game_inner(executable, profile, launch_settings).await {
-        log::error!("Error while launching executable: {e}");
-        return SBILaunchStatus::Failure;
+    let profile_id = plan.profile;
+    let test_launch = plan.is_test_launch();
+    match lauch_game_inner(plan, lock, launch_settings).await {
+        Ok(record) => SBILaunchStatus::Exited {
+            profile: profile_id,
+            record,
+            test_launch,
+        },
+        Err(e) => {
+            log::error!("Error while launching executable: {e}");
+            SBILaunchStatus::Failure
+        }
     }
-    SBILaunchStatus::Success
 }
@@ -0,0 +1,134 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{executable::ExecutableVariant, profile::ProfileId};
+
+static HISTORY_JSON_NAME: &str = "history.json";
+
+/// A single play session of a profile
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LaunchRecord {
+    /// Seconds since the unix epoch at which the game was started
+    pub started: u64,
+    /// Seconds the game was running for
+    pub duration: u64,
+    /// Name of the executable in the config at the time of launch
+    pub executable: String,
+    pub variant: ExecutableVariant,
+    /// Exit code of the game, None if it was killed by a signal
+    pub exit_code: Option<i32>,
+    /// Signal that killed the game, if any
+    pub signal: Option<i32>,
+}
+
+impl LaunchRecord {
+    pub fn started(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(self.started)
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration)
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// Human readable exit status, e.g. `exit code 1` or `signal 11`
+    pub fn exit_status(&self) -> String {
+        match (self.exit_code, self.signal) {
+            (Some(code), _) => format!("exit code {code}"),
+            (None, Some(signal)) => format!("signal {signal}"),
+            (None, None) => String::from("unknown exit"),
+        }
+    }
+}
+
+/// Launch history of every profile
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct LaunchHistory {
+    profiles: rustc_hash::FxHashMap<ProfileId, Vec<LaunchRecord>>,
+}
+
+impl LaunchHistory {
+    /// Sessions of a profile, oldest first
+    pub fn records(&self, profile: ProfileId) -> &[LaunchRecord] {
+        self.profiles
+            .get(&profile)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    pub fn add(&mut self, profile: ProfileId, record: LaunchRecord) {
+        self.profiles.entry(profile).or_default().push(record);
+    }
+
+    pub fn total_playtime(&self, profile: ProfileId) -> Duration {
+        self.records(profile)
+            .iter()
+            .map(LaunchRecord::duration)
+            .sum()
+    }
+
+    pub fn last_played(&self, profile: ProfileId) -> Option<SystemTime> {
+        self.records(profile).last().map(LaunchRecord::started)
+    }
+}
+
+/// Load launch history from disk at `dir/history.json`
+/// On error, returns an empty history
+pub async fn load_history(dir: PathBuf) -> LaunchHistory {
+    let path = dir.join(HISTORY_JSON_NAME);
+    if !path.exists() {
+        return LaunchHistory::default();
+    }
+    match tokio::fs::read(&path)
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?))
+    {
+        Ok(history) => history,
+        Err(e) => {
+            log::error!("Error reading launch history: {e}");
+            LaunchHistory::default()
+        }
+    }
+}
+
+/// Write [history](crate::history::LaunchHistory) to disk at `dir/history.json`
+pub async fn write_history(dir: PathBuf, history: LaunchHistory) -> anyhow::Result<()> {
+    let bytes = serde_json::to_vec(&history)?;
+    tokio::fs::write(dir.join(HISTORY_JSON_NAME), &bytes).await?;
+    Ok(())
+}
+
+/// Formats a duration as hours and minutes, e.g. `12h 5m`
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+/// Formats a point in time relative to now, e.g. `3 days ago`
+pub fn format_time_ago(time: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+    let (amount, unit) = match seconds {
+        s if s < 60 => return String::from("just now"),
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 60 * 60 * 24 => (s / (60 * 60), "hour"),
+        s if s < 60 * 60 * 24 * 30 => (s / (60 * 60 * 24), "day"),
+        s if s < 60 * 60 * 24 * 365 => (s / (60 * 60 * 24 * 30), "month"),
+        s => (s / (60 * 60 * 24 * 365), "year"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    format!("{amount} {unit}{plural} ago")
+}
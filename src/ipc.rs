@@ -0,0 +1,211 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::{SinkExt, Stream};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+/// How long either side waits for the other to finish writing its message
+const IPC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Requests a secondary sbi process forwards to the running instance.
+/// Sent as a single line of json over the socket.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum SBIRequest {
+    /// Bring the window of the running instance to the front
+    Focus,
+    /// Launch the profile with the given id or name
+    Launch {
+        profile: String,
+        /// Launch without the profile's mods, see [crate::launch_plan::LaunchPlan::new]
+        #[serde(default)]
+        safe_mode: bool,
+    },
+}
+
+/// Reply to a [SBIRequest], sent as a single line of json.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum SBIResponse {
+    Accepted,
+    Rejected(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IpcError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("The running sbi instance rejected the request: {0}")]
+    Rejected(String),
+    #[error("The running sbi instance closed the connection without responding")]
+    NoResponse,
+}
+
+pub enum SingleInstance {
+    /// No other sbi is running, this process now owns the socket
+    Primary(UnixListener),
+    /// Another sbi is running and listening on the other end
+    Secondary(UnixStream),
+}
+
+/// Connects to the sbi instance listening on `socket_path`, or becomes that instance.
+/// A socket file left behind by an sbi that did not exit cleanly is replaced.
+pub fn connect_or_bind(socket_path: &Path) -> std::io::Result<SingleInstance> {
+    match UnixStream::connect(socket_path) {
+        Ok(stream) => return Ok(SingleInstance::Secondary(stream)),
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            log::info!("Removing stale socket {}", socket_path.display());
+            std::fs::remove_file(socket_path)?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    UnixListener::bind(socket_path).map(SingleInstance::Primary)
+}
+
+/// Sends a request to the running instance and waits for it to be acknowledged.
+pub fn forward_request(mut stream: UnixStream, request: &SBIRequest) -> Result<(), IpcError> {
+    stream.set_read_timeout(Some(IPC_TIMEOUT))?;
+    stream.set_write_timeout(Some(IPC_TIMEOUT))?;
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    if response.is_empty() {
+        return Err(IpcError::NoResponse);
+    }
+    match serde_json::from_str(&response)? {
+        SBIResponse::Accepted => Ok(()),
+        SBIResponse::Rejected(reason) => Err(IpcError::Rejected(reason)),
+    }
+}
+
+/// Accepts connections on the listener, yielding every request forwarded by another sbi.
+pub fn listen(listener: Arc<UnixListener>) -> impl Stream<Item = SBIRequest> {
+    iced::stream::channel(8, move |mut output| async move {
+        let listener = match listener.try_clone().and_then(|l| {
+            l.set_nonblocking(true)?;
+            tokio::net::UnixListener::from_std(l)
+        }) {
+            Ok(l) => l,
+            Err(e) => {
+                log::error!("Failed to listen for other sbi instances: {e}");
+                return;
+            }
+        };
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    log::warn!("Failed to accept connection from another sbi instance: {e}");
+                    continue;
+                }
+            };
+            let (reader, mut writer) = stream.into_split();
+            let response = match tokio::time::timeout(IPC_TIMEOUT, read_request(reader)).await {
+                Ok(Ok(request)) => {
+                    log::info!("Received request from another sbi instance: {request:?}");
+                    if output.send(request).await.is_err() {
+                        break;
+                    }
+                    SBIResponse::Accepted
+                }
+                Ok(Err(e)) => SBIResponse::Rejected(e.to_string()),
+                Err(e) => SBIResponse::Rejected(e.to_string()),
+            };
+            if let Err(e) = write_response(&mut writer, &response).await {
+                log::warn!("Failed to respond to another sbi instance: {e}");
+            }
+        }
+    })
+}
+
+async fn read_request(reader: tokio::net::unix::OwnedReadHalf) -> Result<SBIRequest, IpcError> {
+    let mut line = String::new();
+    tokio::io::BufReader::new(reader)
+        .read_line(&mut line)
+        .await?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+async fn write_response(
+    writer: &mut tokio::net::unix::OwnedWriteHalf,
+    response: &SBIResponse,
+) -> Result<(), IpcError> {
+    let mut line = serde_json::to_vec(response)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_reach_the_primary_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("sbi.sock");
+        let SingleInstance::Primary(listener) = connect_or_bind(&socket).unwrap() else {
+            panic!("nothing else listens on a fresh socket");
+        };
+        let mut requests = std::pin::pin!(listen(Arc::new(listener)));
+
+        let sent = vec![
+            SBIRequest::Focus,
+            SBIRequest::Launch {
+                profile: String::from("modded"),
+                safe_mode: true,
+            },
+        ];
+        let client_socket = socket.clone();
+        let client_requests = sent.clone();
+        let mut client = tokio::task::spawn_blocking(move || {
+            client_requests.iter().try_for_each(|request| {
+                let SingleInstance::Secondary(stream) = connect_or_bind(&client_socket)? else {
+                    panic!("the primary instance is listening");
+                };
+                forward_request(stream, request)
+            })
+        });
+        // The listener only makes progress while its stream is polled
+        let mut received = Vec::new();
+        let result = loop {
+            tokio::select! {
+                result = &mut client => break result.unwrap(),
+                Some(request) = requests.next() => received.push(request),
+            }
+        };
+        result.unwrap();
+        while received.len() < sent.len() {
+            received.push(requests.next().await.unwrap());
+        }
+        assert_eq!(received, sent);
+    }
+
+    #[test]
+    fn stale_socket_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("sbi.sock");
+        // Dropping the listener leaves the socket file behind, like an sbi that crashed
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+
+        assert!(matches!(
+            connect_or_bind(&socket).unwrap(),
+            SingleInstance::Primary(_)
+        ));
+    }
+}
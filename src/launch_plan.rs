@@ -0,0 +1,413 @@
+//! Everything a launch does, worked out before anything is spawned.
+//! The launcher only carries out a [LaunchPlan], so the plan shown by `sbi launch --dry-run` and
+//! the Launch Plan menu is exactly what launching the profile would run.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Serialize;
+
+use crate::{
+    config::SBIConfig,
+    executable::Executable,
+    game_launcher,
+    launch_strategy::UgcHandling,
+    libraries::{self, OS_LD_LIBRARY_NAME},
+    profile::{Profile, ProfileId},
+    shortcuts,
+    steam_command::SteamCommand,
+    vanilla, SBIDirectories, STARBOUND_BOOT_CONFIG_NAME,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LaunchPlanError {
+    #[error("{0} has no selected executable")]
+    NoSelectedExecutable(String),
+    #[error("Executable {0} does not exist")]
+    UnknownExecutable(String),
+    #[error("{0} has no vanilla assets, set them up in the settings menu")]
+    NoVanillaAssets(String),
+    #[error("Failed to work out the launch plan: {0}")]
+    Interrupted(#[from] tokio::task::JoinError),
+}
+
+/// Which of the profile's mods a launch loads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchMode {
+    Normal,
+    /// Only the vanilla and the executable's own assets, on a throwaway copy of the profile's
+    /// storage, to tell whether a problem is caused by mods or by the executable
+    Safe,
+    /// Only the given mods of the profile, on the same throwaway storage, for a round of
+    /// [crate::bisect::Bisection]
+    Bisect(Vec<PathBuf>),
+}
+
+/// Mods symlinked into a folder that is loaded in place of the profile's asset folders
+#[derive(Serialize, Debug, Clone)]
+pub struct LinkedMods {
+    pub directory: PathBuf,
+    pub mods: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LaunchPlan {
+    pub profile: ProfileId,
+    pub profile_name: String,
+    pub executable_name: String,
+    pub executable: Executable,
+    /// The game runs in its storage folder, the profile's or a throwaway copy of it
+    pub working_directory: PathBuf,
+    /// Set unless the mode is normal: the profile's storage, copied into the working directory
+    /// before the game is started so the profile itself is never touched
+    pub storage_copy_source: Option<PathBuf>,
+    /// Set for bisect rounds
+    pub linked_mods: Option<LinkedMods>,
+    /// Arguments passed to the executable
+    pub args: Vec<String>,
+    /// Variables set on top of sbi's environment, besides the library search path
+    pub env: Vec<(String, String)>,
+    /// Folders the game's `LD_LIBRARY_PATH` is made of
+    pub library_search_path: Vec<PathBuf>,
+    /// Libraries of the executable that can't be found, or why they could not be checked
+    pub missing_libraries: Result<Vec<String>, String>,
+    pub ugc_handling: UgcHandling,
+    pub boot_config_path: PathBuf,
+    /// Contents of the `sbinit.config` written before the game is started
+    pub boot_config: serde_json::Value,
+    /// The full command line, through steam's wrappers if the launch is handed over to steam
+    pub command_line: Vec<String>,
+    pub warnings: Vec<String>,
+    #[serde(skip)]
+    steam_command: Option<SteamCommand>,
+}
+
+impl LaunchPlan {
+    /// Works out how `profile` would be launched with the current settings.
+    /// `game_version` is the version of the vanilla install the profile loads, if known.
+    pub fn new(
+        profile: &Profile,
+        config: &SBIConfig,
+        dirs: &SBIDirectories,
+        game_version: Option<&str>,
+        steam_command: Option<SteamCommand>,
+        mode: LaunchMode,
+    ) -> Result<Self, LaunchPlanError> {
+        let executable_name = profile
+            .selected_executable()
+            .ok_or_else(|| LaunchPlanError::NoSelectedExecutable(profile.name().to_string()))?;
+        let executable = config
+            .get_executable(executable_name)
+            .ok_or_else(|| LaunchPlanError::UnknownExecutable(executable_name.to_string()))?
+            .clone();
+        let (vanilla_assets, vanilla_mods) = vanilla_paths(profile, config, dirs)
+            .ok_or_else(|| LaunchPlanError::NoVanillaAssets(profile.name().to_string()))?;
+        let strategy = executable.launch_strategy();
+
+        let library_search_path =
+            libraries::library_search_path(&executable, profile.library_dirs());
+        let missing_libraries =
+            libraries::unresolved_libraries(&executable.bin, &library_search_path)
+                .map_err(|e| e.to_string());
+
+        let profile_mods = mode == LaunchMode::Normal;
+        let (working_directory, storage_copy_source) = if profile_mods {
+            (profile.path().to_path_buf(), None)
+        } else {
+            (
+                dirs.safe_mode().join(profile.id().to_string()),
+                Some(profile.path().to_path_buf()),
+            )
+        };
+        let linked_mods = match mode {
+            LaunchMode::Bisect(mods) => Some(LinkedMods {
+                directory: dirs.bisect().join(profile.id().to_string()),
+                mods,
+            }),
+            _ => None,
+        };
+        let boot_config_path = working_directory.join(STARBOUND_BOOT_CONFIG_NAME);
+        let mut args = vec![
+            String::from("-bootconfig"),
+            boot_config_path.display().to_string(),
+        ];
+        args.extend(strategy.args());
+        let mut asset_directories = game_launcher::asset_directories(
+            profile,
+            vanilla_mods.as_deref(),
+            vanilla_assets,
+            executable.assets().collect(),
+            profile_mods,
+        );
+        asset_directories.extend(linked_mods.as_ref().map(|linked| linked.directory.clone()));
+        let boot_config =
+            game_launcher::boot_config(asset_directories, &working_directory, strategy.as_ref());
+
+        let mut warnings: Vec<String> = match game_version {
+            Some(version) if !profile_mods => {
+                vanilla::executable_version_warning(version, &executable)
+                    .into_iter()
+                    .collect()
+            }
+            Some(version) => {
+                vanilla::version_warnings(version, &executable, profile, vanilla_mods.as_deref())
+            }
+            None => Vec::new(),
+        };
+        match &missing_libraries {
+            Ok(missing) if missing.is_empty() => {}
+            Ok(missing) => warnings.push(format!(
+                "{} links against libraries that can't be found, the game will likely fail to start: {}",
+                executable.bin.display(),
+                missing.join(", ")
+            )),
+            Err(e) => warnings.push(format!(
+                "Could not check the libraries of {}: {e}",
+                executable.bin.display()
+            )),
+        }
+        if strategy.ugc_handling() == UgcHandling::Unsupported {
+            warnings.push(format!(
+                "{} can't disable workshop content, subscribed workshop mods will be loaded",
+                executable.variant
+            ));
+        }
+
+        let mut plan = Self {
+            profile: profile.id(),
+            profile_name: profile.name().to_string(),
+            executable_name: executable_name.to_string(),
+            ugc_handling: strategy.ugc_handling(),
+            env: strategy.env(),
+            executable,
+            working_directory,
+            storage_copy_source,
+            linked_mods,
+            args,
+            library_search_path,
+            missing_libraries,
+            boot_config_path,
+            boot_config,
+            command_line: Vec::new(),
+            warnings,
+            steam_command,
+        };
+        let command = plan.command();
+        plan.command_line = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        Ok(plan)
+    }
+
+    /// [LaunchPlan::new] on a blocking thread, locating steam's runtime and reading the
+    /// executable's libraries takes a while
+    pub async fn new_task(
+        profile: Profile,
+        config: SBIConfig,
+        dirs: SBIDirectories,
+        game_version: Option<String>,
+        steam_command: Option<SteamCommand>,
+        mode: LaunchMode,
+    ) -> Result<Self, LaunchPlanError> {
+        tokio::task::spawn_blocking(move || {
+            Self::new(
+                &profile,
+                &config,
+                &dirs,
+                game_version.as_deref(),
+                steam_command,
+                mode,
+            )
+        })
+        .await?
+    }
+
+    /// Whether the launch leaves out mods to test the profile (safe mode, a bisect round), which
+    /// runs on a copy of its storage
+    pub fn is_test_launch(&self) -> bool {
+        self.storage_copy_source.is_some()
+    }
+
+    /// Whether the game is started through steam's command instead of directly
+    pub fn is_steam_wrapped(&self) -> bool {
+        self.steam_command.is_some()
+    }
+
+    /// The command starting the game, with its working directory and environment
+    pub fn command(&self) -> Command {
+        let mut command = match &self.steam_command {
+            Some(steam_command) => steam_command.rewrap(&self.executable.bin, &self.args),
+            None => {
+                let mut command = Command::new(&self.executable.bin);
+                command.args(&self.args);
+                command
+            }
+        };
+        command.current_dir(&self.working_directory);
+        if let Ok(path) = std::env::join_paths(&self.library_search_path) {
+            command.env(OS_LD_LIBRARY_NAME, path);
+        }
+        command.envs(self.env.iter().map(|(key, value)| (key, value)));
+        command
+    }
+
+    /// Asset folders in the order the game loads them, as written to `sbinit.config`
+    pub fn asset_directories(&self) -> Vec<PathBuf> {
+        self.boot_config["assetDirectories"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|dir| dir.as_str().map(PathBuf::from))
+            .collect()
+    }
+
+    /// The asset folders after the vanilla and executable assets, which hold the profile's mods
+    pub fn mod_directories(&self) -> Vec<PathBuf> {
+        let mut directories = self.asset_directories();
+        directories.drain(..(1 + self.executable.assets().count()).min(directories.len()));
+        directories
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .unwrap_or_else(|e| format!("Failed to serialize the launch plan: {e}"))
+    }
+}
+
+/// A readable report of the plan, for the log, `--dry-run` and the Launch Plan menu
+impl std::fmt::Display for LaunchPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Profile: {} ({})", self.profile_name, self.profile)?;
+        writeln!(
+            f,
+            "Executable: {} ({}, {})",
+            self.executable_name,
+            self.executable.variant,
+            self.executable.bin.display()
+        )?;
+        writeln!(f, "Working directory: {}", self.working_directory.display())?;
+        match (&self.storage_copy_source, &self.linked_mods) {
+            (Some(source), Some(linked)) => writeln!(
+                f,
+                "Bisect round: only {} of the profile's mods are loaded, the storage is a copy of {}",
+                linked.mods.len(),
+                source.display()
+            )?,
+            (Some(source), None) => writeln!(
+                f,
+                "Safe mode: mods are left out, the storage is a copy of {}",
+                source.display()
+            )?,
+            _ => {}
+        }
+        if self.is_steam_wrapped() {
+            writeln!(f, "Launched through steam's command")?;
+        }
+        writeln!(f, "Command: {}", shell_words(&self.command_line))?;
+        writeln!(f, "Environment:")?;
+        if let Ok(path) = std::env::join_paths(&self.library_search_path) {
+            writeln!(f, "  {OS_LD_LIBRARY_NAME}={}", path.to_string_lossy())?;
+        }
+        for (key, value) in &self.env {
+            writeln!(f, "  {key}={value}")?;
+        }
+        writeln!(
+            f,
+            "Workshop content: {}",
+            match self.ugc_handling {
+                UgcHandling::BootConfig => "disabled in sbinit.config",
+                UgcHandling::Flag => "disabled by a launch argument",
+                UgcHandling::Unsupported => "loaded",
+            }
+        )?;
+        match &self.missing_libraries {
+            Ok(missing) if missing.is_empty() => writeln!(f, "Missing libraries: none")?,
+            Ok(missing) => writeln!(f, "Missing libraries: {}", missing.join(", "))?,
+            Err(e) => writeln!(f, "Missing libraries: could not check, {e}")?,
+        }
+        if !self.warnings.is_empty() {
+            writeln!(f, "Warnings:")?;
+            for warning in &self.warnings {
+                writeln!(f, "  - {warning}")?;
+            }
+        }
+        writeln!(f, "{}:", self.boot_config_path.display())?;
+        let boot_config =
+            serde_json::to_string_pretty(&self.boot_config).map_err(|_| std::fmt::Error)?;
+        write!(f, "{boot_config}")
+    }
+}
+
+/// Joins a command line, quoting the arguments a shell would not take as they are
+fn shell_words(args: &[String]) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    args.iter()
+        .map(|arg| {
+            if arg.is_empty() || !arg.chars().all(is_plain) {
+                shortcuts::quote_shell_arg(arg)
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Assets and mods folders of the vanilla install the profile loads.
+/// Falls back to the default install if the profile's install was removed, None if there is
+/// no default install either.
+pub fn vanilla_paths(
+    profile: &Profile,
+    config: &SBIConfig,
+    dirs: &SBIDirectories,
+) -> Option<(PathBuf, Option<PathBuf>)> {
+    match profile
+        .vanilla_install()
+        .and_then(|name| config.vanilla_installs.get(name))
+    {
+        Some(install) => Some((install.assets(), Some(install.mods()))),
+        None => dirs
+            .vanilla()
+            .map(|paths| (paths.assets.clone(), paths.mods.clone())),
+    }
+}
+
+/// Game folder of the vanilla install the profile loads, to detect its version from
+pub fn vanilla_directory<'a>(
+    profile: &Profile,
+    config: &'a SBIConfig,
+    dirs: &'a SBIDirectories,
+) -> Option<&'a Path> {
+    match profile
+        .vanilla_install()
+        .and_then(|name| config.vanilla_installs.get(name))
+    {
+        Some(install) => Some(&install.directory),
+        None => dirs.vanilla_directory(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_words_quote_what_a_shell_would_change() {
+        let args = [
+            "/games/starbound/starbound",
+            "-bootconfig",
+            "/home/me/Saved Games/sbinit.config",
+            "it's",
+            "$HOME",
+            "",
+        ]
+        .map(String::from);
+        assert_eq!(
+            shell_words(&args),
+            r#"/games/starbound/starbound -bootconfig '/home/me/Saved Games/sbinit.config' 'it'\''s' '$HOME' ''"#
+        );
+    }
+}
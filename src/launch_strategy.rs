@@ -0,0 +1,169 @@
+//! How each variant of the game is launched.
+//! The launcher writes the common `sbinit.config` keys and spawns the binary, everything that
+//! differs between forks (extra boot config keys, arguments, environment and how workshop content
+//! is kept out of a profile) comes from the executable's [LaunchStrategy].
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Game version the supported forks are built against
+const FORK_TARGET_VERSION: &str = "1.4.4";
+
+/// How a variant keeps steam workshop (UGC) content from leaking into a profile
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UgcHandling {
+    /// `"includeUGC": false` in the boot config
+    BootConfig,
+    /// A command line flag such as `-noworkshop`
+    Flag,
+    /// The variant always loads subscribed workshop items
+    Unsupported,
+}
+
+pub trait LaunchStrategy: Send + Sync {
+    /// Keys merged into the generated `sbinit.config`, overriding the common ones
+    fn boot_config(&self) -> Map<String, Value> {
+        Map::new()
+    }
+
+    /// Arguments passed after `-bootconfig`
+    fn args(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Environment variables set for the game
+    fn env(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn ugc_handling(&self) -> UgcHandling;
+
+    /// Game version the variant is built against, None if it always matches its install
+    fn target_version(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+pub struct Vanilla;
+
+impl LaunchStrategy for Vanilla {
+    fn ugc_handling(&self) -> UgcHandling {
+        UgcHandling::Unsupported
+    }
+}
+
+pub struct OpenStarbound;
+
+impl LaunchStrategy for OpenStarbound {
+    fn boot_config(&self) -> Map<String, Value> {
+        Map::from_iter([(String::from("includeUGC"), Value::Bool(false))])
+    }
+
+    fn ugc_handling(&self) -> UgcHandling {
+        UgcHandling::BootConfig
+    }
+
+    fn target_version(&self) -> Option<&'static str> {
+        Some(FORK_TARGET_VERSION)
+    }
+}
+
+pub struct XStarbound;
+
+impl LaunchStrategy for XStarbound {
+    fn args(&self) -> Vec<String> {
+        vec![String::from("-noworkshop")]
+    }
+
+    fn ugc_handling(&self) -> UgcHandling {
+        UgcHandling::Flag
+    }
+
+    fn target_version(&self) -> Option<&'static str> {
+        Some(FORK_TARGET_VERSION)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CustomLaunchError {
+    #[error("Environment variable `{0}` is not of the form KEY=value")]
+    InvalidEnv(String),
+    #[error("Boot config is not valid json: {0}")]
+    InvalidBootConfig(#[from] serde_json::Error),
+    #[error("Boot config must be a json object")]
+    BootConfigNotAnObject,
+}
+
+/// A user-defined launch for forks sbi doesn't know about
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CustomLaunch {
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    /// Json object merged into the boot config, kept as text so it round-trips unchanged
+    pub boot_config: String,
+}
+
+impl CustomLaunch {
+    /// Parses whitespace separated arguments, whitespace separated `KEY=value` pairs and a json
+    /// object, as entered in the settings menu
+    pub fn parse(args: &str, env: &str, boot_config: &str) -> Result<Self, CustomLaunchError> {
+        let env = env
+            .split_whitespace()
+            .map(|pair| {
+                pair.split_once('=')
+                    .filter(|(key, _)| !key.is_empty())
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .ok_or_else(|| CustomLaunchError::InvalidEnv(pair.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        let boot_config = boot_config.trim().to_string();
+        if !boot_config.is_empty() && !serde_json::from_str::<Value>(&boot_config)?.is_object() {
+            return Err(CustomLaunchError::BootConfigNotAnObject);
+        }
+        Ok(Self {
+            args: args.split_whitespace().map(str::to_string).collect(),
+            env,
+            boot_config,
+        })
+    }
+
+    pub fn args_text(&self) -> String {
+        self.args.join(" ")
+    }
+
+    pub fn env_text(&self) -> String {
+        self.env
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl LaunchStrategy for CustomLaunch {
+    fn boot_config(&self) -> Map<String, Value> {
+        match serde_json::from_str(&self.boot_config) {
+            Ok(Value::Object(map)) => map,
+            _ => Map::new(),
+        }
+    }
+
+    fn args(&self) -> Vec<String> {
+        self.args.clone()
+    }
+
+    fn env(&self) -> Vec<(String, String)> {
+        self.env.clone()
+    }
+
+    fn ugc_handling(&self) -> UgcHandling {
+        let config = self.boot_config();
+        if config.get("includeUGC") == Some(&Value::Bool(false)) {
+            UgcHandling::BootConfig
+        } else if self.args.iter().any(|arg| arg == "-noworkshop") {
+            UgcHandling::Flag
+        } else {
+            UgcHandling::Unsupported
+        }
+    }
+}
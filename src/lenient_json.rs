@@ -0,0 +1,442 @@
+//! The JSON dialect of Starbound's assets.
+//! The game's parser accepts `//` and `/* */` comments, trailing commas and raw line breaks in
+//! strings, which mods rely on and `serde_json` rejects. Errors carry the line and column they were
+//! found at.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Number, Value};
+
+/// Objects and lists nested deeper than this are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("line {line}, column {column}: {message}")]
+pub struct JsonError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Line of every value of a document, by JSON pointer
+pub type Lines = BTreeMap<String, usize>;
+
+pub fn from_slice(bytes: &[u8]) -> Result<Value, JsonError> {
+    parse(bytes, false).map(|(value, _)| value)
+}
+
+/// Parses a document and records the line each of its values starts on
+pub fn from_slice_with_lines(bytes: &[u8]) -> Result<(Value, Lines), JsonError> {
+    parse(bytes, true)
+}
+
+fn parse(bytes: &[u8], record_lines: bool) -> Result<(Value, Lines), JsonError> {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => {
+            let valid = &bytes[..e.valid_up_to()];
+            let line = valid.iter().filter(|&&byte| byte == b'\n').count() + 1;
+            let line_start = valid
+                .iter()
+                .rposition(|&byte| byte == b'\n')
+                .map_or(0, |i| i + 1);
+            return Err(JsonError {
+                line,
+                column: String::from_utf8_lossy(&valid[line_start..])
+                    .chars()
+                    .count()
+                    + 1,
+                message: String::from("invalid UTF-8"),
+            });
+        }
+    };
+    let mut parser = Parser {
+        text: text.strip_prefix('\u{feff}').unwrap_or(text),
+        pos: 0,
+        line: 1,
+        line_start: 0,
+        lines: record_lines.then(Lines::new),
+    };
+    parser.skip_whitespace()?;
+    let value = parser.value(&mut String::new(), 0)?;
+    parser.skip_whitespace()?;
+    if parser.peek().is_some() {
+        return Err(parser.unexpected("the end of the document"));
+    }
+    Ok((value, parser.lines.unwrap_or_default()))
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+    /// Position the current line starts at, to count columns from
+    line_start: usize,
+    lines: Option<Lines>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.line_start = self.pos;
+        }
+        Some(byte)
+    }
+
+    fn error(&self, message: impl Into<String>) -> JsonError {
+        let column = self
+            .text
+            .get(self.line_start..self.pos)
+            .map_or(self.pos - self.line_start, |line| line.chars().count());
+        JsonError {
+            line: self.line,
+            column: column + 1,
+            message: message.into(),
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> JsonError {
+        match self
+            .text
+            .get(self.pos..)
+            .and_then(|rest| rest.chars().next())
+        {
+            Some(found) => self.error(format!("expected {expected}, found {found:?}")),
+            None => self.error(format!("expected {expected}, found the end of the file")),
+        }
+    }
+
+    /// Skips whitespace and comments
+    fn skip_whitespace(&mut self) -> Result<(), JsonError> {
+        loop {
+            match (self.peek(), self.text.as_bytes().get(self.pos + 1)) {
+                (Some(b' ' | b'\t' | b'\n' | b'\r'), _) => {
+                    self.bump();
+                }
+                (Some(b'/'), Some(b'/')) => while self.bump().is_some_and(|byte| byte != b'\n') {},
+                (Some(b'/'), Some(b'*')) => {
+                    let unterminated = self.error("unterminated comment");
+                    self.bump();
+                    self.bump();
+                    loop {
+                        match self.bump() {
+                            Some(b'*') if self.peek() == Some(b'/') => {
+                                self.bump();
+                                break;
+                            }
+                            Some(_) => {}
+                            None => return Err(unterminated),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn value(&mut self, pointer: &mut String, depth: usize) -> Result<Value, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        if let Some(lines) = &mut self.lines {
+            lines.insert(pointer.clone(), self.line);
+        }
+        match self.peek() {
+            Some(b'{') => self.object(pointer, depth),
+            Some(b'[') => self.array(pointer, depth),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            _ => Err(self.unexpected("a value")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, JsonError> {
+        if !self.text[self.pos..].starts_with(word) {
+            return Err(self.unexpected("a value"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn object(&mut self, pointer: &mut String, depth: usize) -> Result<Value, JsonError> {
+        self.bump();
+        let mut map = Map::new();
+        loop {
+            self.skip_whitespace()?;
+            // Also closes the object after a trailing comma
+            if self.peek() == Some(b'}') {
+                self.bump();
+                return Ok(Value::Object(map));
+            }
+            if self.peek() != Some(b'"') {
+                return Err(self.unexpected("a key or '}'"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace()?;
+            if self.peek() != Some(b':') {
+                return Err(self.unexpected("':'"));
+            }
+            self.bump();
+            self.skip_whitespace()?;
+            let len = pointer.len();
+            pointer.push('/');
+            pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+            let value = self.value(pointer, depth + 1)?;
+            pointer.truncate(len);
+            map.insert(key, value);
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(b',') => {
+                    self.bump();
+                }
+                Some(b'}') => {}
+                _ => return Err(self.unexpected("',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self, pointer: &mut String, depth: usize) -> Result<Value, JsonError> {
+        self.bump();
+        let mut array = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            // Also closes the list after a trailing comma
+            if self.peek() == Some(b']') {
+                self.bump();
+                return Ok(Value::Array(array));
+            }
+            let len = pointer.len();
+            pointer.push('/');
+            pointer.push_str(&array.len().to_string());
+            let value = self.value(pointer, depth + 1)?;
+            pointer.truncate(len);
+            array.push(value);
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(b',') => {
+                    self.bump();
+                }
+                Some(b']') => {}
+                _ => return Err(self.unexpected("',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.bump();
+        let mut bytes = Vec::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    let escaped = match self.bump() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                Some(byte) => bytes.push(byte),
+            }
+        }
+        // Only whole characters of the text and escapes were copied
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    fn hex_escape(&mut self) -> Result<u32, JsonError> {
+        let code = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let mut code = self.hex_escape()?;
+        // Characters outside the basic plane are escaped as a pair of surrogates
+        if (0xd800..0xdc00).contains(&code) && self.text[self.pos..].starts_with("\\u") {
+            self.pos += 2;
+            let low = self.hex_escape()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("invalid unicode escape"));
+            }
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+        }
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.pos;
+        self.pos += 1;
+        while matches!(
+            self.peek(),
+            Some(b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-')
+        ) {
+            self.pos += 1;
+        }
+        let text = self.text;
+        let text = &text[start..self.pos];
+        let number = match (text.parse::<i64>(), text.parse::<u64>()) {
+            (Ok(n), _) => Some(Number::from(n)),
+            (_, Ok(n)) => Some(Number::from(n)),
+            _ => text.parse::<f64>().ok().and_then(Number::from_f64),
+        };
+        number.map(Value::Number).ok_or_else(|| {
+            self.pos = start;
+            self.error(format!("invalid number {text}"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse_str(text: &str) -> Result<Value, JsonError> {
+        from_slice(text.as_bytes())
+    }
+
+    fn error_at(text: &str) -> (usize, usize) {
+        let e = parse_str(text).unwrap_err();
+        (e.line, e.column)
+    }
+
+    #[test]
+    fn accepts_comments_and_trailing_commas() {
+        let text = r#"
+            // The game's own files are full of these
+            {
+              "name": "apple", // inline
+              /* a block
+                 over lines */ "list": [1, 2, 3,],
+              "nested": {"a": /* here too */ null,},
+            }
+            // after the document
+        "#;
+        assert_eq!(
+            parse_str(text).unwrap(),
+            json!({"name": "apple", "list": [1, 2, 3], "nested": {"a": null}})
+        );
+        assert_eq!(parse_str("[/**/]").unwrap(), json!([]));
+        assert_eq!(parse_str("\u{feff}{}").unwrap(), json!({}));
+    }
+
+    #[test]
+    fn parses_strings() {
+        assert_eq!(
+            parse_str(r#""a\"b\\c\/d\b\f\n\r\t\u00e9""#).unwrap(),
+            json!("a\"b\\c/d\u{8}\u{c}\n\r\té")
+        );
+        // Raw line breaks are kept
+        assert_eq!(parse_str("\"two\nlines\"").unwrap(), json!("two\nlines"));
+        assert_eq!(parse_str("\"ünï\"").unwrap(), json!("ünï"));
+    }
+
+    #[test]
+    fn joins_surrogate_pairs() {
+        assert_eq!(parse_str(r#""\ud83d\ude00""#).unwrap(), json!("😀"));
+        assert_eq!(parse_str(r#""\uD834\uDD1E!""#).unwrap(), json!("𝄞!"));
+        for broken in [
+            r#""\ud83d""#,
+            r#""\ud83dx""#,
+            r#""\ud83d\u0041""#,
+            r#""\ude00""#,
+        ] {
+            let e = parse_str(broken).unwrap_err();
+            assert_eq!(e.message, "invalid unicode escape", "{broken}");
+        }
+        assert!(parse_str(r#""\u12""#).is_err());
+    }
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(
+            parse_str("[0, -12, 1.5, 2e3, -1E-2, 18446744073709551615]").unwrap(),
+            json!([0, -12, 1.5, 2000.0, -0.01, u64::MAX])
+        );
+        let e = parse_str("[1, 1.2.3]").unwrap_err();
+        assert_eq!((e.line, e.column), (1, 5));
+        assert_eq!(e.message, "invalid number 1.2.3");
+    }
+
+    #[test]
+    fn reports_where_errors_are() {
+        assert_eq!(error_at("{\n  \"a\": 1\n  \"b\": 2\n}"), (3, 3));
+        assert_eq!(error_at("[1, 2"), (1, 6));
+        assert_eq!(error_at("{\"a\" 1}"), (1, 6));
+        assert_eq!(error_at("[tru]"), (1, 2));
+        assert_eq!(error_at("{} {}"), (1, 4));
+        assert_eq!(error_at(""), (1, 1));
+        // Columns count characters, not bytes
+        assert_eq!(error_at("[\"ünï\", x]"), (1, 9));
+        // Unterminated comments are reported where they start
+        assert_eq!(error_at("{\n  /* never closed\n"), (2, 3));
+        assert_eq!(error_at("\"open\n"), (2, 1));
+        assert_eq!(error_at("[\"\\q\"]"), (1, 5));
+
+        let e = from_slice(b"{\n  \"a\": \"\xff\"}").unwrap_err();
+        assert_eq!(
+            (e.line, e.column, e.message.as_str()),
+            (2, 9, "invalid UTF-8")
+        );
+        let e = parse_str("[1,\n 2 3]").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 2, column 4: expected ',' or ']', found '3'"
+        );
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let deep = "[".repeat(MAX_DEPTH + 2);
+        assert_eq!(parse_str(&deep).unwrap_err().message, "nested too deeply");
+        let ok = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(parse_str(&ok).is_ok());
+    }
+
+    #[test]
+    fn records_lines_by_pointer() {
+        let text =
+            "{\n  \"items\": [\n    \"a\",\n    {\"a/b~c\": 1}\n  ],\n  \"name\":\n    \"x\"\n}";
+        let (_, lines) = from_slice_with_lines(text.as_bytes()).unwrap();
+        let expected = [
+            ("", 1),
+            ("/items", 2),
+            ("/items/0", 3),
+            ("/items/1", 4),
+            ("/items/1/a~1b~0c", 4),
+            ("/name", 7),
+        ];
+        assert_eq!(
+            lines,
+            expected
+                .into_iter()
+                .map(|(pointer, line)| (pointer.to_string(), line))
+                .collect::<Lines>()
+        );
+        assert!(from_slice(text.as_bytes()).is_ok());
+    }
+}
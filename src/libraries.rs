@@ -0,0 +1,386 @@
+//! Shared library resolution for executables.
+//! Builds the `LD_LIBRARY_PATH` a game is launched with and checks the `DT_NEEDED` entries of
+//! its ELF binary against it, so missing libraries (`libsteam_api.so`, SDL2) are reported
+//! before the game fails to start.
+
+use std::{
+    fs::File,
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+};
+
+use crate::executable::Executable;
+
+pub const OS_LD_LIBRARY_NAME: &str = "LD_LIBRARY_PATH";
+
+/// Library folders of the steam runtime, relative to the steam install
+const STEAM_RUNTIME_LIBRARY_DIRS: [&str; 5] = [
+    "ubuntu12_32/steam-runtime/pinned_libs_64",
+    "ubuntu12_32/steam-runtime/lib/x86_64-linux-gnu",
+    "ubuntu12_32/steam-runtime/usr/lib/x86_64-linux-gnu",
+    "ubuntu12_32/steam-runtime/amd64/lib/x86_64-linux-gnu",
+    "ubuntu12_32/steam-runtime/amd64/usr/lib/x86_64-linux-gnu",
+];
+/// Folders the dynamic linker searches after `LD_LIBRARY_PATH` and its cache
+const SYSTEM_LIBRARY_DIRS: [&str; 6] = [
+    "/lib",
+    "/lib64",
+    "/usr/lib",
+    "/usr/lib64",
+    "/lib/x86_64-linux-gnu",
+    "/usr/lib/x86_64-linux-gnu",
+];
+static LD_SO_CONF_PATH: &str = "/etc/ld.so.conf";
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELF_CLASS_32: u8 = 1;
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_LITTLE_ENDIAN: u8 = 1;
+const SHT_DYNAMIC: u32 = 6;
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_RPATH: u64 = 15;
+const DT_RUNPATH: u64 = 29;
+/// String tables larger than this are taken for a malformed binary, the game's is a few kB
+const MAX_STRING_TABLE_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ElfError {
+    #[error("Error reading binary: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not an ELF binary")]
+    NotElf,
+    #[error("Only little endian ELF binaries are supported")]
+    UnsupportedEndianness,
+    #[error("Truncated or malformed ELF binary")]
+    Malformed,
+    #[error("Binary is statically linked")]
+    NoDynamicSection,
+}
+
+/// Dynamic linking information of an ELF binary
+#[derive(Debug, Clone, Default)]
+pub struct DynamicInfo {
+    /// Libraries the binary links against, as written in `DT_NEEDED`
+    pub needed: Vec<String>,
+    /// Folders from `DT_RPATH` and `DT_RUNPATH`, with `$ORIGIN` expanded
+    pub search_paths: Vec<PathBuf>,
+}
+
+/// The folders to put on `LD_LIBRARY_PATH` when launching `executable` with a profile's extra
+/// library folders, in search order: the executable's folder, the executable's and profile's
+/// extra folders, the steam runtime if enabled, then whatever the environment already had.
+pub fn library_search_path(executable: &Executable, profile_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = executable
+        .bin
+        .parent()
+        .map(Path::to_path_buf)
+        .into_iter()
+        .collect();
+    dirs.extend(executable.library_dirs.iter().cloned());
+    dirs.extend(profile_dirs.iter().cloned());
+    if executable.use_steam_runtime {
+        dirs.extend(steam_runtime_library_dirs());
+    }
+    if let Ok(system_ld_path) = std::env::var(OS_LD_LIBRARY_NAME) {
+        dirs.extend(std::env::split_paths(&system_ld_path));
+    }
+    dirs
+}
+
+/// Library folders of the steam runtime shipped with the local steam install
+pub fn steam_runtime_library_dirs() -> Vec<PathBuf> {
+    match steamlocate::SteamDir::locate() {
+        Ok(steam) => STEAM_RUNTIME_LIBRARY_DIRS
+            .iter()
+            .map(|dir| steam.path().join(dir))
+            .filter(|dir| dir.is_dir())
+            .collect(),
+        Err(e) => {
+            log::error!("Error locating steam for its runtime libraries: {e}");
+            vec![]
+        }
+    }
+}
+
+/// Returns the libraries needed by `bin` that can't be found in `search_path`, the binary's
+/// rpath or the system library folders. Like `ldd`, but only for the binary's direct dependencies.
+pub fn unresolved_libraries(bin: &Path, search_path: &[PathBuf]) -> Result<Vec<String>, ElfError> {
+    let info = read_dynamic_info(bin)?;
+    let system_dirs = system_library_dirs();
+    let dirs: Vec<&Path> = info
+        .search_paths
+        .iter()
+        .chain(search_path)
+        .chain(&system_dirs)
+        .map(PathBuf::as_path)
+        .collect();
+    Ok(info
+        .needed
+        .into_iter()
+        .filter(|lib| !dirs.iter().any(|dir| dir.join(lib).exists()))
+        .collect())
+}
+
+/// Folders listed in `/etc/ld.so.conf` (following its includes) and the default system folders
+fn system_library_dirs() -> Vec<PathBuf> {
+    fn read_conf(path: &Path, dirs: &mut Vec<PathBuf>, depth: usize) {
+        let Ok(conf) = std::fs::read_to_string(path) else {
+            return;
+        };
+        for line in conf
+            .lines()
+            .map(|l| l.split('#').next().unwrap_or("").trim())
+        {
+            if let Some(pattern) = line.strip_prefix("include") {
+                // Includes are globs, in practice always of the form `some/dir/*.conf`
+                let pattern = Path::new(pattern.trim());
+                let (Some(dir), Some(file_pattern)) = (pattern.parent(), pattern.file_name())
+                else {
+                    continue;
+                };
+                let suffix = file_pattern
+                    .to_string_lossy()
+                    .trim_start_matches('*')
+                    .to_string();
+                let Ok(entries) = dir.read_dir() else {
+                    continue;
+                };
+                for entry in entries.filter_map(Result::ok) {
+                    let is_match = entry.file_name().to_string_lossy().ends_with(&suffix);
+                    if is_match && depth < 4 {
+                        read_conf(&entry.path(), dirs, depth + 1);
+                    }
+                }
+            } else if !line.is_empty() {
+                dirs.push(PathBuf::from(line));
+            }
+        }
+    }
+    let mut dirs = Vec::new();
+    read_conf(Path::new(LD_SO_CONF_PATH), &mut dirs, 0);
+    dirs.extend(SYSTEM_LIBRARY_DIRS.iter().map(PathBuf::from));
+    dirs
+}
+
+/// Reads the `DT_NEEDED`, `DT_RPATH` and `DT_RUNPATH` entries of an ELF binary's dynamic section.
+/// Only the headers, the dynamic section and its string table are read, not the whole binary.
+pub fn read_dynamic_info(bin: &Path) -> Result<DynamicInfo, ElfError> {
+    let elf = Elf::open(bin)?;
+    let dynamic = elf
+        .sections()?
+        .find(|section| section.kind == SHT_DYNAMIC)
+        .ok_or(ElfError::NoDynamicSection)?;
+    let strings = elf.section(dynamic.link as usize)?;
+    if strings.size > MAX_STRING_TABLE_SIZE {
+        return Err(ElfError::Malformed);
+    }
+    let mut strings_table = vec![0; strings.size as usize];
+    let strings_offset = usize::try_from(strings.offset).map_err(|_| ElfError::Malformed)?;
+    elf.read_exact_at(&mut strings_table, strings_offset)?;
+    let string_at = |offset: u64| -> Result<String, ElfError> {
+        let start = usize::try_from(offset).map_err(|_| ElfError::Malformed)?;
+        let table = strings_table.get(start..).ok_or(ElfError::Malformed)?;
+        let length = table
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(ElfError::Malformed)?;
+        Ok(String::from_utf8_lossy(&table[..length]).to_string())
+    };
+
+    let origin = bin
+        .parent()
+        .unwrap_or(Path::new("/"))
+        .to_string_lossy()
+        .to_string();
+    let mut info = DynamicInfo::default();
+    let entry_size = elf.word_size() * 2;
+    for i in 0..dynamic.size / entry_size as u64 {
+        let entry = i
+            .checked_mul(entry_size as u64)
+            .ok_or(ElfError::Malformed)
+            .and_then(|entry| checked_offset(dynamic.offset, entry))?;
+        let tag = elf.read_word(entry)?;
+        let value = elf.read_word(checked_offset(entry as u64, elf.word_size() as u64)?)?;
+        match tag {
+            DT_NULL => break,
+            DT_NEEDED => info.needed.push(string_at(value)?),
+            DT_RPATH | DT_RUNPATH => {
+                let paths = string_at(value)?
+                    .replace("${ORIGIN}", &origin)
+                    .replace("$ORIGIN", &origin);
+                info.search_paths.extend(std::env::split_paths(&paths));
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+struct Section {
+    kind: u32,
+    link: u32,
+    offset: u64,
+    size: u64,
+}
+
+/// Minimal little endian ELF reader, just enough to find the dynamic section. Reads the file at
+/// the offsets its headers point to.
+struct Elf {
+    file: File,
+    is_64: bool,
+}
+
+impl Elf {
+    fn open(bin: &Path) -> Result<Self, ElfError> {
+        let mut elf = Self {
+            file: File::open(bin)?,
+            is_64: false,
+        };
+        let ident: [u8; 6] = match elf.read(0) {
+            Ok(ident) => ident,
+            Err(ElfError::Malformed) => return Err(ElfError::NotElf),
+            Err(e) => return Err(e),
+        };
+        if ident[..4] != ELF_MAGIC {
+            return Err(ElfError::NotElf);
+        }
+        elf.is_64 = match ident[4] {
+            ELF_CLASS_32 => false,
+            ELF_CLASS_64 => true,
+            _ => return Err(ElfError::Malformed),
+        };
+        if ident[5] != ELF_DATA_LITTLE_ENDIAN {
+            return Err(ElfError::UnsupportedEndianness);
+        }
+        Ok(elf)
+    }
+
+    fn word_size(&self) -> usize {
+        if self.is_64 {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// Fills `buffer` from `offset`, a read past the end of the file means the binary is malformed
+    fn read_exact_at(&self, buffer: &mut [u8], offset: usize) -> Result<(), ElfError> {
+        offset
+            .checked_add(buffer.len())
+            .ok_or(ElfError::Malformed)?;
+        self.file
+            .read_exact_at(buffer, offset as u64)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::UnexpectedEof => ElfError::Malformed,
+                _ => ElfError::Io(e),
+            })
+    }
+
+    fn read<const N: usize>(&self, offset: usize) -> Result<[u8; N], ElfError> {
+        let mut buffer = [0; N];
+        self.read_exact_at(&mut buffer, offset)?;
+        Ok(buffer)
+    }
+
+    fn read_u16(&self, offset: usize) -> Result<u16, ElfError> {
+        self.read(offset).map(u16::from_le_bytes)
+    }
+
+    fn read_u32(&self, offset: usize) -> Result<u32, ElfError> {
+        self.read(offset).map(u32::from_le_bytes)
+    }
+
+    /// Reads an address sized value (`ElfN_Addr`, `ElfN_Off`, `ElfN_Xword`)
+    fn read_word(&self, offset: usize) -> Result<u64, ElfError> {
+        if self.is_64 {
+            self.read(offset).map(u64::from_le_bytes)
+        } else {
+            self.read_u32(offset).map(u64::from)
+        }
+    }
+
+    fn section(&self, index: usize) -> Result<Section, ElfError> {
+        let (table_offset, entry_size) = if self.is_64 {
+            (self.read_word(0x28)?, self.read_u16(0x3A)?)
+        } else {
+            (self.read_word(0x20)?, self.read_u16(0x2E)?)
+        };
+        let header = (index as u64)
+            .checked_mul(u64::from(entry_size))
+            .ok_or(ElfError::Malformed)
+            .and_then(|entry| checked_offset(table_offset, entry))?;
+        let field = |offset: usize| header.checked_add(offset).ok_or(ElfError::Malformed);
+        let word = self.word_size();
+        // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link
+        Ok(Section {
+            kind: self.read_u32(field(4)?)?,
+            offset: self.read_word(field(8 + 2 * word)?)?,
+            size: self.read_word(field(8 + 3 * word)?)?,
+            link: self.read_u32(field(8 + 4 * word)?)?,
+        })
+    }
+
+    fn sections(&self) -> Result<impl Iterator<Item = Section> + '_, ElfError> {
+        let count = if self.is_64 {
+            self.read_u16(0x3C)?
+        } else {
+            self.read_u16(0x30)?
+        };
+        Ok((0..count as usize).filter_map(|i| self.section(i).ok()))
+    }
+}
+
+/// `base + offset` as an index into the binary, offsets come from the file and can't be trusted
+fn checked_offset(base: u64, offset: u64) -> Result<usize, ElfError> {
+    base.checked_add(offset)
+        .and_then(|offset| usize::try_from(offset).ok())
+        .ok_or(ElfError::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_libraries_of_a_binary() {
+        let info = read_dynamic_info(&std::env::current_exe().unwrap()).unwrap();
+        assert!(info.needed.iter().any(|lib| lib.starts_with("libc.so")));
+    }
+
+    #[test]
+    fn rejects_offsets_past_the_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("broken");
+        // 64 bit little endian header whose section table sits at the very end of the address space
+        let mut header = vec![0; 0x40];
+        header[..4].copy_from_slice(&ELF_MAGIC);
+        header[4] = ELF_CLASS_64;
+        header[5] = ELF_DATA_LITTLE_ENDIAN;
+        header[0x28..0x30].copy_from_slice(&u64::MAX.to_le_bytes());
+        header[0x3A..0x3C].copy_from_slice(&u16::MAX.to_le_bytes());
+        header[0x3C..0x3E].copy_from_slice(&2u16.to_le_bytes());
+        std::fs::write(&bin, &header).unwrap();
+        assert!(matches!(
+            read_dynamic_info(&bin),
+            Err(ElfError::NoDynamicSection)
+        ));
+        let elf = Elf::open(&bin).unwrap();
+        assert!(matches!(elf.section(1), Err(ElfError::Malformed)));
+        assert!(matches!(
+            elf.read::<8>(usize::MAX - 4),
+            Err(ElfError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_elf() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("run-client.sh");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        assert!(matches!(read_dynamic_info(&script), Err(ElfError::NotElf)));
+        let empty = dir.path().join("empty");
+        std::fs::write(&empty, "").unwrap();
+        assert!(matches!(read_dynamic_info(&empty), Err(ElfError::NotElf)));
+    }
+}
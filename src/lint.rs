@@ -0,0 +1,534 @@
+//! Checks of mods for the mistakes that break them in game.
+//! Every file of the linted sources that Starbound reads as JSON is parsed the way the game parses
+//! it, and the metadata of each source is checked for fields of the wrong type. Item names used by
+//! more than one file, and images and scripts referenced by a file but provided by no source, are
+//! reported too, as the game only notices those once the item or object is used.
+
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::{
+    assets::{AssetError, AssetSource, AssetTree},
+    lenient_json::{self, Lines},
+};
+
+/// Extensions of the asset files the game doesn't read as JSON
+const NON_JSON_EXTENSIONS: [&str; 16] = [
+    "png", "lua", "ogg", "wav", "mp3", "ttf", "otf", "txt", "md", "frag", "vert", "abc", "psd",
+    "ase", "aseprite", "zip",
+];
+/// Metadata fields the game expects to be strings
+const METADATA_STRING_FIELDS: [&str; 8] = [
+    "name",
+    "friendlyName",
+    "author",
+    "version",
+    "description",
+    "link",
+    "steamContentId",
+    "tags",
+];
+/// Metadata fields the game expects to be lists of source names
+const METADATA_LIST_FIELDS: [&str; 2] = ["includes", "requires"];
+/// Keys naming the item an item or object file defines
+const ITEM_NAME_KEYS: [&str; 2] = ["itemName", "objectName"];
+/// Extensions of the referenced files that are checked for existence
+const REFERENCE_EXTENSIONS: [&str; 2] = [".png", ".lua"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem with a file of a mod
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LintIssue {
+    /// Path of the file, `<pak>:<asset>` for a file inside a pak
+    pub file: String,
+    pub line: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+/// An item name and where it is defined
+struct ItemDefinition {
+    /// Asset path of the file, the same file can only be loaded once
+    asset: String,
+    file: String,
+    line: Option<usize>,
+}
+
+/// Lints `sources` within the tree of `asset_directories`, which they have to be part of for
+/// references to other sources to be resolved. Issues are sorted by file and line.
+pub fn lint(asset_directories: &[PathBuf], sources: &[PathBuf]) -> Vec<LintIssue> {
+    let (tree, errors) = AssetTree::load(asset_directories);
+    let mut issues: Vec<LintIssue> = errors
+        .into_iter()
+        .map(|e| LintIssue {
+            file: match &e {
+                AssetError::Io(path, _) | AssetError::InvalidPak(path, _) => {
+                    path.display().to_string()
+                }
+                AssetError::NotFound(asset) => asset.clone(),
+            },
+            line: None,
+            severity: Severity::Error,
+            message: e.to_string(),
+        })
+        .collect();
+
+    let mut items: BTreeMap<String, Vec<ItemDefinition>> = BTreeMap::new();
+    for (i, source) in tree.sources().iter().enumerate() {
+        if !sources.contains(&source.path) {
+            continue;
+        }
+        lint_metadata(source, &mut issues);
+        for asset in source.files() {
+            if !is_json(asset) {
+                continue;
+            }
+            let file = location(source, asset);
+            let bytes = match source.read(asset) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    issues.push(LintIssue {
+                        file,
+                        line: None,
+                        severity: Severity::Error,
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let (json, lines) = match lenient_json::from_slice_with_lines(&bytes) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    issues.push(LintIssue {
+                        file,
+                        line: Some(e.line),
+                        severity: Severity::Error,
+                        message: format!("invalid JSON, column {}: {}", e.column, e.message),
+                    });
+                    continue;
+                }
+            };
+            lint_references(
+                &tree,
+                asset,
+                &file,
+                &json,
+                &lines,
+                &mut String::new(),
+                &mut issues,
+            );
+            // Files overridden by a later source are never loaded, so their names can't collide
+            if tree.providers(asset).last() != Some(&i) {
+                continue;
+            }
+            for key in ITEM_NAME_KEYS {
+                if let Some(name) = json.get(key).and_then(Value::as_str) {
+                    items
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(ItemDefinition {
+                            asset: asset.to_string(),
+                            file: file.clone(),
+                            line: lines.get(&format!("/{key}")).copied(),
+                        });
+                }
+            }
+        }
+    }
+
+    for (name, definitions) in items {
+        if definitions.len() < 2 {
+            continue;
+        }
+        for definition in &definitions {
+            let others: Vec<&str> = definitions
+                .iter()
+                .filter(|other| other.asset != definition.asset)
+                .map(|other| other.file.as_str())
+                .collect();
+            issues.push(LintIssue {
+                file: definition.file.clone(),
+                line: definition.line,
+                severity: Severity::Error,
+                message: format!("item name \"{name}\" is also used by {}", others.join(", ")),
+            });
+        }
+    }
+
+    issues.sort();
+    issues
+}
+
+/// [lint] on a blocking thread, every file of the mods is read and parsed
+pub async fn lint_task(asset_directories: Vec<PathBuf>, sources: Vec<PathBuf>) -> Vec<LintIssue> {
+    tokio::task::spawn_blocking(move || lint(&asset_directories, &sources))
+        .await
+        .unwrap_or_else(|e| {
+            vec![LintIssue {
+                file: String::new(),
+                line: None,
+                severity: Severity::Error,
+                message: format!("Failed to lint the mods: {e}"),
+            }]
+        })
+}
+
+fn is_json(asset: &str) -> bool {
+    let name = asset.rsplit('/').next().unwrap_or(asset);
+    match name.rsplit_once('.') {
+        // Hidden files and files without an extension aren't assets the game looks for
+        None | Some(("", _)) => false,
+        Some((_, extension)) => !NON_JSON_EXTENSIONS
+            .iter()
+            .any(|e| extension.eq_ignore_ascii_case(e)),
+    }
+}
+
+/// Where the file of `asset` is found on disk
+fn location(source: &AssetSource, asset: &str) -> String {
+    if source.is_packed() {
+        format!("{}:{asset}", source.path.display())
+    } else {
+        source
+            .path
+            .join(asset.trim_start_matches('/'))
+            .display()
+            .to_string()
+    }
+}
+
+fn lint_metadata(source: &AssetSource, issues: &mut Vec<LintIssue>) {
+    // Paks carry their metadata already parsed, folders are parsed again to find the lines
+    let (file, metadata, lines) = match source.metadata_file() {
+        Some(path) => {
+            let file = path.display().to_string();
+            let parsed = std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| {
+                    lenient_json::from_slice_with_lines(&bytes).map_err(|e| e.to_string())
+                });
+            match parsed {
+                Ok((metadata, lines)) => (file, metadata, lines),
+                Err(e) => {
+                    issues.push(LintIssue {
+                        file,
+                        line: None,
+                        severity: Severity::Error,
+                        message: format!("invalid metadata, {e}"),
+                    });
+                    return;
+                }
+            }
+        }
+        None if source.is_packed() => (
+            source.path.display().to_string(),
+            source.metadata.clone(),
+            Lines::new(),
+        ),
+        None => return,
+    };
+
+    let Some(fields) = metadata.as_object() else {
+        issues.push(LintIssue {
+            file,
+            line: lines.get("").copied(),
+            severity: Severity::Error,
+            message: String::from("metadata is not an object"),
+        });
+        return;
+    };
+    let mut invalid = |key: &str, expected: &str| {
+        issues.push(LintIssue {
+            file: file.clone(),
+            line: lines.get(&format!("/{key}")).copied(),
+            severity: Severity::Error,
+            message: format!("metadata field \"{key}\" should be {expected}"),
+        });
+    };
+    for (key, value) in fields {
+        if METADATA_STRING_FIELDS.contains(&key.as_str()) && !value.is_string() {
+            invalid(key, "a string");
+        } else if key == "priority" && !value.is_number() {
+            invalid(key, "a number");
+        } else if METADATA_LIST_FIELDS.contains(&key.as_str())
+            && !value
+                .as_array()
+                .is_some_and(|list| list.iter().all(Value::is_string))
+        {
+            invalid(key, "a list of source names");
+        }
+    }
+}
+
+/// Warns about every image and script `json` references that no source provides
+fn lint_references(
+    tree: &AssetTree,
+    asset: &str,
+    file: &str,
+    json: &Value,
+    lines: &Lines,
+    pointer: &mut String,
+    issues: &mut Vec<LintIssue>,
+) {
+    match json {
+        Value::String(reference) => {
+            let Some(path) = referenced_asset(asset, reference) else {
+                return;
+            };
+            if !tree.contains(&path) {
+                issues.push(LintIssue {
+                    file: file.to_string(),
+                    line: lines.get(pointer.as_str()).copied(),
+                    severity: Severity::Warning,
+                    message: format!("{reference} does not exist"),
+                });
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                let len = pointer.len();
+                pointer.push_str(&format!("/{i}"));
+                lint_references(tree, asset, file, value, lines, pointer, issues);
+                pointer.truncate(len);
+            }
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                let len = pointer.len();
+                pointer.push('/');
+                pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                lint_references(tree, asset, file, value, lines, pointer, issues);
+                pointer.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The asset path of an image or script `reference` made by `asset`, None for anything else.
+/// Image directives after `?` and frames after `:` are dropped, references built from `<tags>` at
+/// runtime can't be resolved and are skipped.
+fn referenced_asset(asset: &str, reference: &str) -> Option<String> {
+    if reference.contains('<') {
+        return None;
+    }
+    let path = reference.split('?').next().unwrap_or(reference);
+    let path = path.split(':').next().unwrap_or(path);
+    if !REFERENCE_EXTENSIONS.iter().any(|e| path.ends_with(e)) {
+        return None;
+    }
+    let path = match path.strip_prefix('/') {
+        Some(_) => PathBuf::from(path),
+        None => Path::new(asset)
+            .parent()
+            .unwrap_or(Path::new("/"))
+            .join(path),
+    };
+    let mut components: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                components.pop();
+            }
+            _ => {}
+        }
+    }
+    Some(format!("/{}", components.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_files(dir: &Path, files: &[(&str, &str)]) {
+        for (path, contents) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+
+    /// Issues without the temporary folder in their file names
+    fn lint_in(root: &Path, sources: &[&str]) -> Vec<(String, Option<usize>, Severity, String)> {
+        let sources: Vec<PathBuf> = sources.iter().map(|source| root.join(source)).collect();
+        lint(&[root.join("assets"), root.join("mods")], &sources)
+            .into_iter()
+            .map(|issue| {
+                let file = issue
+                    .file
+                    .strip_prefix(&format!("{}/", root.display()))
+                    .unwrap_or(&issue.file)
+                    .to_string();
+                (file, issue.line, issue.severity, issue.message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_broken_mods() {
+        let root = tempfile::tempdir().unwrap();
+        write_files(
+            &root.path().join("assets"),
+            &[
+                ("_metadata", r#"{"name": "base"}"#),
+                ("items/apple.png", ""),
+                ("items/apple.item", r#"{"itemName": "apple"}"#),
+            ],
+        );
+        write_files(
+            &root.path().join("mods/a"),
+            &[
+                (
+                    "_metadata",
+                    "{\n  \"name\": \"a\",\n  \"priority\": \"high\",\n  \"requires\": [\"b\", 1]\n}",
+                ),
+                (
+                    "items/pear.item",
+                    "// a comment the game accepts\n{\n  \"itemName\": \"pear\",\n  \"inventoryIcon\": \"pear.png:0?hueshift=20\",\n}",
+                ),
+                ("items/pear.png", ""),
+                (
+                    "objects/lamp.object",
+                    "{\n  \"objectName\": \"lamp\",\n  \"script\": \"/scripts/lamp.lua\",\n  \"icon\": \"../items/apple.png\",\n  \"frames\": \"<color>.png\"\n}",
+                ),
+                ("broken.config", "{\n  \"a\": 1\n  \"b\": 2\n}"),
+                ("readme.txt", "{ not json"),
+            ],
+        );
+        write_files(
+            &root.path().join("mods/b"),
+            &[
+                ("_metadata", r#"{"name": "b"}"#),
+                ("items/other_pear.item", "{\n  \"itemName\": \"pear\"\n}"),
+            ],
+        );
+
+        let issues = lint_in(root.path(), &["mods/a", "mods/b"]);
+        let error = |file: &str, line, message: &str| {
+            (
+                file.to_string(),
+                Some(line),
+                Severity::Error,
+                message.to_string(),
+            )
+        };
+        assert_eq!(
+            issues,
+            [
+                error(
+                    "mods/a/_metadata",
+                    3,
+                    "metadata field \"priority\" should be a number"
+                ),
+                error(
+                    "mods/a/_metadata",
+                    4,
+                    "metadata field \"requires\" should be a list of source names"
+                ),
+                error(
+                    "mods/a/broken.config",
+                    3,
+                    "invalid JSON, column 3: expected ',' or '}', found '\"'"
+                ),
+                error(
+                    "mods/a/items/pear.item",
+                    3,
+                    &format!(
+                        "item name \"pear\" is also used by {}/mods/b/items/other_pear.item",
+                        root.path().display()
+                    )
+                ),
+                (
+                    String::from("mods/a/objects/lamp.object"),
+                    Some(3),
+                    Severity::Warning,
+                    String::from("/scripts/lamp.lua does not exist")
+                ),
+                error(
+                    "mods/b/items/other_pear.item",
+                    2,
+                    &format!(
+                        "item name \"pear\" is also used by {}/mods/a/items/pear.item",
+                        root.path().display()
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn overridden_files_dont_collide() {
+        let root = tempfile::tempdir().unwrap();
+        write_files(
+            &root.path().join("assets"),
+            &[
+                ("_metadata", r#"{"name": "base"}"#),
+                ("items/apple.item", r#"{"itemName": "apple"}"#),
+            ],
+        );
+        // Replaces the vanilla file instead of adding a second apple
+        write_files(
+            &root.path().join("mods/a"),
+            &[("items/apple.item", r#"{"itemName": "apple", "price": 5}"#)],
+        );
+        assert_eq!(lint_in(root.path(), &["assets", "mods/a"]), []);
+    }
+
+    #[test]
+    fn resolves_references() {
+        let asset = "/items/food/pear.item";
+        assert_eq!(
+            referenced_asset(asset, "pear.png"),
+            Some(String::from("/items/food/pear.png"))
+        );
+        assert_eq!(
+            referenced_asset(asset, "../icons/pear.png:idle.1?flipx"),
+            Some(String::from("/items/icons/pear.png"))
+        );
+        assert_eq!(
+            referenced_asset(asset, "/scripts/pear.lua"),
+            Some(String::from("/scripts/pear.lua"))
+        );
+        assert_eq!(referenced_asset(asset, "pear.frames"), None);
+        assert_eq!(referenced_asset(asset, "<variant>.png"), None);
+    }
+
+    #[test]
+    fn only_json_assets_are_parsed() {
+        assert!(is_json("/items/pear.item"));
+        assert!(is_json("/player.config.patch"));
+        assert!(!is_json("/items/pear.PNG"));
+        assert!(!is_json("/scripts/pear.lua"));
+        assert!(!is_json("/.gitignore"));
+        assert!(!is_json("/LICENSE"));
+    }
+}
@@ -1,35 +1,61 @@
 use std::{
     env::VarError,
     path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
 };
 
 use application::{Application, Message};
 use clap::Parser;
-use cli_args::CliArgs;
+use cli_args::{CliArgs, SBICommand};
 use directories::ProjectDirs;
 use iced::Task;
+use ipc::{SBIRequest, SingleInstance};
+use launch_plan::{LaunchMode, LaunchPlan};
+use patch::PatchOutcome;
+use steam_command::SteamCommand;
+use vanilla::VanillaPaths;
 
 mod application;
+mod assets;
+mod bisect;
 mod cli_args;
 mod config;
 mod executable;
 mod game_launcher;
+mod history;
+mod ipc;
+mod launch_plan;
+mod launch_strategy;
+mod lenient_json;
+mod libraries;
+mod lint;
 mod menus;
+mod migration;
+mod patch;
 mod profile;
+mod profile_lock;
+mod shortcuts;
+mod steam_command;
+mod steam_shortcuts;
+mod trash;
+mod vanilla;
+mod vdf;
+mod watcher;
 
 static ORGANIZATION_QUALIFIER: &str = "";
 static ORGANIZATION_NAME: &str = "";
 static APPLICATION_NAME: &str = "sbi";
 
 static PROFILE_JSON_NAME: &str = "profile.json";
+static PROFILE_LOCK_NAME: &str = "sbi.lock";
+static VANILLA_PROFILE_JSON_NAME: &str = "vanilla_profile.json";
 static SBI_CONFIG_JSON_NAME: &str = "config.json";
+static SBI_SOCKET_NAME: &str = "sbi.sock";
 
 static STARBOUND_STEAM_ID: u32 = 211820;
 static STARBOUND_BOOT_CONFIG_NAME: &str = "sbinit.config";
 
-// static LOCAL_PIPE_NAME: &str = "@SBI_PIPE_NAME";
-// static LOCAL_PIPE_FS_NAME: &str = "/tmp/@SBI_PIPE_NAME";
-
 #[derive(Debug, thiserror::Error)]
 enum SBIInitializationError {
     #[error("{0}")]
@@ -40,6 +66,18 @@ enum SBIInitializationError {
     IcedApplicationError(#[from] iced::Error),
     #[error("{0}")]
     ClapFailedToParseCLI(#[from] clap::Error),
+    #[error("Failed to forward request to the running sbi instance: {0}")]
+    ForwardToRunningInstance(#[from] ipc::IpcError),
+    #[error("Failed to start the async runtime: {0}")]
+    AsyncRuntime(#[from] std::io::Error),
+    #[error("No profile with the id or name {0}")]
+    UnknownProfile(String),
+    #[error("{0}")]
+    LaunchPlan(#[from] launch_plan::LaunchPlanError),
+    #[error("{0}")]
+    Asset(#[from] assets::AssetError),
+    #[error("{0}")]
+    Patch(#[from] patch::PatchError),
 }
 
 /// Reads an environment variable and returns the value as a PathBuf, or None if parsing failed.
@@ -66,8 +104,6 @@ pub fn parse_path_from_env(variable: &str) -> Option<PathBuf> {
 enum SBIDirectoryError {
     #[error("No home directory could be found. Default directories cannot be constructed.")]
     NoDefaultDirectories,
-    #[error("No vanilla assets directory! Please launch the game through steam with `sbi -- %command%` or directly with `SBI_VANILLA_ASSETS_DIR=some/path/ sbi`. SBI_VANILLA_ASSETS will take priority over the arguments.")]
-    NoVanillaAssetsDirectory,
     #[error("SBI data directory cannot be created: {0}")]
     FailedToCreateDataDir(std::io::Error),
     #[error("SBI profiles directory cannot be created: {0}")]
@@ -78,21 +114,33 @@ enum SBIDirectoryError {
 struct SBIDirectories {
     data_directory: PathBuf,
     profiles_directory: PathBuf,
-    vanilla_assets: PathBuf,
-    vanilla_storage: Option<PathBuf>,
-    vanilla_mods: Option<PathBuf>,
+    /// Deleted profiles are moved here until the trash is emptied
+    trash_directory: PathBuf,
+    /// Throwaway copies of the profiles' storage used by safe mode launches and bisect rounds
+    safe_mode_directory: PathBuf,
+    /// Links to the mods loaded by the current bisect round of each profile
+    bisect_directory: PathBuf,
+    /// Vanilla install given through `--assets` or `SBI_VANILLA_ASSETS_DIR`
+    vanilla_override: Option<VanillaPaths>,
+    /// Vanilla install chosen in the setup wizard, read from the config
+    vanilla_configured: Option<VanillaPaths>,
+    /// Vanilla install steam launched or steamlocate found
+    vanilla_steam: Option<VanillaPaths>,
 }
 
 impl SBIDirectories {
-    fn new(cli: CliArgs) -> Result<Self, SBIDirectoryError> {
-        // TODO: Ensure this error gets ignored if the data directory is specified through environment
-        // variables.
-        let default_proj_dirs =
-            ProjectDirs::from(ORGANIZATION_QUALIFIER, ORGANIZATION_NAME, APPLICATION_NAME)
-                .ok_or(SBIDirectoryError::NoDefaultDirectories)?;
-
-        let data_dir = parse_path_from_env("SBI_DATA_DIR")
-            .unwrap_or_else(|| default_proj_dirs.data_dir().to_path_buf());
+    fn new(
+        cli_assets: Option<PathBuf>,
+        steam_command: Option<&SteamCommand>,
+    ) -> Result<Self, SBIDirectoryError> {
+        // The default directories are only needed if the data directory isn't given explicitly
+        let data_dir = match parse_path_from_env("SBI_DATA_DIR") {
+            Some(dir) => dir,
+            None => ProjectDirs::from(ORGANIZATION_QUALIFIER, ORGANIZATION_NAME, APPLICATION_NAME)
+                .ok_or(SBIDirectoryError::NoDefaultDirectories)?
+                .data_dir()
+                .to_path_buf(),
+        };
         if !data_dir.exists() {
             std::fs::create_dir_all(&data_dir).map_err(SBIDirectoryError::FailedToCreateDataDir)?;
         }
@@ -103,45 +151,39 @@ impl SBIDirectories {
                 .map_err(SBIDirectoryError::FailedToCreateProfilesDir)?;
         }
 
-        let starbound_steam_dir = match steamlocate::SteamDir::locate() {
-            Err(e) => {
-                log::error!("Error while parsing steam installtion: {e}");
-                None
-            }
-            Ok(steam) => match steam.find_app(STARBOUND_STEAM_ID) {
-                Err(e) => {
-                    log::error!("{e}");
-                    None
-                }
-                Ok(None) => {
-                    log::error!("Starbound in not installed via steam. Please specify the location to find vanilla assets via SBI_VANILLA_ASSETS_DIR or the `--assets=/path/to/vanilla/assets` argument.");
-                    None
-                }
-                Ok(Some((starbound, library))) => Some(library.resolve_app_dir(&starbound)),
-            },
-        };
+        // Created once the first profile is deleted
+        let trash_dir = data_dir.join("trash");
+        // Created by the first safe mode launch or bisect round
+        let safe_mode_dir = data_dir.join("safe_mode");
+        let bisect_dir = data_dir.join("bisect");
 
-        let vanilla_assets = {
-            let vanilla_assets_source_cli = cli.assets;
-            let vanilla_assets_source_env = parse_path_from_env("SBI_VANILLA_ASSETS_DIR");
-            let vanilla_assets_source_steam =
-                starbound_steam_dir.as_ref().map(|d| d.join("assets"));
+        // The command steam actually launched is the most accurate source, steamlocate can miss
+        // libraries it fails to parse.
+        let starbound_steam_dir = steam_command
+            .and_then(SteamCommand::game_directory)
+            .or_else(locate_starbound_steam_dir);
 
-            vanilla_assets_source_cli
-                .or(vanilla_assets_source_env)
-                .or(vanilla_assets_source_steam)
-                .ok_or(SBIDirectoryError::NoVanillaAssetsDirectory)?
-        };
-
-        let vanilla_storage = starbound_steam_dir.as_ref().map(|d| d.join("storage"));
-        let vanilla_mods = starbound_steam_dir.map(|d| d.join("mods"));
+        let vanilla_steam = starbound_steam_dir
+            .as_deref()
+            .map(VanillaPaths::from_game_directory);
+        // Explicit assets still use steam's storage and mods, as they did before the setup wizard
+        let vanilla_override = cli_assets
+            .or_else(|| parse_path_from_env("SBI_VANILLA_ASSETS_DIR"))
+            .map(|assets| VanillaPaths {
+                assets,
+                storage: vanilla_steam.as_ref().and_then(|p| p.storage.clone()),
+                mods: vanilla_steam.as_ref().and_then(|p| p.mods.clone()),
+            });
 
         Ok(Self {
             data_directory: data_dir,
             profiles_directory: profiles_dir,
-            vanilla_assets,
-            vanilla_storage,
-            vanilla_mods,
+            trash_directory: trash_dir,
+            safe_mode_directory: safe_mode_dir,
+            bisect_directory: bisect_dir,
+            vanilla_override,
+            vanilla_configured: None,
+            vanilla_steam,
         })
     }
 
@@ -153,16 +195,64 @@ impl SBIDirectories {
         &self.profiles_directory
     }
 
-    pub fn vanilla_assets(&self) -> &Path {
-        &self.vanilla_assets
+    pub fn trash(&self) -> &Path {
+        &self.trash_directory
+    }
+
+    pub fn safe_mode(&self) -> &Path {
+        &self.safe_mode_directory
+    }
+
+    pub fn bisect(&self) -> &Path {
+        &self.bisect_directory
+    }
+
+    /// The default vanilla install: `--assets` or `SBI_VANILLA_ASSETS_DIR`, then the one
+    /// configured in the setup wizard, then steam's. None if the setup wizard is needed.
+    pub fn vanilla(&self) -> Option<&VanillaPaths> {
+        self.vanilla_override
+            .as_ref()
+            .or(self.vanilla_configured.as_ref())
+            .or(self.vanilla_steam.as_ref())
+    }
+
+    /// Uses the vanilla install from the config, called whenever the config is (re-)loaded
+    pub fn set_configured_vanilla(&mut self, paths: Option<VanillaPaths>) {
+        self.vanilla_configured = paths;
+    }
+
+    pub fn vanilla_assets(&self) -> Option<&Path> {
+        self.vanilla().map(|p| p.assets.as_path())
+    }
+
+    /// Game folder of the default vanilla install
+    pub fn vanilla_directory(&self) -> Option<&Path> {
+        self.vanilla().and_then(VanillaPaths::game_directory)
     }
 
     pub fn vanilla_storage(&self) -> Option<&Path> {
-        self.vanilla_storage.as_deref()
+        self.vanilla().and_then(|p| p.storage.as_deref())
     }
+}
 
-    pub fn vanilla_mods(&self) -> Option<&Path> {
-        self.vanilla_mods.as_deref()
+/// Finds the Starbound install folder in the steam libraries known to steamlocate
+fn locate_starbound_steam_dir() -> Option<PathBuf> {
+    match steamlocate::SteamDir::locate() {
+        Err(e) => {
+            log::error!("Error while parsing steam installtion: {e}");
+            None
+        }
+        Ok(steam) => match steam.find_app(STARBOUND_STEAM_ID) {
+            Err(e) => {
+                log::error!("{e}");
+                None
+            }
+            Ok(None) => {
+                log::error!("Starbound in not installed via steam. Please specify the location to find vanilla assets in the setup wizard, via SBI_VANILLA_ASSETS_DIR or the `--assets=/path/to/vanilla/assets` argument.");
+                None
+            }
+            Ok(Some((starbound, library))) => Some(library.resolve_app_dir(&starbound)),
+        },
     }
 }
 
@@ -188,9 +278,177 @@ impl SBIDirectories {
 //     log::error!("{e}");
 // }
 
-fn main() -> Result<(), SBIInitializationError> {
+/// Works out the launch plan of a profile for the commands that run without the GUI
+fn load_launch_plan(
+    mut dirs: SBIDirectories,
+    id_or_name: &str,
+    steam_command: Option<SteamCommand>,
+    mode: LaunchMode,
+) -> Result<LaunchPlan, SBIInitializationError> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let config = runtime.block_on(config::load_config(dirs.data().to_path_buf()));
+    dirs.set_configured_vanilla(config.vanilla.clone());
+    let profiles = runtime.block_on(profile::find_profiles(
+        dirs.profiles().to_path_buf(),
+        dirs.vanilla_storage().map(PathBuf::from),
+    ));
+    let profile = profile::find_by_id_or_name(&profiles, id_or_name)
+        .ok_or_else(|| SBIInitializationError::UnknownProfile(id_or_name.to_string()))?;
+    let game_version =
+        launch_plan::vanilla_directory(profile, &config, &dirs).and_then(vanilla::detect_version);
+    Ok(LaunchPlan::new(
+        profile,
+        &config,
+        &dirs,
+        game_version.as_deref(),
+        steam_command,
+        mode,
+    )?)
+}
+
+/// `sbi launch <profile> --dry-run`: prints how the profile would be launched, without launching
+/// it or handing it to a running sbi.
+fn print_launch_plan(
+    dirs: SBIDirectories,
+    id_or_name: &str,
+    json: bool,
+    safe_mode: bool,
+    steam_command: Option<SteamCommand>,
+) -> Result<(), SBIInitializationError> {
+    let mode = if safe_mode {
+        LaunchMode::Safe
+    } else {
+        LaunchMode::Normal
+    };
+    let plan = load_launch_plan(dirs, id_or_name, steam_command, mode)?;
+    if json {
+        println!("{}", plan.to_json());
+    } else {
+        println!("{plan}");
+    }
+    Ok(())
+}
+
+/// Prints the patched JSON of `asset`, or the patches of the whole profile that didn't apply
+fn print_patches(
+    dirs: SBIDirectories,
+    id_or_name: &str,
+    asset: Option<&str>,
+    all: bool,
+) -> Result<(), SBIInitializationError> {
+    let plan = load_launch_plan(dirs, id_or_name, None, LaunchMode::Normal)?;
+    let (tree, errors) = assets::AssetTree::load(&plan.asset_directories());
+    for e in errors {
+        eprintln!("{e}");
+    }
+    let reports = match asset {
+        Some(asset) => {
+            let patched = patch::patched_asset(&tree, asset)?;
+            for report in &patched.reports {
+                eprintln!("{report}");
+            }
+            println!("{:#}", patched.json?);
+            return Ok(());
+        }
+        None => patch::check_patches(&tree),
+    };
+    let failed = reports
+        .iter()
+        .filter(|report| matches!(report.outcome, PatchOutcome::Failed(_)))
+        .count();
+    for report in reports
+        .iter()
+        .filter(|report| all || !matches!(report.outcome, PatchOutcome::Applied))
+    {
+        println!("{report}");
+    }
+    println!(
+        "{} patches in {} sources, {failed} failed",
+        reports.len(),
+        tree.sources().len()
+    );
+    Ok(())
+}
+
+/// `sbi --steam-wrap launch <profile> -- %command%`: launches the profile without a window,
+/// replacing this process, the one steam started and tracks, with steam's rewritten command.
+fn launch_steam_wrapped(
+    dirs: SBIDirectories,
+    id_or_name: &str,
+    safe_mode: bool,
+    steam_command: SteamCommand,
+) -> Result<ExitCode, SBIInitializationError> {
+    let mode = if safe_mode {
+        LaunchMode::Safe
+    } else {
+        LaunchMode::Normal
+    };
+    let plan = load_launch_plan(dirs, id_or_name, Some(steam_command), mode)?;
+    log::info!("Launching {} with plan:\n{plan}", plan.profile_name);
+    let launch_settings = game_launcher::SBILaunchSettings {
+        close_on_launch: false,
+        replace_process: true,
+    };
+    let runtime = tokio::runtime::Runtime::new()?;
+    // Only returns if the launch failed, the game replaces sbi otherwise
+    match runtime.block_on(game_launcher::launch_game(plan, launch_settings)) {
+        game_launcher::SBILaunchStatus::Failure => Ok(ExitCode::FAILURE),
+        game_launcher::SBILaunchStatus::Exited { .. } => Ok(ExitCode::SUCCESS),
+    }
+}
+
+/// Prints the issues of a profile's mods, or of the mod folder `target` on top of the default
+/// vanilla assets. Returns false if any of them would break the game.
+fn print_lint(mut dirs: SBIDirectories, target: &str) -> Result<bool, SBIInitializationError> {
+    let folder = Path::new(target);
+    let (asset_directories, mod_directories) = if folder.is_dir() {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let config = runtime.block_on(config::load_config(dirs.data().to_path_buf()));
+        dirs.set_configured_vanilla(config.vanilla.clone());
+        let asset_directories = dirs
+            .vanilla_assets()
+            .map(Path::to_path_buf)
+            .into_iter()
+            .chain([folder.to_path_buf()])
+            .collect();
+        (asset_directories, vec![folder.to_path_buf()])
+    } else {
+        let plan = load_launch_plan(dirs, target, None, LaunchMode::Normal)?;
+        (plan.asset_directories(), plan.mod_directories())
+    };
+    let sources: Vec<PathBuf> = mod_directories
+        .iter()
+        .flat_map(|dir| assets::source_paths(dir))
+        .collect();
+    let issues = lint::lint(&asset_directories, &sources);
+    for issue in &issues {
+        println!("{issue}");
+    }
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == lint::Severity::Error)
+        .count();
+    println!(
+        "{} sources checked, {errors} errors, {} warnings",
+        sources.len(),
+        issues.len() - errors
+    );
+    Ok(errors == 0)
+}
+
+fn main() -> Result<ExitCode, SBIInitializationError> {
     let cli = CliArgs::parse();
-    let dirs = SBIDirectories::new(cli)?;
+    let request = match &cli.command {
+        Some(SBICommand::Launch {
+            profile, safe_mode, ..
+        }) => SBIRequest::Launch {
+            profile: profile.clone(),
+            safe_mode: *safe_mode,
+        },
+        Some(SBICommand::Patches { .. } | SBICommand::Lint { .. }) | None => SBIRequest::Focus,
+    };
+    let steam_command = cli.default_command.as_deref().and_then(SteamCommand::parse);
+    let dirs = SBIDirectories::new(cli.assets, steam_command.as_ref())?;
     let _log_handle = flexi_logger::Logger::try_with_env_or_str("info")?
         .log_to_file(
             flexi_logger::FileSpec::default()
@@ -199,23 +457,125 @@ fn main() -> Result<(), SBIInitializationError> {
                 .basename("sbi"), // .suppress_timestamp(),
         )
         .start()?;
+    if dirs.vanilla().is_none() {
+        // Not fatal, the config may name an install, otherwise the setup wizard asks for one.
+        log::warn!("No vanilla assets found through `--assets`, SBI_VANILLA_ASSETS_DIR or steam");
+    }
 
-    let application = Application::new(dirs);
+    if let Err(e) = migration::migrate_legacy_instances(dirs.data(), dirs.profiles()) {
+        log::error!("Error migrating legacy instances: {e}");
+    }
+
+    if let Some(command) = steam_command.as_ref() {
+        log::info!("Steam launched {}", command.game().display());
+        for wrapper in command.wrappers() {
+            log::info!("Steam command is wrapped by {wrapper}");
+        }
+    }
+    let steam_command = match (cli.steam_wrap, steam_command) {
+        (true, None) => {
+            log::warn!("--steam-wrap requires a steam command containing starbound after `--`, launching directly instead");
+            None
+        }
+        (true, Some(command)) if command.is_proton() => {
+            log::warn!("--steam-wrap can't swap a linux executable into a Proton launch, launching directly instead");
+            None
+        }
+        (true, Some(command)) => Some(command),
+        (false, _) => None,
+    };
+    if let Some(SBICommand::Patches {
+        profile,
+        asset,
+        all,
+    }) = &cli.command
+    {
+        return print_patches(dirs, profile, asset.as_deref(), *all).map(|()| ExitCode::SUCCESS);
+    }
+    if let Some(SBICommand::Lint { target }) = &cli.command {
+        // Fails the exit code on errors, so scripts can run it before launching
+        return print_lint(dirs, target).map(|clean| {
+            if clean {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        });
+    }
+    if let Some(SBICommand::Launch {
+        profile,
+        dry_run: true,
+        json,
+        safe_mode,
+    }) = &cli.command
+    {
+        return print_launch_plan(dirs, profile, *json, *safe_mode, steam_command)
+            .map(|()| ExitCode::SUCCESS);
+    }
+    if let (
+        Some(SBICommand::Launch {
+            profile, safe_mode, ..
+        }),
+        Some(command),
+    ) = (&cli.command, &steam_command)
+    {
+        return launch_steam_wrapped(dirs, profile, *safe_mode, command.clone());
+    }
+
+    // Only one sbi may own the data directory at a time, any other invocation hands its request
+    // over to the running instance instead of opening a second window.
+    let ipc_listener = match ipc::connect_or_bind(&dirs.data().join(SBI_SOCKET_NAME)) {
+        // Steam tracks the process it started, which has to stay and launch on its own
+        Ok(SingleInstance::Secondary(_)) if steam_command.is_some() => {
+            log::warn!("Another sbi is running, opening a second window for steam's launch");
+            None
+        }
+        Ok(SingleInstance::Secondary(stream)) => {
+            ipc::forward_request(stream, &request)?;
+            log::info!("Forwarded {request:?} to the running sbi instance");
+            return Ok(ExitCode::SUCCESS);
+        }
+        Ok(SingleInstance::Primary(listener)) => Some(Arc::new(listener)),
+        Err(e) => {
+            log::error!("Failed to create the sbi socket, other instances won't be detected: {e}");
+            None
+        }
+    };
+    let startup_request = match request {
+        SBIRequest::Focus => Task::none(),
+        request => Task::done(Message::HandleRequest(request)),
+    };
+
+    let application = Application::new(dirs, ipc_listener, steam_command);
     let profiles_dir = application.dirs().profiles().to_path_buf();
-    let vanilla_profile_dir = application.dirs().vanilla_storage().map(PathBuf::from);
+    let mut startup_dirs = application.dirs().clone();
     let data_dir = application.dirs().data().to_path_buf();
     iced::application("SBI", Application::update, Application::view)
         .theme(Application::theme)
+        .subscription(Application::subscription)
         .run_with(move || {
             (
                 application,
-                Task::perform(config::load_config(data_dir), Message::FetchedConfig).chain(
-                    Task::perform(
-                        profile::find_profiles(profiles_dir, vanilla_profile_dir),
-                        Message::FetchedProfiles,
-                    ),
-                ),
+                // The vanilla profile may come from the config, so profiles are found after it loads
+                Task::perform(
+                    config::load_config(data_dir.clone()),
+                    std::convert::identity,
+                )
+                .then(move |config| {
+                    startup_dirs.set_configured_vanilla(config.vanilla.clone());
+                    let vanilla_profile_dir = startup_dirs.vanilla_storage().map(PathBuf::from);
+                    Task::done(Message::FetchedConfig(config))
+                        .chain(Task::perform(
+                            history::load_history(data_dir.clone()),
+                            Message::FetchedHistory,
+                        ))
+                        .chain(Task::perform(
+                            profile::find_profiles(profiles_dir.clone(), vanilla_profile_dir),
+                            Message::FetchedProfiles,
+                        ))
+                })
+                .chain(startup_request),
             )
         })?;
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
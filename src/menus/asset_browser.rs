@@ -0,0 +1,364 @@
+// Asset Browser Submenu
+
+use std::sync::Arc;
+
+use iced::{widget, Element, Task};
+
+use crate::{
+    application::{Application, Message},
+    assets::AssetTree,
+    patch::{self, PATCH_EXTENSION},
+    profile::ProfileId,
+};
+
+/// Previews longer than this are cut off, the text widget slows down on huge files
+const PREVIEW_MAX_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub enum AssetBrowserSubmenuMessage {
+    Loaded(Arc<AssetTree>, Vec<String>),
+    OpenFolder(String),
+    Up,
+    Filter(String),
+    Select(String),
+    Previewed(String, Preview),
+    Exit,
+}
+
+#[derive(Debug, Clone)]
+pub enum Preview {
+    /// The asset after every patch is applied, and what became of each patch
+    Json {
+        json: String,
+        patches: Vec<String>,
+    },
+    Text(String),
+    Image(widget::image::Handle),
+    Binary(usize),
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetBrowserSubmenuData {
+    profile: ProfileId,
+    /// None while the sources are read
+    tree: Option<Arc<AssetTree>>,
+    errors: Vec<String>,
+    /// Asset path of the open folder, `""` being the root
+    directory: String,
+    folders: Vec<String>,
+    files: Vec<String>,
+    filter: String,
+    selected: Option<String>,
+    preview: Option<Preview>,
+}
+
+// Submenus are compared by what they show, the tree and preview can't be compared
+impl PartialEq for AssetBrowserSubmenuData {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+impl Eq for AssetBrowserSubmenuData {}
+impl PartialOrd for AssetBrowserSubmenuData {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for AssetBrowserSubmenuData {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl AssetBrowserSubmenuData {
+    pub fn new(profile: ProfileId) -> Self {
+        Self {
+            profile,
+            tree: None,
+            errors: Vec::new(),
+            directory: String::new(),
+            folders: Vec::new(),
+            files: Vec::new(),
+            filter: String::new(),
+            selected: None,
+            preview: None,
+        }
+    }
+
+    fn key(&self) -> (ProfileId, &str, Option<&str>) {
+        (self.profile, &self.directory, self.selected.as_deref())
+    }
+
+    fn open_folder(&mut self, directory: String) {
+        if let Some(tree) = &self.tree {
+            (self.folders, self.files) = tree.list(&directory);
+        }
+        self.directory = directory;
+        self.filter.clear();
+    }
+
+    pub fn update(&mut self, m: AssetBrowserSubmenuMessage) -> Task<Message> {
+        type M = AssetBrowserSubmenuMessage;
+        match m {
+            M::Loaded(tree, errors) => {
+                self.tree = Some(tree);
+                self.errors = errors;
+                self.open_folder(String::new());
+                Task::none()
+            }
+            M::OpenFolder(directory) => {
+                self.open_folder(directory);
+                Task::none()
+            }
+            M::Up => {
+                let parent = self
+                    .directory
+                    .rsplit_once('/')
+                    .map(|(parent, _)| parent.to_string())
+                    .unwrap_or_default();
+                self.open_folder(parent);
+                Task::none()
+            }
+            M::Filter(filter) => {
+                self.filter = filter;
+                Task::none()
+            }
+            M::Select(path) => {
+                let Some(tree) = self.tree.clone() else {
+                    return Task::none();
+                };
+                self.selected = Some(path.clone());
+                self.preview = None;
+                Task::perform(load_preview(tree, path.clone()), move |preview| {
+                    M::Previewed(path.clone(), preview).into()
+                })
+            }
+            M::Previewed(path, preview) => {
+                // A preview arriving after another file was selected is dropped
+                if self.selected.as_ref() == Some(&path) {
+                    self.preview = Some(preview);
+                }
+                Task::none()
+            }
+            M::Exit => Task::done(Message::ButtonExitSubmenuPressed),
+        }
+    }
+
+    pub fn view<'a>(&'a self, root: &'a Application) -> Element<'a, AssetBrowserSubmenuMessage> {
+        type M = AssetBrowserSubmenuMessage;
+        let back = widget::button("Back").on_press(M::Exit);
+        let profile_name = root
+            .profile(self.profile)
+            .map(|p| p.name())
+            .unwrap_or_default();
+        let Some(tree) = &self.tree else {
+            return widget::column![
+                widget::text!("Assets of {profile_name}"),
+                widget::text("Reading the asset sources...").height(iced::Fill),
+                back
+            ]
+            .spacing(5)
+            .padding(5)
+            .into();
+        };
+
+        let errors = self.errors.iter().map(|e| {
+            widget::text(e)
+                .size(12)
+                .color(iced::Color::from_rgb(0.9, 0.3, 0.3))
+                .into()
+        });
+        let header = widget::column![
+            widget::text!("Assets of {profile_name}"),
+            widget::text!(
+                "{} sources, in the order the game loads them",
+                tree.sources().len()
+            )
+            .size(12),
+        ]
+        .extend(errors)
+        .spacing(3);
+
+        // Folder listing
+        let filter = self.filter.to_lowercase();
+        let matches = |name: &&String| filter.is_empty() || name.to_lowercase().contains(&filter);
+        let folders = self.folders.iter().filter(matches).map(|name| {
+            widget::button(widget::text!("{name}/").size(14))
+                .style(widget::button::text)
+                .padding(1)
+                .on_press(M::OpenFolder(format!("{}/{name}", self.directory)))
+                .into()
+        });
+        let files = self.files.iter().filter(matches).map(|name| {
+            let path = format!("{}/{name}", self.directory);
+            let style = if self.selected.as_ref() == Some(&path) {
+                widget::button::primary
+            } else {
+                widget::button::text
+            };
+            widget::button(widget::text(name).size(14))
+                .style(style)
+                .padding(1)
+                .on_press(M::Select(path))
+                .into()
+        });
+        let listing = widget::column![
+            widget::row![
+                widget::button("Up").on_press_maybe((!self.directory.is_empty()).then_some(M::Up)),
+                widget::text!("{}/", self.directory),
+            ]
+            .spacing(5)
+            .align_y(iced::alignment::Vertical::Center),
+            widget::text_input("Filter...", &self.filter).on_input(M::Filter),
+            widget::scrollable(widget::column(folders.chain(files)).width(iced::Fill))
+                .height(iced::Fill),
+        ]
+        .spacing(5)
+        .width(iced::FillPortion(2));
+
+        let details = match &self.selected {
+            Some(path) => self.details(tree, path),
+            None => sources_list(tree),
+        };
+
+        widget::column![
+            header,
+            widget::row![
+                listing,
+                widget::container(details).width(iced::FillPortion(3))
+            ]
+            .spacing(10)
+            .height(iced::Fill),
+            back,
+        ]
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+
+    /// Which sources provide and patch the selected file, and its preview
+    fn details<'a>(
+        &'a self,
+        tree: &'a AssetTree,
+        path: &'a str,
+    ) -> Element<'a, AssetBrowserSubmenuMessage> {
+        let providers = tree.providers(path);
+        let provided_by = providers
+            .last()
+            .map(|&source| widget::text!("Provided by {}", tree.source(source).name()));
+        let overridden = (providers.len() > 1).then(|| {
+            let names: Vec<String> = providers[..providers.len() - 1]
+                .iter()
+                .rev()
+                .map(|&source| tree.source(source).name())
+                .collect();
+            widget::text!("Overrides {}", names.join(", ")).size(12)
+        });
+        let patchers = tree.providers(&format!("{path}{PATCH_EXTENSION}"));
+        let patched_by = (!patchers.is_empty()).then(|| {
+            let names: Vec<String> = patchers
+                .iter()
+                .map(|&source| tree.source(source).name())
+                .collect();
+            widget::text!("Patched by {}", names.join(", ")).size(12)
+        });
+
+        let preview: Element<'a, AssetBrowserSubmenuMessage> = match &self.preview {
+            None => widget::text("Loading...").into(),
+            Some(Preview::Json { json, patches }) => widget::column(
+                patches
+                    .iter()
+                    .map(|patch| widget::text(patch).size(12).into()),
+            )
+            .push(widget::scrollable(monospace(json)).height(iced::Fill))
+            .spacing(3)
+            .into(),
+            Some(Preview::Text(text)) => widget::scrollable(monospace(text))
+                .height(iced::Fill)
+                .into(),
+            Some(Preview::Image(handle)) => widget::image(handle.clone())
+                .filter_method(widget::image::FilterMethod::Nearest)
+                .content_fit(iced::ContentFit::ScaleDown)
+                .width(iced::Fill)
+                .into(),
+            Some(Preview::Binary(size)) => widget::text!("No preview, {size} bytes").into(),
+            Some(Preview::Error(e)) => widget::text(e)
+                .color(iced::Color::from_rgb(0.9, 0.3, 0.3))
+                .into(),
+        };
+
+        widget::column![widget::text(path)]
+            .push_maybe(provided_by)
+            .push_maybe(overridden)
+            .push_maybe(patched_by)
+            .push(preview)
+            .spacing(3)
+            .into()
+    }
+}
+
+fn monospace<'a>(text: &'a str) -> widget::Text<'a> {
+    widget::text(text).font(iced::Font::MONOSPACE).size(12)
+}
+
+/// The sources in load order, shown until a file is selected
+fn sources_list(tree: &AssetTree) -> Element<'_, AssetBrowserSubmenuMessage> {
+    let sources = tree.sources().iter().map(|source| {
+        widget::column![
+            widget::text(source.name()),
+            widget::text(source.path.display().to_string()).size(12),
+        ]
+        .into()
+    });
+    widget::scrollable(widget::column(sources).spacing(5).width(iced::Fill))
+        .height(iced::Fill)
+        .into()
+}
+
+/// Reads the selected file on a blocking thread, patching it if it is JSON
+async fn load_preview(tree: Arc<AssetTree>, path: String) -> Preview {
+    tokio::task::spawn_blocking(move || preview(&tree, &path))
+        .await
+        .unwrap_or_else(|e| Preview::Error(e.to_string()))
+}
+
+fn preview(tree: &AssetTree, path: &str) -> Preview {
+    let bytes = match tree.read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => return Preview::Error(e.to_string()),
+    };
+    if path.ends_with(".png") {
+        return Preview::Image(widget::image::Handle::from_bytes(bytes));
+    }
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => return Preview::Binary(e.as_bytes().len()),
+    };
+    if path.ends_with(".lua") {
+        return Preview::Text(truncate(text));
+    }
+    // Starbound's JSON assets use all kinds of extensions, anything that parses is treated as JSON
+    match patch::patched_asset(tree, path) {
+        Ok(patch::PatchedAsset {
+            json: Ok(json),
+            reports,
+        }) => Preview::Json {
+            json: truncate(format!("{json:#}")),
+            patches: reports.iter().map(ToString::to_string).collect(),
+        },
+        _ => Preview::Text(truncate(text)),
+    }
+}
+
+fn truncate(mut text: String) -> String {
+    if text.len() > PREVIEW_MAX_LEN {
+        let end = (0..=PREVIEW_MAX_LEN)
+            .rev()
+            .find(|&i| text.is_char_boundary(i))
+            .unwrap_or_default();
+        text.truncate(end);
+        text.push_str("\n...");
+    }
+    text
+}
@@ -0,0 +1,226 @@
+// Mod Bisection Submenu
+
+use std::path::{Path, PathBuf};
+
+use iced::{widget, Element, Task};
+
+use crate::{
+    application::{Application, Message},
+    bisect::{self, Bisection, BisectionResult},
+    history::LaunchRecord,
+    profile::ProfileId,
+};
+
+#[derive(Debug, Clone)]
+pub enum BisectSubmenuMessage {
+    Launch,
+    /// The round's game exited, None if it could not be launched
+    RoundFinished(Option<LaunchRecord>),
+    CheckedLog(Option<String>),
+    Answer(bool),
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BisectSubmenuData {
+    profile: ProfileId,
+    /// Throwaway storage the rounds run in, checked for crashes after each round
+    storage: PathBuf,
+    bisection: Bisection,
+    running: bool,
+    /// How the last round's game exited
+    last_exit: Option<String>,
+    /// Whether the last round looks broken from its exit status and log
+    suggestion: Option<bool>,
+    crash_line: Option<String>,
+}
+
+impl BisectSubmenuData {
+    pub fn new(profile: ProfileId, storage: PathBuf, mods: Vec<PathBuf>) -> Self {
+        Self {
+            profile,
+            storage,
+            bisection: Bisection::new(mods),
+            running: false,
+            last_exit: None,
+            suggestion: None,
+            crash_line: None,
+        }
+    }
+
+    pub fn profile(&self) -> ProfileId {
+        self.profile
+    }
+
+    pub fn update(&mut self, m: BisectSubmenuMessage) -> Task<Message> {
+        type M = BisectSubmenuMessage;
+        match m {
+            M::Launch => {
+                self.running = true;
+                self.last_exit = None;
+                self.suggestion = None;
+                self.crash_line = None;
+                Task::done(Message::LaunchBisectRound(
+                    self.profile,
+                    self.bisection.round_mods(),
+                ))
+            }
+            M::RoundFinished(_) if !self.running => Task::none(),
+            M::RoundFinished(None) => {
+                self.running = false;
+                self.last_exit = Some(String::from("The game could not be launched"));
+                Task::none()
+            }
+            M::RoundFinished(Some(record)) => {
+                self.running = false;
+                self.last_exit = Some(format!(
+                    "The game ran for {}s and exited with {}",
+                    record.duration,
+                    record.exit_status()
+                ));
+                self.suggestion = Some(!record.succeeded());
+                Task::perform(bisect::crash_in_log(self.storage.clone()), |line| {
+                    BisectSubmenuMessage::CheckedLog(line).into()
+                })
+            }
+            M::CheckedLog(line) => {
+                if line.is_some() {
+                    self.suggestion = Some(true);
+                }
+                self.crash_line = line;
+                Task::none()
+            }
+            M::Answer(broken) => {
+                self.bisection.answer(broken);
+                self.last_exit = None;
+                self.suggestion = None;
+                self.crash_line = None;
+                Task::none()
+            }
+            M::Exit => Task::done(Message::ButtonExitSubmenuPressed),
+        }
+    }
+
+    pub fn view<'a>(&'a self, _root: &'a Application) -> Element<'a, BisectSubmenuMessage> {
+        type M = BisectSubmenuMessage;
+        let header = widget::column![
+            widget::text("Find Broken Mod"),
+            widget::text(
+                "Each round launches the game with part of the profile's mods, on a copy of its \
+                 storage so your saves are never touched."
+            )
+            .size(12),
+        ]
+        .spacing(5);
+
+        let body: Element<'a, M> = match self.bisection.result() {
+            Some(BisectionResult::Culprits([])) => {
+                widget::text("The profile has no mods to narrow down").into()
+            }
+            Some(BisectionResult::Culprits([culprit])) => widget::column![
+                widget::text("The game breaks because of"),
+                widget::text(mod_name(culprit)).font(iced::Font::MONOSPACE),
+            ]
+            .spacing(5)
+            .into(),
+            Some(BisectionResult::Culprits(culprits)) => widget::column![
+                widget::text("The game breaks when these mods are loaded together"),
+                mod_list(culprits),
+            ]
+            .spacing(5)
+            .into(),
+            Some(BisectionResult::NotBroken) => {
+                widget::text("The game works with all of the profile's mods loaded").into()
+            }
+            Some(BisectionResult::Inconclusive(culprits)) => widget::column![
+                widget::text(
+                    "These mods were narrowed down to but did not break the game on their own, \
+                     it may only break some of the time"
+                ),
+                mod_list(culprits),
+            ]
+            .spacing(5)
+            .into(),
+            None => {
+                let mods = self.bisection.round_mods();
+                let heuristic = self.suggestion.map(|broken| {
+                    let guess = if broken {
+                        "Looks like it broke"
+                    } else {
+                        "Looks like it worked"
+                    };
+                    widget::text!(
+                        "{guess}{}",
+                        self.crash_line
+                            .as_ref()
+                            .map(|line| format!(", the log says: {line}"))
+                            .unwrap_or_default()
+                    )
+                    .size(12)
+                });
+                // Only a launched round can be answered
+                let answerable = !self.running && self.last_exit.is_some();
+                widget::column![
+                    widget::text!(
+                        "Round {}, {}",
+                        self.bisection.round(),
+                        self.bisection.round_purpose()
+                    ),
+                    widget::text!("This round loads {} mods:", mods.len()),
+                    widget::scrollable(mod_list(&mods)).height(iced::Fill),
+                    widget::button(if self.running {
+                        "Running..."
+                    } else {
+                        "Launch Round"
+                    })
+                    .on_press_maybe((!self.running).then_some(M::Launch)),
+                ]
+                .push_maybe(
+                    self.last_exit
+                        .as_ref()
+                        .map(|exit| widget::text(exit).size(12)),
+                )
+                .push_maybe(heuristic)
+                .push(
+                    widget::row![
+                        widget::text("Did it work?"),
+                        widget::button("It worked")
+                            .on_press_maybe(answerable.then_some(M::Answer(false))),
+                        widget::button("It broke")
+                            .on_press_maybe(answerable.then_some(M::Answer(true))),
+                    ]
+                    .spacing(5)
+                    .align_y(iced::alignment::Vertical::Center),
+                )
+                .spacing(5)
+                .into()
+            }
+        };
+
+        widget::column![
+            header,
+            widget::container(body).height(iced::Fill),
+            widget::button("Back").on_press(M::Exit),
+        ]
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+}
+
+fn mod_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn mod_list<'a>(mods: &[PathBuf]) -> Element<'a, BisectSubmenuMessage> {
+    widget::column(mods.iter().map(|m| {
+        widget::text(mod_name(m))
+            .font(iced::Font::MONOSPACE)
+            .size(12)
+            .into()
+    }))
+    .spacing(2)
+    .into()
+}
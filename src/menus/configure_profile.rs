@@ -1,28 +1,55 @@
 // Configure Profile Submenu
 
+use std::path::PathBuf;
+
 use iced::{widget, Element, Task};
 
 use crate::{
     application::{Application, Message},
-    profile::ProfileJson,
+    profile::{ProfileId, ProfileJson},
 };
 
+/// Entry of the vanilla install picker, None for the default install
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VanillaInstallChoice(pub Option<String>);
+
+impl std::fmt::Display for VanillaInstallChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(name) => f.write_str(name),
+            None => f.write_str("Default"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ConfigureProfileSubmenuMessage {
     Exit,
     ToggleLinkModsCheckbox(bool),
+    EditGroups(String),
+    SubmitGroups,
+    AddLibraryDir,
+    PickedLibraryDir(Option<PathBuf>),
+    RemoveLibraryDir(usize),
+    SelectVanillaInstall(VanillaInstallChoice),
+    CreateDesktopEntry,
+    ExportLaunchScript,
+    AddToSteam,
     Delete,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ConfigureProfileSubmenuData {
     profile_copy: ProfileJson,
+    /// Comma separated groups as typed by the user
+    groups: String,
 }
 
 impl ConfigureProfileSubmenuData {
     pub fn new(original: &ProfileJson) -> Self {
         Self {
             profile_copy: original.clone(),
+            groups: original.groups.join(", "),
         }
     }
 
@@ -32,29 +59,133 @@ impl ConfigureProfileSubmenuData {
             M::Exit => Task::done(Message::ButtonExitSubmenuPressed),
             M::ToggleLinkModsCheckbox(b) => {
                 self.profile_copy.link_mods = b;
-                Task::done(Message::ModifyCurrentProfile(self.profile_copy.clone()))
+                Task::done(Message::ModifyProfile(self.profile_copy.clone()))
+            }
+            M::EditGroups(s) => {
+                self.groups = s;
+                Task::none()
+            }
+            M::SubmitGroups => {
+                let mut groups: Vec<String> = Vec::new();
+                for group in self.groups.split(',').map(str::trim) {
+                    if !group.is_empty() && !groups.iter().any(|g| g == group) {
+                        groups.push(group.to_string());
+                    }
+                }
+                self.groups = groups.join(", ");
+                self.profile_copy.groups = groups;
+                Task::done(Message::ModifyProfile(self.profile_copy.clone()))
+            }
+            M::AddLibraryDir => {
+                async fn pick_library_dir() -> Option<PathBuf> {
+                    let folder: Option<rfd::FileHandle> =
+                        rfd::AsyncFileDialog::new().pick_folder().await;
+                    folder.map(|f| f.path().to_path_buf())
+                }
+                Task::perform(pick_library_dir(), |r| {
+                    Message::ConfigureProfileMessage(M::PickedLibraryDir(r))
+                })
+            }
+            M::PickedLibraryDir(mby_dir) => {
+                if let Some(dir) = mby_dir {
+                    self.profile_copy.library_dirs.push(dir);
+                    Task::done(Message::ModifyProfile(self.profile_copy.clone()))
+                } else {
+                    Task::none()
+                }
             }
-            M::Delete => Task::done(Message::DeleteCurrentProfile)
-                .chain(Task::done(Message::ButtonExitSubmenuPressed)),
+            M::RemoveLibraryDir(i) => {
+                if i < self.profile_copy.library_dirs.len() {
+                    self.profile_copy.library_dirs.remove(i);
+                }
+                Task::done(Message::ModifyProfile(self.profile_copy.clone()))
+            }
+            M::SelectVanillaInstall(VanillaInstallChoice(install)) => {
+                self.profile_copy.vanilla_install = install;
+                Task::done(Message::ModifyProfile(self.profile_copy.clone()))
+            }
+            M::CreateDesktopEntry => Task::done(Message::CreateDesktopEntry),
+            M::ExportLaunchScript => Task::done(Message::ExportLaunchScript),
+            M::AddToSteam => Task::done(Message::AddSteamShortcut),
+            M::Delete => Task::done(Message::ButtonDeletePressed(self.profile_copy.id)),
         }
     }
 
     pub fn view<'a>(
         &'a self,
-        _root: &'a Application,
+        root: &'a Application,
     ) -> Element<'a, ConfigureProfileSubmenuMessage> {
         type M = ConfigureProfileSubmenuMessage;
+        let mut install_names: Vec<&String> = root.config().vanilla_installs.keys().collect();
+        install_names.sort();
+        let installs: Vec<VanillaInstallChoice> = std::iter::once(VanillaInstallChoice(None))
+            .chain(
+                install_names
+                    .into_iter()
+                    .map(|name| VanillaInstallChoice(Some(name.clone()))),
+            )
+            .collect();
+        let selected_install = VanillaInstallChoice(self.profile_copy.vanilla_install.clone());
+        let game_version = root
+            .game_version(self.profile_copy.vanilla_install.as_deref())
+            .unwrap_or("unknown version");
+        let library_dirs = self
+            .profile_copy
+            .library_dirs
+            .iter()
+            .enumerate()
+            .map(|(i, dir)| {
+                widget::row![
+                    widget::text!("{}", dir.display()).width(iced::Fill),
+                    widget::button("Remove").on_press(M::RemoveLibraryDir(i)),
+                ]
+                .spacing(5)
+                .into()
+            });
+        // The vanilla profile is the default install's own storage and can't be deleted
+        let is_vanilla = self.profile_copy.id == ProfileId::VANILLA;
+        let vanilla_install = (!is_vanilla).then(|| {
+            widget::row![
+                widget::text("Vanilla install"),
+                widget::pick_list(installs, Some(selected_install), M::SelectVanillaInstall),
+                widget::text(game_version),
+            ]
+            .spacing(5)
+            .align_y(iced::alignment::Vertical::Center)
+        });
+        let delete_action = (!is_vanilla).then_some(M::Delete);
         widget::column![
             widget::column![widget::text("Configuring Profile"),].spacing(8),
             widget::checkbox("Link mods", self.profile_copy.link_mods)
                 .on_toggle(M::ToggleLinkModsCheckbox),
+        ]
+        .push_maybe(vanilla_install)
+        .push(widget::column![
+            widget::row![
+                widget::text("Groups"),
+                widget::text_input("--Comma separated--", &self.groups)
+                    .on_input(M::EditGroups)
+                    .on_submit(M::SubmitGroups),
+                widget::button("Apply").on_press(M::SubmitGroups),
+            ]
+            .spacing(5)
+            .align_y(iced::alignment::Vertical::Center),
+            widget::button("Add Library Folder").on_press(M::AddLibraryDir),
+            widget::column(library_dirs).spacing(3),
+            widget::row![
+                widget::button("Add to Application Menu").on_press(M::CreateDesktopEntry),
+                widget::button("Export Launch Script").on_press(M::ExportLaunchScript),
+                widget::button("Add to Steam").on_press(M::AddToSteam),
+            ]
+            .spacing(5),
+            widget::text("Steam must be closed while adding a profile to it.").size(12),
             widget::vertical_space(),
             widget::row![
                 widget::button("Close").on_press(M::Exit),
                 widget::horizontal_space(),
-                widget::button("Delete").on_press(M::Delete),
+                widget::button("Delete").on_press_maybe(delete_action),
             ]
-        ]
+        ])
         .padding(5)
         .into()
     }
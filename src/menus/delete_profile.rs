@@ -0,0 +1,74 @@
+// Delete Profile Submenu
+
+use iced::{widget, Element, Task};
+
+use crate::{
+    application::{Application, Message},
+    profile::ProfileId,
+    trash::ProfileContents,
+};
+
+#[derive(Debug, Clone)]
+pub enum DeleteProfileSubmenuMessage {
+    LoadedContents(ProfileContents),
+    Confirm,
+    Cancel,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeleteProfileSubmenuData {
+    profile: ProfileId,
+    name: String,
+    /// None until the profile folder has been scanned
+    contents: Option<ProfileContents>,
+}
+
+impl DeleteProfileSubmenuData {
+    pub fn new(profile: ProfileId, name: &str) -> Self {
+        Self {
+            profile,
+            name: name.to_string(),
+            contents: None,
+        }
+    }
+
+    pub fn update(&mut self, m: DeleteProfileSubmenuMessage) -> Task<Message> {
+        type M = DeleteProfileSubmenuMessage;
+        match m {
+            M::LoadedContents(contents) => {
+                self.contents = Some(contents);
+                Task::none()
+            }
+            M::Confirm => Task::done(Message::DeleteProfile(self.profile))
+                .chain(Task::done(Message::ButtonExitSubmenuPressed)),
+            M::Cancel => Task::done(Message::ButtonExitSubmenuPressed),
+        }
+    }
+
+    pub fn view<'a>(&'a self, _root: &'a Application) -> Element<'a, DeleteProfileSubmenuMessage> {
+        type M = DeleteProfileSubmenuMessage;
+        let contents = match &self.contents {
+            Some(contents) => widget::text!("This profile contains {contents}."),
+            None => widget::text("Scanning profile..."),
+        };
+        widget::column![
+            widget::text!("Delete {}?", self.name),
+            contents,
+            widget::text(
+                "The profile is moved to the trash and can be restored until the trash is emptied."
+            )
+            .size(12),
+            widget::vertical_space(),
+            widget::row![
+                widget::button("Cancel").on_press(M::Cancel),
+                widget::horizontal_space(),
+                widget::button("Delete")
+                    .on_press(M::Confirm)
+                    .style(widget::button::danger),
+            ]
+        ]
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+}
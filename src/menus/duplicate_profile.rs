@@ -2,11 +2,16 @@
 
 use iced::{widget, Element, Task};
 
-use crate::application::{Application, Message};
+use crate::{
+    application::{Application, Message},
+    profile::ProfileId,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DuplicateData {
     pub name: String,
+    /// Executable for the copy, None keeps the original's
+    pub selected_executable: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -17,15 +22,17 @@ pub enum DuplicateSubmenuMessage {
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DuplicateSubmenuData {
+    pub profile: ProfileId,
     pub original: String,
     pub name: String,
 }
 
 impl DuplicateSubmenuData {
-    pub fn new(original: &str) -> Self {
+    pub fn new(profile: ProfileId, original: &str) -> Self {
         let mut name = original.to_string();
         name.push_str(" (Copy)");
         Self {
+            profile,
             original: original.to_string(),
             name,
         }
@@ -39,8 +46,11 @@ impl DuplicateSubmenuData {
             }
             DuplicateSubmenuMessage::Done => {
                 let name = self.name.trim().to_string();
-                let data = DuplicateData { name };
-                Task::done(Message::DuplicateCurrentProfile(data))
+                let data = DuplicateData {
+                    name,
+                    selected_executable: None,
+                };
+                Task::done(Message::DuplicateProfile(self.profile, data))
                     .chain(Task::done(Message::ButtonExitSubmenuPressed))
             }
         }
@@ -0,0 +1,65 @@
+// Launch History Submenu
+
+use iced::{widget, Element, Task};
+
+use crate::{
+    application::{Application, Message},
+    history,
+    profile::ProfileId,
+};
+
+#[derive(Debug, Clone)]
+pub enum LaunchHistorySubmenuMessage {
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LaunchHistorySubmenuData {
+    profile: ProfileId,
+}
+
+impl LaunchHistorySubmenuData {
+    pub fn new(profile: ProfileId) -> Self {
+        Self { profile }
+    }
+
+    pub fn update(&mut self, m: LaunchHistorySubmenuMessage) -> Task<Message> {
+        match m {
+            LaunchHistorySubmenuMessage::Exit => Task::done(Message::ButtonExitSubmenuPressed),
+        }
+    }
+
+    pub fn view<'a>(&'a self, root: &'a Application) -> Element<'a, LaunchHistorySubmenuMessage> {
+        let history = root.history();
+        let records = history.records(self.profile);
+        let summary = widget::text!(
+            "{} sessions, {} played in total",
+            records.len(),
+            history::format_duration(history.total_playtime(self.profile))
+        );
+        // Newest sessions first
+        let rows = records.iter().rev().map(|record| {
+            let status_color =
+                (!record.succeeded()).then_some(iced::Color::from_rgb(0.9, 0.3, 0.3));
+            widget::row![
+                widget::text(history::format_time_ago(record.started())).width(120),
+                widget::text!("{} ({})", record.executable, record.variant).width(iced::Fill),
+                widget::text(history::format_duration(record.duration())).width(80),
+                widget::text(record.exit_status())
+                    .color_maybe(status_color)
+                    .width(100),
+            ]
+            .spacing(5)
+            .into()
+        });
+        widget::column![
+            widget::text("Launch History"),
+            summary,
+            widget::scrollable(widget::column(rows).spacing(3)).height(iced::Fill),
+            widget::button("Back").on_press(LaunchHistorySubmenuMessage::Exit),
+        ]
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+}
@@ -0,0 +1,98 @@
+// Launch Plan Submenu
+
+use iced::{widget, Element, Task};
+
+use crate::{
+    application::{Application, Message},
+    launch_plan::{LaunchPlan, LaunchPlanError},
+};
+
+#[derive(Debug, Clone)]
+pub enum LaunchPlanSubmenuMessage {
+    /// The readable report and the JSON of the plan, which is worked out off the UI thread
+    Loaded(String, Option<String>),
+    ToggleJson(bool),
+    Copy,
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LaunchPlanSubmenuData {
+    /// The readable report, or why the profile can't be launched
+    report: String,
+    /// None if there is no plan to show
+    json: Option<String>,
+    show_json: bool,
+}
+
+impl LaunchPlanSubmenuMessage {
+    pub fn loaded(plan: Result<LaunchPlan, LaunchPlanError>) -> Self {
+        match plan {
+            Ok(plan) => Self::Loaded(plan.to_string(), Some(plan.to_json())),
+            Err(e) => Self::Loaded(format!("Cannot launch: {e}"), None),
+        }
+    }
+}
+
+impl LaunchPlanSubmenuData {
+    pub fn new() -> Self {
+        Self {
+            report: String::from("Working out the launch plan..."),
+            json: None,
+            show_json: false,
+        }
+    }
+
+    fn shown(&self) -> &str {
+        match &self.json {
+            Some(json) if self.show_json => json,
+            _ => &self.report,
+        }
+    }
+
+    pub fn update(&mut self, m: LaunchPlanSubmenuMessage) -> Task<Message> {
+        type M = LaunchPlanSubmenuMessage;
+        match m {
+            M::Loaded(report, json) => {
+                self.report = report;
+                self.json = json;
+                Task::none()
+            }
+            M::ToggleJson(state) => {
+                self.show_json = state;
+                Task::none()
+            }
+            M::Copy => iced::clipboard::write(self.shown().to_string()),
+            M::Exit => Task::done(Message::ButtonExitSubmenuPressed),
+        }
+    }
+
+    pub fn view<'a>(&'a self, _root: &'a Application) -> Element<'a, LaunchPlanSubmenuMessage> {
+        type M = LaunchPlanSubmenuMessage;
+        let toggle_json = self
+            .json
+            .as_ref()
+            .map(|_| widget::checkbox("JSON", self.show_json).on_toggle(M::ToggleJson));
+        widget::column![
+            widget::text("Launch Plan"),
+            widget::text("Nothing is launched, this is what the Launch button would run.").size(12),
+            widget::scrollable(
+                widget::text(self.shown())
+                    .font(iced::Font::MONOSPACE)
+                    .size(12)
+            )
+            .height(iced::Fill),
+            widget::row![
+                widget::button("Back").on_press(M::Exit),
+                widget::horizontal_space()
+            ]
+            .push_maybe(toggle_json)
+            .push(widget::button("Copy").on_press(M::Copy))
+            .spacing(5)
+            .align_y(iced::alignment::Vertical::Center),
+        ]
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+}
@@ -0,0 +1,107 @@
+// Mod Lint Submenu
+
+use iced::{widget, Element, Task};
+
+use crate::{
+    application::{Application, Message},
+    lint::{LintIssue, Severity},
+    profile::ProfileId,
+};
+
+#[derive(Debug, Clone)]
+pub enum LintSubmenuMessage {
+    Linted(Vec<LintIssue>),
+    Launch,
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LintSubmenuData {
+    profile: ProfileId,
+    /// Whether the lint was started by launching the profile, which goes on if nothing is broken
+    launch_after: bool,
+    /// None while the mods are checked
+    issues: Option<Vec<LintIssue>>,
+}
+
+impl LintSubmenuData {
+    pub fn new(profile: ProfileId, launch_after: bool) -> Self {
+        Self {
+            profile,
+            launch_after,
+            issues: None,
+        }
+    }
+
+    fn errors(&self) -> usize {
+        self.issues
+            .iter()
+            .flatten()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count()
+    }
+
+    pub fn update(&mut self, m: LintSubmenuMessage) -> Task<Message> {
+        type M = LintSubmenuMessage;
+        match m {
+            M::Linted(issues) => {
+                self.issues = Some(issues);
+                if self.launch_after && self.errors() == 0 {
+                    Task::done(Message::LaunchWithoutLint(self.profile))
+                } else {
+                    Task::none()
+                }
+            }
+            M::Launch => Task::done(Message::LaunchWithoutLint(self.profile)),
+            M::Exit => Task::done(Message::ButtonExitSubmenuPressed),
+        }
+    }
+
+    pub fn view<'a>(&'a self, root: &'a Application) -> Element<'a, LintSubmenuMessage> {
+        type M = LintSubmenuMessage;
+        let profile_name = root
+            .profile(self.profile)
+            .map(|p| p.name())
+            .unwrap_or_default();
+        let body: Element<'a, M> = match &self.issues {
+            None => widget::text("Checking the mods...").into(),
+            Some(issues) if issues.is_empty() => widget::text("No issues found").into(),
+            Some(issues) => {
+                let issues = issues.iter().map(|issue| {
+                    let color = match issue.severity {
+                        Severity::Error => iced::Color::from_rgb(0.9, 0.3, 0.3),
+                        Severity::Warning => iced::Color::from_rgb(0.9, 0.6, 0.2),
+                    };
+                    widget::text(issue.to_string())
+                        .font(iced::Font::MONOSPACE)
+                        .size(12)
+                        .color(color)
+                        .into()
+                });
+                widget::column![widget::text!(
+                    "{} errors, {} warnings",
+                    self.errors(),
+                    self.issues.iter().flatten().count() - self.errors()
+                )]
+                .push(widget::scrollable(widget::column(issues).spacing(2)).height(iced::Fill))
+                .spacing(5)
+                .into()
+            }
+        };
+        let launch_button = self.launch_after.then(|| {
+            widget::button("Launch Anyway")
+                .on_press_maybe(self.issues.is_some().then_some(M::Launch))
+        });
+
+        widget::column![
+            widget::text!("Mods of {profile_name}"),
+            widget::container(body).height(iced::Fill),
+            widget::row![widget::button("Back").on_press(M::Exit)]
+                .push_maybe(launch_button)
+                .spacing(5),
+        ]
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+}
@@ -1,5 +1,14 @@
 pub mod new_profile;
+pub mod asset_browser;
+pub mod bisect;
 pub mod configure_profile;
+pub mod delete_profile;
 pub mod duplicate_profile;
+pub mod launch_history;
+pub mod launch_plan;
+pub mod lint;
+pub mod onboarding;
 pub mod rename_profile;
 pub mod settings;
+pub mod trash;
+pub mod vanilla_setup;
@@ -51,11 +51,17 @@ impl NewProfileSubmenuData {
                         (!self.collection_id.is_empty()).then(|| self.collection_id.clone());
                     // Make a new profile with just a name
                     crate::profile::ProfileJson {
+                        schema_version: crate::migration::PROFILE_SCHEMA_VERSION,
+                        id: crate::profile::ProfileId::generate(),
                         name: self.name.clone(),
                         additional_assets: None,
                         collection_id,
                         link_mods: false,
                         selected_executable: None,
+                        favorite: false,
+                        groups: Vec::new(),
+                        library_dirs: Vec::new(),
+                        vanilla_install: None,
                     }
                 };
                 Task::done(Message::CreateProfile(profile))
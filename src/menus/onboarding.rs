@@ -0,0 +1,450 @@
+// Onboarding Submenu
+
+use std::path::PathBuf;
+
+use iced::{widget, Element, Task};
+
+use crate::{
+    application::{Application, Message},
+    config::SBIConfig,
+    executable::{self, Executable, ExecutableVariant},
+    menus::{duplicate_profile::DuplicateData, settings},
+    migration,
+    profile::{ProfileId, ProfileJson},
+    vanilla::{self, VanillaPaths},
+    SBIDirectories,
+};
+
+static OPENSTARBOUND_RELEASES_URL: &str = "https://github.com/OpenStarbound/OpenStarbound/releases";
+static XSTARBOUND_RELEASES_URL: &str = "https://github.com/xStarbound/xStarbound/releases";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OnboardingStep {
+    Vanilla,
+    Executables,
+    Profile,
+}
+
+#[derive(Debug, Clone)]
+pub enum OnboardingSubmenuMessage {
+    Next,
+    Back,
+    Skip,
+    // Vanilla step
+    AutoDetectVanilla,
+    DetectedVanilla(Option<VanillaPaths>),
+    PickGameFolder,
+    PickedGameFolder(Option<PathBuf>),
+    // Executables step
+    DetectedExecutables(Vec<Executable>),
+    AddDetectedExecutable(usize),
+    EditExecutableName(String),
+    PickExecutableBinary,
+    PickedExecutableBinary(Option<PathBuf>),
+    OpenReleases(ExecutableVariant),
+    // Profile step
+    EditProfileName(String),
+    ToggleCopyVanilla(bool),
+    SelectExecutable(String),
+    CreateProfile,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OnboardingSubmenuData {
+    step: OnboardingStep,
+    /// Why the picked game folder was rejected, or that auto-detection found nothing
+    vanilla_error: Option<String>,
+    /// Executables found on disk, None while searching
+    detected_executables: Option<Vec<Executable>>,
+    new_executable_name: String,
+    /// Why the picked binary was rejected
+    executable_error: Option<String>,
+    profile_name: String,
+    copy_vanilla: bool,
+    selected_executable: Option<String>,
+}
+
+impl OnboardingSubmenuData {
+    pub fn new() -> Self {
+        Self {
+            step: OnboardingStep::Vanilla,
+            vanilla_error: None,
+            detected_executables: None,
+            new_executable_name: String::new(),
+            executable_error: None,
+            profile_name: String::from("My Profile"),
+            copy_vanilla: true,
+            selected_executable: None,
+        }
+    }
+
+    /// Name for an executable that isn't taken yet, e.g. `OpenStarbound (2)`
+    fn unique_executable_name(base: &str, config: &SBIConfig) -> String {
+        let mut name = base.to_string();
+        let mut i = 2;
+        while config.executables.contains_key(&name) {
+            name = format!("{base} ({i})");
+            i += 1;
+        }
+        name
+    }
+
+    pub fn update(
+        &mut self,
+        m: OnboardingSubmenuMessage,
+        config: &SBIConfig,
+        dirs: &SBIDirectories,
+    ) -> Task<Message> {
+        type M = OnboardingSubmenuMessage;
+        match m {
+            M::Next => match self.step {
+                OnboardingStep::Vanilla => {
+                    self.step = OnboardingStep::Executables;
+                    // Releases are usually unpacked next to the game, at home or in downloads
+                    let user_dirs = directories::UserDirs::new();
+                    let roots: Vec<PathBuf> = dirs
+                        .vanilla_directory()
+                        .map(PathBuf::from)
+                        .into_iter()
+                        .chain(user_dirs.as_ref().map(|d| d.home_dir().to_path_buf()))
+                        .chain(
+                            user_dirs
+                                .as_ref()
+                                .and_then(|d| d.download_dir())
+                                .map(PathBuf::from),
+                        )
+                        .collect();
+                    Task::perform(executable::detect_executables(roots), |r| {
+                        M::DetectedExecutables(r).into()
+                    })
+                }
+                OnboardingStep::Executables => {
+                    self.step = OnboardingStep::Profile;
+                    if self
+                        .selected_executable
+                        .as_ref()
+                        .is_none_or(|name| !config.executables.contains_key(name))
+                    {
+                        let mut names: Vec<&String> = config.executables.keys().collect();
+                        names.sort();
+                        self.selected_executable = names.first().map(|name| name.to_string());
+                    }
+                    Task::none()
+                }
+                OnboardingStep::Profile => Task::none(),
+            },
+            M::Back => {
+                self.step = match self.step {
+                    OnboardingStep::Vanilla | OnboardingStep::Executables => {
+                        OnboardingStep::Vanilla
+                    }
+                    OnboardingStep::Profile => OnboardingStep::Executables,
+                };
+                Task::none()
+            }
+            M::Skip => Task::done(Message::FinishOnboarding),
+            M::AutoDetectVanilla => {
+                self.vanilla_error = None;
+                Task::perform(vanilla::detect_paths(), |r| M::DetectedVanilla(r).into())
+            }
+            M::DetectedVanilla(Some(paths)) => Task::done(Message::WriteVanillaPaths(paths)),
+            M::DetectedVanilla(None) => {
+                self.vanilla_error = Some(String::from(
+                    "Starbound could not be found in any steam library, please pick its folder",
+                ));
+                Task::none()
+            }
+            M::PickGameFolder => {
+                async fn pick_game_folder() -> Option<PathBuf> {
+                    let folder: Option<rfd::FileHandle> = rfd::AsyncFileDialog::new()
+                        .set_title("Select the Starbound folder")
+                        .pick_folder()
+                        .await;
+                    folder.map(|f| f.path().to_path_buf())
+                }
+                Task::perform(pick_game_folder(), |r| M::PickedGameFolder(r).into())
+            }
+            M::PickedGameFolder(Some(dir)) => {
+                let mut paths = VanillaPaths::from_game_directory(&dir);
+                // A game that was never run has no storage folder yet
+                paths.storage = paths.storage.filter(|p| p.is_dir());
+                paths.mods = paths.mods.filter(|p| p.is_dir());
+                match paths.validate() {
+                    Ok(()) => {
+                        self.vanilla_error = None;
+                        Task::done(Message::WriteVanillaPaths(paths))
+                    }
+                    Err(e) => {
+                        self.vanilla_error = Some(e.to_string());
+                        Task::none()
+                    }
+                }
+            }
+            M::PickedGameFolder(None) => Task::none(),
+            M::DetectedExecutables(executables) => {
+                log::info!("Found {} executable(s) on disk", executables.len());
+                self.detected_executables = Some(executables);
+                Task::none()
+            }
+            M::AddDetectedExecutable(i) => {
+                let Some(executable) = self
+                    .detected_executables
+                    .as_ref()
+                    .and_then(|detected| detected.get(i))
+                else {
+                    return Task::none();
+                };
+                let name = Self::unique_executable_name(&executable.variant.to_string(), config);
+                Task::done(Message::WriteExecutable(name, executable.clone()))
+            }
+            M::EditExecutableName(s) => {
+                self.new_executable_name = s;
+                self.executable_error = None;
+                Task::none()
+            }
+            M::PickExecutableBinary => Task::perform(settings::pick_executable_binary(), |r| {
+                M::PickedExecutableBinary(r).into()
+            }),
+            M::PickedExecutableBinary(Some(bin)) => {
+                let executable = Executable::from_binary(bin);
+                if let Err(e) = executable.validate() {
+                    self.executable_error = Some(e.to_string());
+                    return Task::none();
+                }
+                self.executable_error = None;
+                // The name may have been taken since the binary was picked
+                let name = Self::unique_executable_name(self.new_executable_name.trim(), config);
+                self.new_executable_name.clear();
+                Task::done(Message::WriteExecutable(name, executable))
+            }
+            M::PickedExecutableBinary(None) => Task::none(),
+            M::OpenReleases(variant) => {
+                let url = match variant {
+                    ExecutableVariant::XStarbound => XSTARBOUND_RELEASES_URL,
+                    _ => OPENSTARBOUND_RELEASES_URL,
+                };
+                // Waited on so the finished xdg-open is reaped
+                async fn open_url(url: &str) {
+                    match tokio::process::Command::new("xdg-open")
+                        .arg(url)
+                        .status()
+                        .await
+                    {
+                        Ok(status) if !status.success() => {
+                            log::error!("Error opening {url}: xdg-open exited with {status}")
+                        }
+                        Ok(_) => {}
+                        Err(e) => log::error!("Error opening {url}: {e}"),
+                    }
+                }
+                Task::perform(open_url(url), Message::Dummy)
+            }
+            M::EditProfileName(s) => {
+                self.profile_name = s;
+                Task::none()
+            }
+            M::ToggleCopyVanilla(b) => {
+                self.copy_vanilla = b;
+                Task::none()
+            }
+            M::SelectExecutable(name) => {
+                self.selected_executable = Some(name);
+                Task::none()
+            }
+            M::CreateProfile => {
+                let name = self.profile_name.trim().to_string();
+                let selected_executable = self.selected_executable.clone();
+                let has_vanilla_profile = dirs.vanilla_storage().is_some_and(|p| p.is_dir());
+                let create = if self.copy_vanilla && has_vanilla_profile {
+                    let data = DuplicateData {
+                        name,
+                        selected_executable,
+                    };
+                    Task::done(Message::DuplicateProfile(ProfileId::VANILLA, data))
+                } else {
+                    Task::done(Message::CreateProfile(ProfileJson {
+                        schema_version: migration::PROFILE_SCHEMA_VERSION,
+                        id: ProfileId::generate(),
+                        name,
+                        additional_assets: None,
+                        collection_id: None,
+                        link_mods: false,
+                        selected_executable,
+                        favorite: false,
+                        groups: Vec::new(),
+                        library_dirs: Vec::new(),
+                        vanilla_install: None,
+                    }))
+                };
+                create.chain(Task::done(Message::FinishOnboarding))
+            }
+        }
+    }
+
+    pub fn view<'a>(&'a self, root: &'a Application) -> Element<'a, OnboardingSubmenuMessage> {
+        type M = OnboardingSubmenuMessage;
+        let (title, body, next) = match self.step {
+            OnboardingStep::Vanilla => (
+                "Welcome to sbi! (1/3) Starbound",
+                self.view_vanilla(root),
+                Some(M::Next),
+            ),
+            OnboardingStep::Executables => (
+                "Welcome to sbi! (2/3) Executables",
+                self.view_executables(root),
+                Some(M::Next),
+            ),
+            OnboardingStep::Profile => (
+                "Welcome to sbi! (3/3) First Profile",
+                self.view_profile(root),
+                None,
+            ),
+        };
+        let back = (self.step != OnboardingStep::Vanilla).then_some(M::Back);
+        widget::column![
+            widget::text(title),
+            body,
+            widget::vertical_space(),
+            widget::row![
+                widget::button("Skip").on_press(M::Skip),
+                widget::horizontal_space(),
+                widget::button("Back").on_press_maybe(back),
+            ]
+            .push_maybe(next.map(|next| widget::button("Next").on_press(next)))
+            .spacing(5),
+        ]
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+
+    fn view_vanilla<'a>(&'a self, root: &'a Application) -> Element<'a, OnboardingSubmenuMessage> {
+        type M = OnboardingSubmenuMessage;
+        let status = match root.dirs().vanilla() {
+            Some(paths) => widget::text!(
+                "Using Starbound at {} ({})",
+                paths.assets.display(),
+                root.game_version(None).unwrap_or("unknown version")
+            ),
+            None => widget::text(
+                "Starbound was not found. Every profile needs its vanilla assets to launch.",
+            )
+            .color(iced::Color::from_rgb(0.9, 0.3, 0.3)),
+        };
+        let error = self.vanilla_error.as_ref().map(|e| {
+            widget::text(e)
+                .size(12)
+                .color(iced::Color::from_rgb(0.9, 0.3, 0.3))
+        });
+        widget::column![
+            status,
+            widget::row![
+                widget::button("Auto-detect").on_press(M::AutoDetectVanilla),
+                widget::button("Pick Game Folder").on_press(M::PickGameFolder),
+            ]
+            .spacing(5),
+        ]
+        .push_maybe(error)
+        .spacing(5)
+        .into()
+    }
+
+    fn view_executables<'a>(
+        &'a self,
+        root: &'a Application,
+    ) -> Element<'a, OnboardingSubmenuMessage> {
+        type M = OnboardingSubmenuMessage;
+        let detected: Element<'a, M> = match &self.detected_executables {
+            None => widget::text("Searching for executables...").into(),
+            Some(detected) if detected.is_empty() => {
+                widget::text("No executables found next to the game, at home or in downloads.")
+                    .into()
+            }
+            Some(detected) => widget::column(detected.iter().enumerate().map(|(i, executable)| {
+                let registered = root.executables().values().any(|e| e.bin == executable.bin);
+                widget::row![
+                    widget::text!("{} - {}", executable.variant, executable.bin.display())
+                        .width(iced::Fill),
+                    widget::button(if registered { "Added" } else { "Add" })
+                        .on_press_maybe((!registered).then_some(M::AddDetectedExecutable(i))),
+                ]
+                .spacing(5)
+                .align_y(iced::alignment::Vertical::Center)
+                .into()
+            }))
+            .spacing(3)
+            .into(),
+        };
+        let mut registered: Vec<&String> = root.executables().keys().collect();
+        registered.sort();
+        let registered = if registered.is_empty() {
+            String::from("none yet")
+        } else {
+            registered
+                .into_iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let name_taken = root
+            .executables()
+            .contains_key(self.new_executable_name.trim());
+        let pick_action = (!self.new_executable_name.trim().is_empty() && !name_taken)
+            .then_some(M::PickExecutableBinary);
+        let error = self.executable_error.as_ref().map(|e| {
+            widget::text(e)
+                .size(12)
+                .color(iced::Color::from_rgb(0.9, 0.3, 0.3))
+        });
+        let register = widget::column![widget::row![
+            widget::text_input("-Name-", &self.new_executable_name).on_input(M::EditExecutableName),
+            widget::button("Pick Executable").on_press_maybe(pick_action),
+        ]
+        .spacing(5)]
+        .push_maybe(error)
+        .spacing(5);
+        widget::column![
+            widget::text("Found executables"),
+            detected,
+            widget::text("Register another executable"),
+            register,
+            widget::text("Don't have one yet? Download and unzip a release, then pick its binary.")
+                .size(12),
+            widget::row![
+                widget::button("Get OpenStarbound")
+                    .on_press(M::OpenReleases(ExecutableVariant::OpenStarbound)),
+                widget::button("Get xStarbound")
+                    .on_press(M::OpenReleases(ExecutableVariant::XStarbound)),
+            ]
+            .spacing(5),
+            widget::text!("Registered: {registered}"),
+        ]
+        .spacing(5)
+        .into()
+    }
+
+    fn view_profile<'a>(&'a self, root: &'a Application) -> Element<'a, OnboardingSubmenuMessage> {
+        type M = OnboardingSubmenuMessage;
+        let has_vanilla_profile = root.profile(ProfileId::VANILLA).is_some();
+        let mut executables: Vec<String> = root.executables().keys().cloned().collect();
+        executables.sort();
+        let create_action = (!self.profile_name.trim().is_empty()).then_some(M::CreateProfile);
+        widget::column![
+            widget::text_input("-Name-", &self.profile_name).on_input(M::EditProfileName),
+            widget::checkbox(
+                "Start from the vanilla universe and characters",
+                self.copy_vanilla && has_vanilla_profile,
+            )
+            .on_toggle_maybe(has_vanilla_profile.then_some(M::ToggleCopyVanilla)),
+            widget::pick_list(
+                executables,
+                self.selected_executable.clone(),
+                M::SelectExecutable,
+            )
+            .placeholder("Select an executable..."),
+            widget::button("Create Profile").on_press_maybe(create_action),
+        ]
+        .spacing(5)
+        .into()
+    }
+}
@@ -2,7 +2,10 @@
 
 use iced::{widget, Element, Task};
 
-use crate::application::{Application, Message};
+use crate::{
+    application::{Application, Message},
+    profile::ProfileId,
+};
 
 #[derive(Debug, Clone)]
 pub enum RenameSubmenuMessage {
@@ -12,12 +15,14 @@ pub enum RenameSubmenuMessage {
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RenameSubmenuData {
+    pub profile: ProfileId,
     pub name: String,
 }
 
 impl RenameSubmenuData {
-    pub fn new(original: &str) -> Self {
+    pub fn new(profile: ProfileId, original: &str) -> Self {
         Self {
+            profile,
             name: original.to_string(),
         }
     }
@@ -29,7 +34,7 @@ impl RenameSubmenuData {
                 Task::none()
             }
             RenameSubmenuMessage::Done => {
-                Task::done(Message::RenameCurrentProfile(self.name.clone()))
+                Task::done(Message::RenameProfile(self.profile, self.name.clone()))
                     .chain(Task::done(Message::ButtonExitSubmenuPressed))
             }
         }
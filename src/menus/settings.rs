@@ -8,251 +8,594 @@ use crate::{
     application::{Application, Message},
     config::SBIConfig,
     executable::{Executable, ExecutableVariant},
-    SBIDirectories,
+    launch_strategy::CustomLaunch,
+    libraries,
+    vanilla::VanillaInstall,
 };
 
+/// Asks the user for a starbound binary, shared with the onboarding guide
+pub async fn pick_executable_binary() -> Option<PathBuf> {
+    let file: Option<rfd::FileHandle> = rfd::AsyncFileDialog::new().pick_file().await;
+    file.map(|f| f.path().to_path_buf())
+}
+
+async fn pick_folder() -> Option<PathBuf> {
+    let folder: Option<rfd::FileHandle> = rfd::AsyncFileDialog::new().pick_folder().await;
+    folder.map(|f| f.path().to_path_buf())
+}
+
 #[derive(Debug, Clone)]
 pub enum SettingsSubmenuMessage {
     Exit,
     SelectExecutable(String),
-    GenerateExectuable,
+    NewExecutable,
     DeleteExecutable,
+    SaveExecutable,
     EditExecutableName(String),
+    EditExecutableVersion(String),
+    EditExecutableNotes(String),
 
     PickExecutableBinary,
     PickedExecutableBinary(Option<PathBuf>),
-    PickExecutableAssets,
-    PickedExecutableAssets(Option<PathBuf>),
+    AddAssetDir,
+    PickedAssetDir(Option<PathBuf>),
+    RemoveAssetDir(usize),
     SelectExecutableVariant(ExecutableVariant),
+    EditCustomArgs(String),
+    EditCustomEnv(String),
+    EditCustomBootConfig(String),
+    AddLibraryDir,
+    PickedLibraryDir(Option<PathBuf>),
+    RemoveLibraryDir(usize),
+    ToggleSteamRuntime(bool),
+    CheckLibraries,
+    CheckedLibraries(Result<Vec<String>, String>),
+    OpenVanillaSetup,
+    EditVanillaInstallName(String),
+    AddVanillaInstall,
+    PickedVanillaInstall(Option<PathBuf>),
+    RemoveVanillaInstall(String),
     ToggleCloseOnLaunch(bool),
+    ToggleLintBeforeLaunch(bool),
+}
+
+/// An executable being created or edited. Changes are only written once saved.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct ExecutableForm {
+    /// Name the executable is saved under, None while creating a new one
+    original_name: Option<String>,
+    name: String,
+    executable: Executable,
+    /// Launch settings of a custom variant as entered, parsed when saving
+    custom_args: String,
+    custom_env: String,
+    custom_boot_config: String,
+    /// Why saving failed
+    error: Option<String>,
+}
+
+impl ExecutableForm {
+    fn new() -> Self {
+        Self {
+            original_name: None,
+            name: String::new(),
+            executable: Executable::default(),
+            custom_args: String::new(),
+            custom_env: String::new(),
+            custom_boot_config: String::new(),
+            error: None,
+        }
+    }
+
+    fn edit(name: &str, executable: &Executable) -> Self {
+        Self {
+            original_name: Some(name.to_string()),
+            name: name.to_string(),
+            executable: executable.clone(),
+            custom_args: executable.custom_launch.args_text(),
+            custom_env: executable.custom_launch.env_text(),
+            custom_boot_config: executable.custom_launch.boot_config.clone(),
+            error: None,
+        }
+    }
+
+    fn is_modified(&self, config: &SBIConfig) -> bool {
+        let custom = &self.executable.custom_launch;
+        match &self.original_name {
+            Some(original) => {
+                *original != self.name
+                    || config.get_executable(original) != Some(&self.executable)
+                    || self.custom_args != custom.args_text()
+                    || self.custom_env != custom.env_text()
+                    || self.custom_boot_config != custom.boot_config
+            }
+            None => true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct SettingsSubmenuData {
-    pub selected_executable: Option<String>,
-    new_executable_name: String,
-    // new_executable_path: Option<PathBuf>,
-    // new_executable_assets: Option<PathBuf>,
-    // new_executable_variant: Option<ExecutableVariant>,
+    form: Option<ExecutableForm>,
+    /// Libraries of the edited executable that could not be resolved, or why the check failed
+    library_check: Option<Result<Vec<String>, String>>,
+    new_vanilla_install_name: String,
 }
 
 impl SettingsSubmenuData {
     pub fn new() -> Self {
         Self {
-            selected_executable: None,
-            new_executable_name: String::new(),
-            // new_executable_path: None,
-            // new_executable_assets: None,
-            // new_executable_variant: Some(ExecutableVariant::default()),
+            form: None,
+            library_check: None,
+            new_vanilla_install_name: String::new(),
         }
     }
 
-    pub fn update(
-        &mut self,
-        m: SettingsSubmenuMessage,
-        config: &mut SBIConfig,
-        dirs: &SBIDirectories,
-    ) -> Task<Message> {
+    pub fn update(&mut self, m: SettingsSubmenuMessage, config: &SBIConfig) -> Task<Message> {
         type M = SettingsSubmenuMessage;
+        let form = self.form.as_mut();
         match m {
             M::Exit => Task::done(Message::ButtonExitSubmenuPressed),
-            SettingsSubmenuMessage::SelectExecutable(s) => {
-                self.selected_executable = Some(s);
+            SettingsSubmenuMessage::SelectExecutable(name) => {
+                self.form = config
+                    .get_executable(&name)
+                    .map(|executable| ExecutableForm::edit(&name, executable));
+                self.library_check = None;
                 Task::none()
             }
-            SettingsSubmenuMessage::GenerateExectuable => {
-                let name = self.new_executable_name.clone();
-                // let path = self.new_executable_path.clone().unwrap();
-                let path = dirs.data_directory.clone();
-                // let assets = self
-                //     .new_executable_assets
-                //     .as_ref()
-                //     .map(|p| p.parent().unwrap().to_owned());
-                let assets = None;
-                let variant = ExecutableVariant::Vanilla;
-                let executable = Executable {
-                    bin: path,
-                    assets,
-                    variant,
-                };
-                Task::done(Message::WriteExecutable(name, executable))
+            SettingsSubmenuMessage::NewExecutable => {
+                self.form = Some(ExecutableForm::new());
+                self.library_check = None;
+                Task::none()
             }
             SettingsSubmenuMessage::DeleteExecutable => {
-                let name = self
-                    .selected_executable
-                    .take()
-                    .expect("REMOVE_BUTTON_CLICKED_WITHOUT_SELECTED_EXECUTABLE");
+                let Some(name) = self.form.take().and_then(|form| form.original_name) else {
+                    return Task::none();
+                };
                 Task::done(Message::RemoveExecutable(name))
             }
+            SettingsSubmenuMessage::SaveExecutable => {
+                let Some(form) = form else {
+                    return Task::none();
+                };
+                let name = form.name.trim().to_string();
+                let renamed = form.original_name.as_ref().is_some_and(|o| *o != name);
+                let is_new = form.original_name.is_none();
+                let custom_launch = CustomLaunch::parse(
+                    &form.custom_args,
+                    &form.custom_env,
+                    &form.custom_boot_config,
+                );
+                let validation = if name.is_empty() {
+                    Err(String::from("The executable needs a name"))
+                } else if (is_new || renamed) && config.executables.contains_key(&name) {
+                    Err(format!("An executable named {name} already exists"))
+                } else {
+                    form.executable.validate().map_err(|e| e.to_string())
+                };
+                let custom_launch = match (validation, custom_launch) {
+                    (Err(e), _) => Err(e),
+                    (Ok(()), r) => r.map_err(|e| e.to_string()),
+                };
+                match custom_launch {
+                    Ok(custom_launch) => form.executable.custom_launch = custom_launch,
+                    Err(e) => {
+                        form.error = Some(e);
+                        return Task::none();
+                    }
+                }
+                // Show the launch settings the way they were saved
+                form.custom_args = form.executable.custom_launch.args_text();
+                form.custom_env = form.executable.custom_launch.env_text();
+                form.custom_boot_config = form.executable.custom_launch.boot_config.clone();
+                form.error = None;
+                let write = Task::done(Message::WriteExecutable(
+                    name.clone(),
+                    form.executable.clone(),
+                ));
+                let task = match form.original_name.replace(name.clone()) {
+                    Some(original) if renamed => {
+                        Task::done(Message::RenameExecutable(original, name.clone())).chain(write)
+                    }
+                    _ => write,
+                };
+                form.name = name;
+                task
+            }
             SettingsSubmenuMessage::EditExecutableName(s) => {
-                self.new_executable_name = s;
+                if let Some(form) = form {
+                    form.name = s;
+                }
+                Task::none()
+            }
+            SettingsSubmenuMessage::EditExecutableVersion(s) => {
+                if let Some(form) = form {
+                    form.executable.version = (!s.is_empty()).then_some(s);
+                }
+                Task::none()
+            }
+            SettingsSubmenuMessage::EditExecutableNotes(s) => {
+                if let Some(form) = form {
+                    form.executable.notes = s;
+                }
                 Task::none()
             }
             // TODO: Should this be asynchronous?
             // User can open the picker multiple times...
             SettingsSubmenuMessage::PickExecutableBinary => {
-                async fn pick_executable() -> Option<PathBuf> {
-                    let file: Option<rfd::FileHandle> =
-                        rfd::AsyncFileDialog::new().pick_file().await;
-                    file.map(|f| f.path().to_path_buf())
-                }
-                Task::perform(pick_executable(), |r| {
+                Task::perform(pick_executable_binary(), |r| {
                     Message::SettingsMessage(SettingsSubmenuMessage::PickedExecutableBinary(r))
                 })
             }
             SettingsSubmenuMessage::PickedExecutableBinary(mby_file) => {
-                if let Some(path) = mby_file {
+                if let (Some(path), Some(form)) = (mby_file, form) {
                     log::info!("Picked file {}", path.display());
-                    if let Some(name) = self.selected_executable.as_ref() {
-                        if let Some(executable) = config.get_executable_mut(name) {
-                            executable.bin = path;
-                            return Task::done(Message::WriteExecutable(
-                                name.clone(),
-                                executable.clone(),
-                            ));
-                        };
+                    // Fill in what the release layout tells us, unless the user already did
+                    if form.executable.assets.is_empty() {
+                        let guess = Executable::from_binary(path.clone());
+                        form.executable.variant = guess.variant;
+                        form.executable.assets = guess.assets;
                     }
-                    // self.new_executable_path = Some(path);
+                    form.executable.bin = path;
+                    form.error = None;
                 }
                 Task::none()
             }
-            // TODO: Should this be asynchronous?
-            // User can open the picker multiple times...
-            SettingsSubmenuMessage::PickExecutableAssets => {
-                async fn pick_executable_assets() -> Option<PathBuf> {
-                    let file: Option<rfd::FileHandle> = rfd::AsyncFileDialog::new()
-                        .add_filter("Pak", &["pak"])
-                        .pick_file()
-                        .await;
-                    file.map(|f| f.path().to_path_buf())
+            SettingsSubmenuMessage::AddAssetDir => Task::perform(pick_folder(), |r| {
+                Message::SettingsMessage(SettingsSubmenuMessage::PickedAssetDir(r))
+            }),
+            SettingsSubmenuMessage::PickedAssetDir(mby_dir) => {
+                log::info!("Picked folder {mby_dir:?}");
+                if let (Some(dir), Some(form)) = (mby_dir, form) {
+                    form.executable.assets.push(dir);
                 }
-                Task::perform(pick_executable_assets(), |r| {
-                    Message::SettingsMessage(SettingsSubmenuMessage::PickedExecutableAssets(r))
-                })
+                Task::none()
             }
-            SettingsSubmenuMessage::PickedExecutableAssets(mby_file) => {
-                log::info!("Picked file {mby_file:?}");
-                if let Some(name) = self.selected_executable.as_ref() {
-                    if let Some(executable) = config.get_executable_mut(name) {
-                        executable.assets = mby_file;
-                        return Task::done(Message::WriteExecutable(
-                            name.clone(),
-                            executable.clone(),
-                        ));
-                    };
+            SettingsSubmenuMessage::RemoveAssetDir(i) => {
+                if let Some(form) = form.filter(|f| i < f.executable.assets.len()) {
+                    form.executable.assets.remove(i);
                 }
-                // self.new_executable_assets = Some(path);
                 Task::none()
             }
             SettingsSubmenuMessage::SelectExecutableVariant(variant) => {
-                // self.new_executable_variant.replace(variant);
-                if let Some(name) = self.selected_executable.as_ref() {
-                    if let Some(executable) = config.get_executable_mut(name) {
-                        executable.variant = variant;
-                        return Task::done(Message::WriteExecutable(
-                            name.clone(),
-                            executable.clone(),
-                        ));
-                    };
+                if let Some(form) = form {
+                    form.executable.variant = variant;
+                }
+                Task::none()
+            }
+            SettingsSubmenuMessage::EditCustomArgs(s) => {
+                if let Some(form) = form {
+                    form.custom_args = s;
+                }
+                Task::none()
+            }
+            SettingsSubmenuMessage::EditCustomEnv(s) => {
+                if let Some(form) = form {
+                    form.custom_env = s;
+                }
+                Task::none()
+            }
+            SettingsSubmenuMessage::EditCustomBootConfig(s) => {
+                if let Some(form) = form {
+                    form.custom_boot_config = s;
                 }
                 Task::none()
             }
+            SettingsSubmenuMessage::AddLibraryDir => Task::perform(pick_folder(), |r| {
+                Message::SettingsMessage(SettingsSubmenuMessage::PickedLibraryDir(r))
+            }),
+            SettingsSubmenuMessage::PickedLibraryDir(mby_dir) => {
+                if let (Some(dir), Some(form)) = (mby_dir, form) {
+                    form.executable.library_dirs.push(dir);
+                }
+                Task::none()
+            }
+            SettingsSubmenuMessage::RemoveLibraryDir(i) => {
+                if let Some(form) = form.filter(|f| i < f.executable.library_dirs.len()) {
+                    form.executable.library_dirs.remove(i);
+                }
+                Task::none()
+            }
+            SettingsSubmenuMessage::ToggleSteamRuntime(state) => {
+                if let Some(form) = form {
+                    form.executable.use_steam_runtime = state;
+                }
+                Task::none()
+            }
+            SettingsSubmenuMessage::CheckLibraries => {
+                let Some(executable) = form.map(|f| f.executable.clone()) else {
+                    return Task::none();
+                };
+                async fn check_libraries(executable: Executable) -> Result<Vec<String>, String> {
+                    tokio::task::spawn_blocking(move || {
+                        let search_path = libraries::library_search_path(&executable, &[]);
+                        libraries::unresolved_libraries(&executable.bin, &search_path)
+                            .map_err(|e| e.to_string())
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.to_string()))
+                }
+                Task::perform(check_libraries(executable), |r| {
+                    Message::SettingsMessage(SettingsSubmenuMessage::CheckedLibraries(r))
+                })
+            }
+            SettingsSubmenuMessage::CheckedLibraries(result) => {
+                self.library_check = Some(result);
+                Task::none()
+            }
+            SettingsSubmenuMessage::OpenVanillaSetup => {
+                Task::done(Message::ButtonVanillaSetupPressed)
+            }
+            SettingsSubmenuMessage::EditVanillaInstallName(s) => {
+                self.new_vanilla_install_name = s;
+                Task::none()
+            }
+            SettingsSubmenuMessage::AddVanillaInstall => {
+                async fn pick_game_folder() -> Option<PathBuf> {
+                    let folder: Option<rfd::FileHandle> = rfd::AsyncFileDialog::new()
+                        .set_title("Select the Starbound folder")
+                        .pick_folder()
+                        .await;
+                    folder.map(|f| f.path().to_path_buf())
+                }
+                Task::perform(pick_game_folder(), |r| {
+                    Message::SettingsMessage(SettingsSubmenuMessage::PickedVanillaInstall(r))
+                })
+            }
+            SettingsSubmenuMessage::PickedVanillaInstall(mby_dir) => {
+                let Some(directory) = mby_dir else {
+                    return Task::none();
+                };
+                let install = VanillaInstall { directory };
+                if !install.assets().is_dir() {
+                    log::error!(
+                        "{} is not a Starbound folder, it has no assets folder",
+                        install.directory.display()
+                    );
+                    return Task::none();
+                }
+                let name = std::mem::take(&mut self.new_vanilla_install_name);
+                Task::done(Message::WriteVanillaInstall(name, install))
+            }
+            SettingsSubmenuMessage::RemoveVanillaInstall(name) => {
+                Task::done(Message::RemoveVanillaInstall(name))
+            }
             SettingsSubmenuMessage::ToggleCloseOnLaunch(state) => {
                 Task::done(Message::ToggleCloseOnLaunch(state))
             }
+            SettingsSubmenuMessage::ToggleLintBeforeLaunch(state) => {
+                Task::done(Message::ToggleLintBeforeLaunch(state))
+            }
         }
     }
 
     pub fn view<'a>(&'a self, root: &'a Application) -> Element<'a, SettingsSubmenuMessage> {
         type M = SettingsSubmenuMessage;
+        let selected_name = self
+            .form
+            .as_ref()
+            .and_then(|form| form.original_name.as_ref());
         let executable_to_element =
             |(i, executable_name): (usize, &'a String)| -> (usize, Element<'a, SettingsSubmenuMessage>) {
-                let color = self
-                    .selected_executable
-                    .as_ref()
+                let color = selected_name
                     .filter(|selected| selected.as_str() == executable_name.as_str())
                     .and(Some(iced::color!(0x00ff00)));
-                let clickable = widget::mouse_area(widget::row![
-                    widget::text(executable_name).color_maybe(color)
-                ])
-                .on_press(M::SelectExecutable(
-                    executable_name.to_string(),
-                ));
+                let version = root
+                    .executables()
+                    .get(executable_name)
+                    .and_then(|e| e.version.as_deref())
+                    .map(|version| widget::text!(" {version}").size(12));
+                let clickable = widget::mouse_area(
+                    widget::row![widget::text(executable_name).color_maybe(color)]
+                        .push_maybe(version)
+                        .align_y(iced::alignment::Vertical::Center),
+                )
+                .on_press(M::SelectExecutable(executable_name.to_string()));
                 (i, clickable.into())
             };
+        let mut executable_names: Vec<&String> = root.executables().keys().collect();
+        executable_names.sort();
         let executables = widget::keyed_column(
-            root.executables()
-                .keys()
+            executable_names
+                .into_iter()
                 .enumerate()
                 .map(executable_to_element),
         );
 
-        let selected_executable = self
-            .selected_executable
+        let executable_form = self
+            .form
             .as_ref()
-            .and_then(|n| root.config().get_executable(n));
-        let (
-            remove_button_action,
-            executable_variant,
-            executable_bin,
-            executable_assets,
-            pick_binary_action,
-            pick_assets_action,
-        ) = if let Some(executable) = selected_executable {
-            (
-                Some(M::DeleteExecutable),
-                executable.variant.clone(),
-                Some(executable.bin.clone()),
-                executable.assets.clone(),
-                Some(M::PickExecutableBinary),
-                Some(M::PickExecutableAssets),
-            )
-        } else {
-            (None, ExecutableVariant::Vanilla, None, None, None, None)
-        };
-        let pick_variant = widget::pick_list(
-            ExecutableVariant::options(),
-            Some(executable_variant),
-            M::SelectExecutableVariant,
-        );
-        let add_button_action =
-            (!self.new_executable_name.is_empty()).then_some(M::GenerateExectuable);
+            .map(|form| self.view_executable_form(form, root));
 
-        let edit_name =
-            widget::text_input("-Name-", &self.new_executable_name).on_input(M::EditExecutableName);
-
-        widget::column![
-            widget::text("Settings"),
+        // Vanilla Installs
+        let version_text = |install: Option<&str>| -> String {
+            root.game_version(install)
+                .unwrap_or("unknown version, run it once")
+                .to_string()
+        };
+        let default_install = match root.dirs().vanilla_assets() {
+            Some(assets) => widget::text!("Default: {} ({})", assets.display(), version_text(None)),
+            None => widget::text("Default: none, profiles can't launch")
+                .color(iced::Color::from_rgb(0.9, 0.3, 0.3)),
+        };
+        let mut install_names: Vec<&String> = root.config().vanilla_installs.keys().collect();
+        install_names.sort();
+        let vanilla_installs = install_names.into_iter().map(|name| {
+            let install = &root.config().vanilla_installs[name];
             widget::row![
-                widget::button("Add").on_press_maybe(add_button_action),
-                edit_name,
+                widget::text!(
+                    "{name}: {} ({})",
+                    install.directory.display(),
+                    version_text(Some(name))
+                )
+                .width(iced::Fill),
+                widget::button("Remove").on_press(M::RemoveVanillaInstall(name.clone())),
             ]
-            .spacing(5),
+            .spacing(5)
+            .into()
+        });
+        let add_install_action =
+            (!self.new_vanilla_install_name.is_empty()).then_some(M::AddVanillaInstall);
+
+        let settings = widget::column![
             widget::column![
                 widget::text("Executables"),
                 widget::horizontal_rule(2),
-                widget::scrollable(executables),
+                executables,
                 widget::horizontal_rule(2)
             ],
-            widget::row![widget::button("Remove").on_press_maybe(remove_button_action)].spacing(5),
+            widget::button("New Executable").on_press(M::NewExecutable),
+        ]
+        .push_maybe(executable_form)
+        .push(widget::text("Vanilla Installs"))
+        .push(
             widget::row![
-                widget::button("Pick Executable").on_press_maybe(pick_binary_action),
-                widget::text!("{:?}", executable_bin),
+                default_install.width(iced::Fill),
+                widget::button("Setup").on_press(M::OpenVanillaSetup),
             ]
-            .spacing(5),
+            .spacing(5)
+            .align_y(iced::alignment::Vertical::Center),
+        )
+        .push(widget::column(vanilla_installs).spacing(3))
+        .push(
             widget::row![
-                widget::button("Pick Assets").on_press_maybe(pick_assets_action),
-                widget::text!("{:?}", executable_assets),
+                widget::button("Add Install").on_press_maybe(add_install_action),
+                widget::text_input("-Name-", &self.new_vanilla_install_name)
+                    .on_input(M::EditVanillaInstallName),
             ]
             .spacing(5),
-            pick_variant,
+        )
+        .push(
             widget::checkbox("Close on Launch", root.config().close_on_launch)
                 .on_toggle(M::ToggleCloseOnLaunch),
-            widget::vertical_space(),
-            widget::button("Close").on_press(M::Exit)
+        )
+        .push(
+            widget::checkbox("Lint Mods Before Launch", root.config().lint_before_launch)
+                .on_toggle(M::ToggleLintBeforeLaunch),
+        )
+        .spacing(5);
+
+        widget::column![
+            widget::text("Settings"),
+            widget::scrollable(settings).height(iced::Fill),
+            widget::button("Close").on_press(M::Exit),
         ]
         .spacing(5)
         .padding(5)
         .into()
     }
+
+    /// Editor for the executable being created or edited
+    fn view_executable_form<'a>(
+        &'a self,
+        form: &'a ExecutableForm,
+        root: &'a Application,
+    ) -> Element<'a, SettingsSubmenuMessage> {
+        type M = SettingsSubmenuMessage;
+        let executable = &form.executable;
+        let folder_rows = |dirs: &'a [PathBuf], remove: fn(usize) -> M| {
+            widget::column(dirs.iter().enumerate().map(move |(i, dir)| {
+                widget::row![
+                    widget::text!("{}", dir.display()).width(iced::Fill),
+                    widget::button("Remove").on_press(remove(i)),
+                ]
+                .spacing(5)
+                .into()
+            }))
+            .spacing(3)
+        };
+        let bin = if executable.bin.as_os_str().is_empty() {
+            String::from("No binary selected")
+        } else {
+            executable.bin.display().to_string()
+        };
+        let library_check = self.library_check.as_ref().map(|result| match result {
+            Ok(missing) if missing.is_empty() => widget::text("All libraries found"),
+            Ok(missing) => widget::text!("Missing libraries: {}", missing.join(", "))
+                .color(iced::Color::from_rgb(0.9, 0.3, 0.3)),
+            Err(e) => widget::text!("Could not check libraries: {e}"),
+        });
+        let error = form.error.as_ref().map(|e| {
+            widget::text(e)
+                .size(12)
+                .color(iced::Color::from_rgb(0.9, 0.3, 0.3))
+        });
+        // Forks sbi doesn't know about describe how they are launched themselves
+        let custom_launch = (executable.variant == ExecutableVariant::Custom).then(|| {
+            widget::column![
+                widget::text("Launch settings"),
+                widget::text_input("-Arguments, e.g. -noworkshop-", &form.custom_args)
+                    .on_input(M::EditCustomArgs),
+                widget::text_input(
+                    "-Environment, e.g. KEY=value OTHER=value-",
+                    &form.custom_env
+                )
+                .on_input(M::EditCustomEnv),
+                widget::text_input(
+                    "-Boot config json, e.g. {\"includeUGC\": false}-",
+                    &form.custom_boot_config
+                )
+                .on_input(M::EditCustomBootConfig),
+            ]
+            .spacing(5)
+        });
+        let save_action = form.is_modified(root.config()).then_some(M::SaveExecutable);
+        let delete_action = form.original_name.is_some().then_some(M::DeleteExecutable);
+
+        widget::column![
+            widget::text(if form.original_name.is_some() {
+                "Edit Executable"
+            } else {
+                "New Executable"
+            }),
+            widget::text_input("-Name-", &form.name).on_input(M::EditExecutableName),
+            widget::row![
+                widget::button("Pick Executable").on_press(M::PickExecutableBinary),
+                widget::text(bin),
+            ]
+            .spacing(5)
+            .align_y(iced::alignment::Vertical::Center),
+            widget::row![
+                widget::pick_list(
+                    ExecutableVariant::options(),
+                    Some(executable.variant.clone()),
+                    M::SelectExecutableVariant,
+                ),
+                widget::text_input("-Version-", executable.version.as_deref().unwrap_or(""))
+                    .on_input(M::EditExecutableVersion),
+            ]
+            .spacing(5),
+            widget::text_input("-Notes-", &executable.notes).on_input(M::EditExecutableNotes),
+        ]
+        .push_maybe(custom_launch)
+        .push(widget::text("Asset folders"))
+        .push(folder_rows(&executable.assets, M::RemoveAssetDir))
+        .push(widget::button("Add Asset Folder").on_press(M::AddAssetDir))
+        .push(widget::text("Library folders"))
+        .push(folder_rows(&executable.library_dirs, M::RemoveLibraryDir))
+        .push(
+            widget::row![
+                widget::button("Add Library Folder").on_press(M::AddLibraryDir),
+                widget::checkbox("Use Steam runtime libraries", executable.use_steam_runtime)
+                    .on_toggle(M::ToggleSteamRuntime),
+                widget::button("Check Libraries").on_press(M::CheckLibraries),
+            ]
+            .spacing(5)
+            .align_y(iced::alignment::Vertical::Center),
+        )
+        .push_maybe(library_check)
+        .push_maybe(error)
+        .push(
+            widget::row![
+                widget::button("Delete")
+                    .on_press_maybe(delete_action)
+                    .style(widget::button::danger),
+                widget::horizontal_space(),
+                widget::button("Save").on_press_maybe(save_action),
+            ]
+            .spacing(5),
+        )
+        .spacing(5)
+        .into()
+    }
 }
@@ -0,0 +1,100 @@
+// Trash Submenu
+
+use iced::{widget, Element, Task};
+
+use crate::{
+    application::{Application, Message},
+    history,
+    trash::TrashedProfile,
+};
+
+#[derive(Debug, Clone)]
+pub enum TrashSubmenuMessage {
+    Loaded(Vec<TrashedProfile>),
+    Restore(usize),
+    EmptyTrash,
+    ConfirmEmptyTrash,
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TrashSubmenuData {
+    /// None until the trash folder has been read
+    trashed: Option<Vec<TrashedProfile>>,
+    /// Emptying the trash asks for a second click
+    confirm_empty: bool,
+}
+
+impl TrashSubmenuData {
+    pub fn new() -> Self {
+        Self {
+            trashed: None,
+            confirm_empty: false,
+        }
+    }
+
+    pub fn update(&mut self, m: TrashSubmenuMessage) -> Task<Message> {
+        type M = TrashSubmenuMessage;
+        match m {
+            M::Loaded(trashed) => {
+                self.trashed = Some(trashed);
+                self.confirm_empty = false;
+                Task::none()
+            }
+            M::Restore(i) => match self.trashed.as_ref().and_then(|t| t.get(i)) {
+                Some(trashed) => Task::done(Message::RestoreProfile(trashed.clone())),
+                None => Task::none(),
+            },
+            M::EmptyTrash => {
+                self.confirm_empty = true;
+                Task::none()
+            }
+            M::ConfirmEmptyTrash => Task::done(Message::EmptyTrash),
+            M::Exit => Task::done(Message::ButtonExitSubmenuPressed),
+        }
+    }
+
+    pub fn view<'a>(&'a self, _root: &'a Application) -> Element<'a, TrashSubmenuMessage> {
+        type M = TrashSubmenuMessage;
+        let trashed: Element<'a, M> = match &self.trashed {
+            None => widget::text("Reading trash...").into(),
+            Some(trashed) if trashed.is_empty() => widget::text("The trash is empty").into(),
+            Some(trashed) => {
+                let rows = trashed.iter().enumerate().map(|(i, t)| {
+                    let deleted = std::time::SystemTime::UNIX_EPOCH
+                        + std::time::Duration::from_secs(t.metadata.deleted);
+                    widget::row![
+                        widget::text(&t.metadata.name).width(iced::Fill),
+                        widget::text!("Deleted {}", history::format_time_ago(deleted)),
+                        widget::button("Restore").on_press(M::Restore(i)),
+                    ]
+                    .spacing(5)
+                    .align_y(iced::alignment::Vertical::Center)
+                    .into()
+                });
+                widget::scrollable(widget::column(rows).spacing(3)).into()
+            }
+        };
+        let has_trash = self.trashed.as_ref().is_some_and(|t| !t.is_empty());
+        let empty_button = if self.confirm_empty {
+            widget::button("Permanently delete everything?")
+                .on_press(M::ConfirmEmptyTrash)
+                .style(widget::button::danger)
+        } else {
+            widget::button("Empty Trash").on_press_maybe(has_trash.then_some(M::EmptyTrash))
+        };
+        widget::column![
+            widget::text("Trash"),
+            trashed,
+            widget::vertical_space(),
+            widget::row![
+                widget::button("Close").on_press(M::Exit),
+                widget::horizontal_space(),
+                empty_button,
+            ]
+        ]
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+}
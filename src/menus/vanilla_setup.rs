@@ -0,0 +1,175 @@
+// Vanilla Setup Submenu
+
+use std::path::PathBuf;
+
+use iced::{widget, Element, Task};
+
+use crate::{
+    application::{Application, Message},
+    vanilla::{self, VanillaPaths},
+};
+
+/// Which of the vanilla folders a message refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VanillaFolder {
+    Assets,
+    Storage,
+    Mods,
+}
+
+#[derive(Debug, Clone)]
+pub enum VanillaSetupSubmenuMessage {
+    Edit(VanillaFolder, String),
+    Browse(VanillaFolder),
+    Picked(VanillaFolder, Option<PathBuf>),
+    AutoDetect,
+    Detected(Option<VanillaPaths>),
+    Save,
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VanillaSetupSubmenuData {
+    assets: String,
+    storage: String,
+    mods: String,
+    /// Why the entered folders were rejected, or that auto-detection found nothing
+    error: Option<String>,
+}
+
+impl VanillaSetupSubmenuData {
+    /// Starts out with the current default install, if there is one
+    pub fn new(current: Option<&VanillaPaths>) -> Self {
+        let display = |p: Option<&PathBuf>| p.map(|p| p.display().to_string()).unwrap_or_default();
+        Self {
+            assets: display(current.map(|c| &c.assets)),
+            storage: display(current.and_then(|c| c.storage.as_ref())),
+            mods: display(current.and_then(|c| c.mods.as_ref())),
+            error: None,
+        }
+    }
+
+    fn field_mut(&mut self, folder: VanillaFolder) -> &mut String {
+        match folder {
+            VanillaFolder::Assets => &mut self.assets,
+            VanillaFolder::Storage => &mut self.storage,
+            VanillaFolder::Mods => &mut self.mods,
+        }
+    }
+
+    /// The entered folders, empty storage and mods fields are left out
+    fn paths(&self) -> VanillaPaths {
+        let optional = |s: &str| (!s.trim().is_empty()).then(|| PathBuf::from(s.trim()));
+        VanillaPaths {
+            assets: PathBuf::from(self.assets.trim()),
+            storage: optional(&self.storage),
+            mods: optional(&self.mods),
+        }
+    }
+
+    pub fn update(&mut self, m: VanillaSetupSubmenuMessage) -> Task<Message> {
+        type M = VanillaSetupSubmenuMessage;
+        match m {
+            M::Edit(folder, s) => {
+                *self.field_mut(folder) = s;
+                self.error = None;
+                Task::none()
+            }
+            M::Browse(folder) => {
+                async fn pick_folder() -> Option<PathBuf> {
+                    let folder: Option<rfd::FileHandle> =
+                        rfd::AsyncFileDialog::new().pick_folder().await;
+                    folder.map(|f| f.path().to_path_buf())
+                }
+                Task::perform(pick_folder(), move |r| M::Picked(folder, r).into())
+            }
+            M::Picked(folder, Some(path)) => {
+                *self.field_mut(folder) = path.display().to_string();
+                self.error = None;
+                Task::none()
+            }
+            M::Picked(_, None) => Task::none(),
+            M::AutoDetect => Task::perform(vanilla::detect_paths(), |r| M::Detected(r).into()),
+            M::Detected(Some(paths)) => {
+                *self = Self::new(Some(&paths));
+                Task::none()
+            }
+            M::Detected(None) => {
+                self.error = Some(String::from(
+                    "Starbound could not be found in any steam library, please pick the folders",
+                ));
+                Task::none()
+            }
+            M::Save => {
+                let paths = self.paths();
+                match paths.validate() {
+                    Ok(()) => Task::done(Message::WriteVanillaPaths(paths))
+                        .chain(Task::done(Message::ButtonExitSubmenuPressed)),
+                    Err(e) => {
+                        self.error = Some(e.to_string());
+                        Task::none()
+                    }
+                }
+            }
+            M::Exit => Task::done(Message::ButtonExitSubmenuPressed),
+        }
+    }
+
+    pub fn view<'a>(&'a self, root: &'a Application) -> Element<'a, VanillaSetupSubmenuMessage> {
+        type M = VanillaSetupSubmenuMessage;
+        let folder_row =
+            |label: &'a str, placeholder: &'a str, folder: VanillaFolder, value: &'a str| {
+                widget::row![
+                    widget::text(label).width(70),
+                    widget::text_input(placeholder, value).on_input(move |s| M::Edit(folder, s)),
+                    widget::button("Browse").on_press(M::Browse(folder)),
+                ]
+                .spacing(5)
+                .align_y(iced::alignment::Vertical::Center)
+            };
+        let intro = if root.dirs().vanilla().is_some() {
+            "Pick the Starbound install profiles load their vanilla assets from."
+        } else {
+            "No Starbound install was found. Every profile needs the vanilla assets to launch, \
+             pick the folders of your Starbound install or let sbi look for it in steam."
+        };
+        let error = self.error.as_ref().map(|e| {
+            widget::text(e)
+                .size(12)
+                .color(iced::Color::from_rgb(0.9, 0.3, 0.3))
+        });
+        widget::column![
+            widget::text("Vanilla Setup"),
+            widget::text(intro).size(12),
+            folder_row(
+                "Assets",
+                "--Starbound/assets--",
+                VanillaFolder::Assets,
+                &self.assets
+            ),
+            folder_row(
+                "Storage",
+                "--Optional, Starbound/storage--",
+                VanillaFolder::Storage,
+                &self.storage
+            ),
+            folder_row(
+                "Mods",
+                "--Optional, Starbound/mods--",
+                VanillaFolder::Mods,
+                &self.mods
+            ),
+            widget::button("Auto-detect").on_press(M::AutoDetect),
+        ]
+        .push_maybe(error)
+        .push(widget::vertical_space())
+        .push(widget::row![
+            widget::button("Later").on_press(M::Exit),
+            widget::horizontal_space(),
+            widget::button("Save").on_press(M::Save),
+        ])
+        .spacing(5)
+        .padding(5)
+        .into()
+    }
+}
@@ -0,0 +1,435 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::{vanilla::VanillaPaths, PROFILE_JSON_NAME};
+
+/// Current version of the `config.json` layout written by sbi.
+pub const CONFIG_SCHEMA_VERSION: u32 = 2;
+/// Current version of the `profile.json` layout written by sbi.
+pub const PROFILE_SCHEMA_VERSION: u32 = 2;
+
+/// Name of the json file the TUI wrote into each instance folder
+static LEGACY_INSTANCE_JSON_NAME: &str = "instance.json";
+/// Name of the folder the TUI stored its instances in, relative to the data directory
+static LEGACY_INSTANCES_DIR_NAME: &str = "instances";
+
+/// A single upgrade step, taking a json object from version `n` to version `n + 1`.
+type Migration = fn(&mut Map<String, Value>);
+
+/// Config migrations, indexed by the version they upgrade from.
+const CONFIG_MIGRATIONS: [Migration; CONFIG_SCHEMA_VERSION as usize] =
+    [config_v0_to_v1, config_v1_to_v2];
+/// Profile migrations, indexed by the version they upgrade from.
+const PROFILE_MIGRATIONS: [Migration; PROFILE_SCHEMA_VERSION as usize] =
+    [profile_v0_to_v1, profile_v1_to_v2];
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("Expected a json object at the root of the file")]
+    NotAnObject,
+    #[error("Invalid schema_version field: {0}")]
+    InvalidVersion(Value),
+    #[error("Schema version {found} is newer than the supported version {supported}, refusing to downgrade")]
+    FromTheFuture { found: u32, supported: u32 },
+}
+
+/// Upgrades a parsed `config.json` to [CONFIG_SCHEMA_VERSION].
+/// Returns whether any migration was applied, in which case the file should be re-written.
+pub fn migrate_config(value: &mut Value) -> Result<bool, MigrationError> {
+    migrate(value, &CONFIG_MIGRATIONS)
+}
+
+/// Upgrades a parsed `profile.json` (or a legacy `instance.json`) to [PROFILE_SCHEMA_VERSION].
+/// Returns whether any migration was applied, in which case the file should be re-written.
+pub fn migrate_profile(value: &mut Value) -> Result<bool, MigrationError> {
+    migrate(value, &PROFILE_MIGRATIONS)
+}
+
+/// Runs every migration from the version stored in `schema_version` up to the latest one.
+/// Files without a `schema_version` field predate versioning and are treated as version 0.
+fn migrate(value: &mut Value, migrations: &[Migration]) -> Result<bool, MigrationError> {
+    let object = value.as_object_mut().ok_or(MigrationError::NotAnObject)?;
+    let supported = migrations.len() as u32;
+    let found = match object.get("schema_version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| MigrationError::InvalidVersion(v.clone()))?,
+    };
+    if found > supported {
+        return Err(MigrationError::FromTheFuture { found, supported });
+    }
+    for (version, migration) in migrations.iter().enumerate().skip(found as usize) {
+        log::info!(
+            "Migrating json from schema version {version} to {}",
+            version + 1
+        );
+        migration(object);
+        object.insert(String::from("schema_version"), Value::from(version + 1));
+    }
+    Ok(found < supported)
+}
+
+/// v0 covers both the unversioned GUI config and the TUI config.
+/// The TUI stored executable assets as `custom_assets`, used an empty string as "no default
+/// executable" and kept a global `vanilla_assets` path, which becomes the vanilla install of the
+/// setup wizard.
+fn config_v0_to_v1(config: &mut Map<String, Value>) {
+    if let Some(Value::Object(executables)) = config.get_mut("executables") {
+        for executable in executables.values_mut().filter_map(Value::as_object_mut) {
+            if let Some(assets) = executable.remove("custom_assets") {
+                executable.entry("assets").or_insert(assets);
+            }
+        }
+    } else {
+        config.insert(String::from("executables"), Value::Object(Map::new()));
+    }
+    if config
+        .get("default_executable")
+        .and_then(Value::as_str)
+        .is_some_and(str::is_empty)
+    {
+        config.insert(String::from("default_executable"), Value::Null);
+    }
+    let vanilla_assets = config.remove("vanilla_assets");
+    if let Some(Value::String(assets)) = vanilla_assets.filter(|_| !config.contains_key("vanilla"))
+    {
+        let assets = PathBuf::from(assets);
+        // The storage and mods folders sit next to the assets of a regular game install
+        let paths = match assets.parent() {
+            Some(game_directory) if assets.ends_with("assets") => {
+                VanillaPaths::from_game_directory(game_directory)
+            }
+            _ => VanillaPaths {
+                assets,
+                storage: None,
+                mods: None,
+            },
+        };
+        if let Ok(paths) = serde_json::to_value(paths) {
+            config.insert(String::from("vanilla"), paths);
+        }
+    }
+}
+
+/// v2 allows several asset folders per executable, `assets` changes from a path or null to a list.
+fn config_v1_to_v2(config: &mut Map<String, Value>) {
+    let Some(Value::Object(executables)) = config.get_mut("executables") else {
+        return;
+    };
+    for executable in executables.values_mut().filter_map(Value::as_object_mut) {
+        let assets = match executable.remove("assets") {
+            Some(Value::String(path)) => vec![Value::String(path)],
+            Some(Value::Array(paths)) => paths,
+            _ => vec![],
+        };
+        executable.insert(String::from("assets"), Value::Array(assets));
+    }
+}
+
+/// v0 covers both the unversioned GUI profile and the TUI instance.
+/// The TUI stored the chosen executable as `executable`.
+fn profile_v0_to_v1(profile: &mut Map<String, Value>) {
+    if let Some(executable) = profile.remove("executable") {
+        profile.entry("selected_executable").or_insert(executable);
+    }
+}
+
+/// v2 gives every profile a stable id, profiles were previously only identified by their folder.
+fn profile_v1_to_v2(profile: &mut Map<String, Value>) {
+    profile
+        .entry("id")
+        .or_insert_with(|| Value::String(uuid::Uuid::new_v4().to_string()));
+}
+
+/// Moves every instance from the TUI-era `instances` directory into the profiles directory.
+///
+/// The TUI kept the game's storage in an `instance/storage` subfolder and loaded `instance/mods` as
+/// an asset source, whereas profiles use the profile folder itself as storage.
+/// The storage contents are moved up a level and the mods folder is kept as an additional asset.
+pub fn migrate_legacy_instances(data_dir: &Path, profiles_dir: &Path) -> std::io::Result<()> {
+    let instances_dir = data_dir.join(LEGACY_INSTANCES_DIR_NAME);
+    if !instances_dir.is_dir() {
+        return Ok(());
+    }
+    for entry in instances_dir.read_dir()?.filter_map(Result::ok) {
+        let instance_path = entry.path();
+        if !instance_path.is_dir() {
+            continue;
+        }
+        if let Err(e) = migrate_legacy_instance(&instance_path, profiles_dir) {
+            log::error!(
+                "Failed to migrate legacy instance {}: {e}",
+                instance_path.display()
+            );
+        }
+    }
+    // Only succeeds once every instance has been moved out
+    if std::fs::remove_dir(&instances_dir).is_ok() {
+        log::info!("Removed empty legacy instances directory");
+    }
+    Ok(())
+}
+
+fn migrate_legacy_instance(instance_path: &Path, profiles_dir: &Path) -> anyhow::Result<()> {
+    let json_path = [PROFILE_JSON_NAME, LEGACY_INSTANCE_JSON_NAME]
+        .into_iter()
+        .map(|name| instance_path.join(name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| anyhow::anyhow!("No instance json found"))?;
+    let mut json: Value = serde_json::from_slice(&std::fs::read(&json_path)?)?;
+    migrate_profile(&mut json)?;
+    let name = json
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("Instance has no name"))?
+        .to_string();
+
+    let profile_path = crate::profile::find_valid_profile_path(&name, profiles_dir);
+    log::info!(
+        "Migrating legacy instance {} to {}",
+        instance_path.display(),
+        profile_path.display()
+    );
+    std::fs::rename(instance_path, &profile_path)?;
+
+    let storage_path = profile_path.join("storage");
+    if storage_path.is_dir() {
+        for entry in storage_path.read_dir()?.filter_map(Result::ok) {
+            std::fs::rename(entry.path(), profile_path.join(entry.file_name()))?;
+        }
+        std::fs::remove_dir(&storage_path)?;
+    }
+
+    // Relative additional assets were resolved against the instance folder
+    let object = json.as_object_mut().ok_or(MigrationError::NotAnObject)?;
+    let mut additional_assets: Vec<PathBuf> = object
+        .get("additional_assets")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(|p| profile_path.join(p))
+        .collect();
+    let mods_path = profile_path.join("mods");
+    if mods_path.is_dir() {
+        additional_assets.push(mods_path);
+    }
+    if !additional_assets.is_empty() {
+        object.insert(
+            String::from("additional_assets"),
+            serde_json::to_value(additional_assets)?,
+        );
+    }
+
+    let legacy_json_path = profile_path.join(LEGACY_INSTANCE_JSON_NAME);
+    if legacy_json_path.exists() {
+        std::fs::remove_file(legacy_json_path)?;
+    }
+    std::fs::write(
+        profile_path.join(PROFILE_JSON_NAME),
+        serde_json::to_vec(&json)?,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn config_v0_to_v1_renames_tui_fields() {
+        let mut config = json!({
+            "executables": { "xstarbound": { "bin": "/xs/starbound", "custom_assets": "/xs/assets" } },
+            "default_executable": "",
+            "vanilla_assets": "/steam/starbound/assets",
+        });
+        config_v0_to_v1(config.as_object_mut().unwrap());
+        assert_eq!(
+            config,
+            json!({
+                "executables": { "xstarbound": { "bin": "/xs/starbound", "assets": "/xs/assets" } },
+                "default_executable": null,
+                "vanilla": {
+                    "assets": "/steam/starbound/assets",
+                    "storage": "/steam/starbound/storage",
+                    "mods": "/steam/starbound/mods",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn config_v0_to_v1_keeps_vanilla_assets_outside_a_game_folder() {
+        let mut config = json!({ "executables": {}, "vanilla_assets": "/unpacked" });
+        config_v0_to_v1(config.as_object_mut().unwrap());
+        assert_eq!(
+            config["vanilla"],
+            json!({ "assets": "/unpacked", "storage": null, "mods": null })
+        );
+    }
+
+    #[test]
+    fn config_v0_to_v1_adds_missing_executables() {
+        let mut config = json!({ "default_executable": "opensb" });
+        config_v0_to_v1(config.as_object_mut().unwrap());
+        assert_eq!(
+            config,
+            json!({ "executables": {}, "default_executable": "opensb" })
+        );
+    }
+
+    #[test]
+    fn config_v1_to_v2_turns_assets_into_lists() {
+        let mut config = json!({
+            "executables": {
+                "single": { "assets": "/a" },
+                "none": { "assets": null },
+                "missing": {},
+                "list": { "assets": ["/a", "/b"] },
+            },
+        });
+        config_v1_to_v2(config.as_object_mut().unwrap());
+        assert_eq!(
+            config,
+            json!({
+                "executables": {
+                    "single": { "assets": ["/a"] },
+                    "none": { "assets": [] },
+                    "missing": { "assets": [] },
+                    "list": { "assets": ["/a", "/b"] },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn profile_v0_to_v1_renames_executable() {
+        let mut profile = json!({ "name": "modded", "executable": "opensb" });
+        profile_v0_to_v1(profile.as_object_mut().unwrap());
+        assert_eq!(
+            profile,
+            json!({ "name": "modded", "selected_executable": "opensb" })
+        );
+    }
+
+    #[test]
+    fn profile_v1_to_v2_keeps_existing_ids() {
+        let mut profile = json!({ "name": "modded" });
+        profile_v1_to_v2(profile.as_object_mut().unwrap());
+        let id = profile["id"].as_str().expect("no id generated").to_string();
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+
+        profile_v1_to_v2(profile.as_object_mut().unwrap());
+        assert_eq!(profile["id"], json!(id));
+    }
+
+    #[test]
+    fn migrate_runs_every_step_from_unversioned() {
+        let mut config = json!({
+            "executables": { "xstarbound": { "bin": "/xs/starbound", "custom_assets": "/xs/assets" } },
+        });
+        assert!(migrate_config(&mut config).unwrap());
+        assert_eq!(config["schema_version"], json!(CONFIG_SCHEMA_VERSION));
+        assert_eq!(
+            config["executables"]["xstarbound"]["assets"],
+            json!(["/xs/assets"])
+        );
+
+        let mut profile = json!({ "name": "modded", "executable": "opensb" });
+        assert!(migrate_profile(&mut profile).unwrap());
+        assert_eq!(profile["schema_version"], json!(PROFILE_SCHEMA_VERSION));
+        assert_eq!(profile["selected_executable"], json!("opensb"));
+        assert!(profile["id"].is_string());
+    }
+
+    #[test]
+    fn migrate_skips_applied_steps() {
+        // A v1 config's string assets are only turned into a list, not renamed again
+        let mut config = json!({
+            "schema_version": 1,
+            "executables": { "opensb": { "custom_assets": "/kept", "assets": "/a" } },
+        });
+        assert!(migrate_config(&mut config).unwrap());
+        assert_eq!(
+            config["executables"]["opensb"],
+            json!({ "custom_assets": "/kept", "assets": ["/a"] })
+        );
+
+        let mut current = json!({ "schema_version": CONFIG_SCHEMA_VERSION, "executables": {} });
+        let before = current.clone();
+        assert!(!migrate_config(&mut current).unwrap());
+        assert_eq!(current, before);
+    }
+
+    #[test]
+    fn migrate_rejects_invalid_files() {
+        assert!(matches!(
+            migrate_config(&mut json!([])),
+            Err(MigrationError::NotAnObject)
+        ));
+        assert!(matches!(
+            migrate_config(&mut json!({ "schema_version": "2" })),
+            Err(MigrationError::InvalidVersion(_))
+        ));
+        assert!(matches!(
+            migrate_profile(&mut json!({ "schema_version": PROFILE_SCHEMA_VERSION + 1 })),
+            Err(MigrationError::FromTheFuture { .. })
+        ));
+    }
+
+    #[test]
+    fn legacy_instances_become_profiles() {
+        let data = tempfile::tempdir().unwrap();
+        let profiles = data.path().join("profiles");
+        std::fs::create_dir(&profiles).unwrap();
+        let instance = data.path().join(LEGACY_INSTANCES_DIR_NAME).join("modded");
+        std::fs::create_dir_all(instance.join("storage/player")).unwrap();
+        std::fs::create_dir_all(instance.join("mods")).unwrap();
+        std::fs::write(instance.join("storage/player/save"), "save").unwrap();
+        std::fs::write(
+            instance.join(LEGACY_INSTANCE_JSON_NAME),
+            json!({ "name": "My Mods", "executable": "opensb", "additional_assets": ["extra"] })
+                .to_string(),
+        )
+        .unwrap();
+
+        migrate_legacy_instances(data.path(), &profiles).unwrap();
+
+        let profile = profiles.join("My_Mods");
+        assert!(!data.path().join(LEGACY_INSTANCES_DIR_NAME).exists());
+        assert_eq!(
+            std::fs::read_to_string(profile.join("player/save")).unwrap(),
+            "save"
+        );
+        assert!(!profile.join("storage").exists());
+        assert!(!profile.join(LEGACY_INSTANCE_JSON_NAME).exists());
+        let json: Value =
+            serde_json::from_slice(&std::fs::read(profile.join(PROFILE_JSON_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(json["selected_executable"], json!("opensb"));
+        assert_eq!(json["schema_version"], json!(PROFILE_SCHEMA_VERSION));
+        assert_eq!(
+            json["additional_assets"],
+            json!([profile.join("extra"), profile.join("mods")])
+        );
+    }
+
+    #[test]
+    fn legacy_instances_without_json_are_left_alone() {
+        let data = tempfile::tempdir().unwrap();
+        let profiles = data.path().join("profiles");
+        std::fs::create_dir(&profiles).unwrap();
+        let instance = data.path().join(LEGACY_INSTANCES_DIR_NAME).join("broken");
+        std::fs::create_dir_all(&instance).unwrap();
+
+        migrate_legacy_instances(data.path(), &profiles).unwrap();
+
+        assert!(instance.is_dir());
+        assert_eq!(profiles.read_dir().unwrap().count(), 0);
+    }
+}
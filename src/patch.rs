@@ -0,0 +1,520 @@
+//! Starbound's JSON patches, simulated the way the game applies them.
+//! A mod changes an asset by shipping `<asset>.patch` next to where the asset would be. The file is
+//! either a list of RFC 6902 operations or a list of such lists. Each list is applied on its own and
+//! as a whole: when one of its operations fails, none of them are applied. The game skips a list
+//! whose `test` operation fails without a word, which is how patches are made conditional, and
+//! logs any other failure without stopping the game.
+
+use serde_json::Value;
+
+use crate::{
+    assets::{AssetError, AssetTree},
+    lenient_json,
+};
+
+/// Appended to the path of the asset a patch file changes
+pub const PATCH_EXTENSION: &str = ".patch";
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum PatchError {
+    #[error("Operation {0}: test of {1} failed, {2}")]
+    TestFailed(usize, String, String),
+    #[error("Operation {0}: {1} does not exist")]
+    MissingPath(usize, String),
+    #[error("Operation {0}: {1}")]
+    InvalidOperation(usize, String),
+    #[error("Patch is not a list of operations or a list of lists of operations")]
+    InvalidPatch,
+    #[error("{0} does not exist, the patch is ignored")]
+    NoAsset(String),
+    #[error("Failed to parse {0}: {1}")]
+    Parse(String, String),
+    #[error("{0}")]
+    Unreadable(String),
+}
+
+/// What became of one list of operations
+#[derive(Debug, Clone)]
+pub enum PatchOutcome {
+    Applied,
+    /// Every operation succeeded but the asset is the same as before
+    Unchanged,
+    /// A `test` operation failed, the game skips the list silently
+    Skipped(PatchError),
+    /// The game logs an error and skips the list
+    Failed(PatchError),
+}
+
+/// One list of operations of a patch file and what became of it
+#[derive(Debug, Clone)]
+pub struct PatchReport {
+    /// Name of the source shipping the patch file
+    pub source: String,
+    /// Asset path of the patch file
+    pub file: String,
+    /// Position of the list in a list of lists, None for a patch file of plain operations
+    pub set: Option<usize>,
+    pub outcome: PatchOutcome,
+}
+
+impl std::fmt::Display for PatchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.file, self.source)?;
+        if let Some(set) = self.set {
+            write!(f, " list {set}")?;
+        }
+        match &self.outcome {
+            PatchOutcome::Applied => write!(f, ": applied"),
+            PatchOutcome::Unchanged => write!(f, ": applied, but changed nothing"),
+            PatchOutcome::Skipped(e) => write!(f, ": skipped, {e}"),
+            PatchOutcome::Failed(e) => write!(f, ": failed, {e}"),
+        }
+    }
+}
+
+/// An asset as the game sees it after every patch is applied
+#[derive(Debug, Clone)]
+pub struct PatchedAsset {
+    /// The patched JSON, or why the asset could not be parsed
+    pub json: Result<Value, PatchError>,
+    pub reports: Vec<PatchReport>,
+}
+
+/// Applies every patch of `asset` in the order the sources are loaded
+pub fn patched_asset(tree: &AssetTree, asset: &str) -> Result<PatchedAsset, AssetError> {
+    let bytes = tree.read(asset)?;
+    let file = format!("{asset}{PATCH_EXTENSION}");
+    let mut json = lenient_json::from_slice(&bytes)
+        .map_err(|e| PatchError::Parse(asset.to_string(), e.to_string()));
+    let mut reports = Vec::new();
+    for &source in tree.providers(&file) {
+        let source = tree.source(source);
+        let outcomes = match (&mut json, source.read(&file)) {
+            // Nothing can be patched, the error is reported against every patch file instead
+            (Err(e), _) => vec![(None, PatchOutcome::Failed(e.clone()))],
+            (Ok(json), Ok(bytes)) => match lenient_json::from_slice(&bytes) {
+                Ok(patch) => apply_patch_file(json, &patch),
+                Err(e) => vec![(
+                    None,
+                    PatchOutcome::Failed(PatchError::Parse(file.clone(), e.to_string())),
+                )],
+            },
+            (Ok(_), Err(e)) => vec![(
+                None,
+                PatchOutcome::Failed(PatchError::Unreadable(e.to_string())),
+            )],
+        };
+        reports.extend(outcomes.into_iter().map(|(set, outcome)| PatchReport {
+            source: source.name(),
+            file: file.clone(),
+            set,
+            outcome,
+        }));
+    }
+    Ok(PatchedAsset { json, reports })
+}
+
+/// Simulates every patch file of the tree, including those of assets that don't exist, which the
+/// game ignores
+pub fn check_patches(tree: &AssetTree) -> Vec<PatchReport> {
+    let mut reports = Vec::new();
+    for file in tree.paths().filter(|path| path.ends_with(PATCH_EXTENSION)) {
+        let asset = &file[..file.len() - PATCH_EXTENSION.len()];
+        if !tree.contains(asset) {
+            for &source in tree.providers(file) {
+                reports.push(PatchReport {
+                    source: tree.source(source).name(),
+                    file: file.to_string(),
+                    set: None,
+                    outcome: PatchOutcome::Skipped(PatchError::NoAsset(asset.to_string())),
+                });
+            }
+            continue;
+        }
+        match patched_asset(tree, asset) {
+            Ok(patched) => reports.extend(patched.reports),
+            Err(e) => log::error!("Failed to check the patches of {asset}: {e}"),
+        }
+    }
+    reports
+}
+
+/// Applies a patch file to `json`, returning what became of each of its lists of operations
+pub fn apply_patch_file(json: &mut Value, patch: &Value) -> Vec<(Option<usize>, PatchOutcome)> {
+    let Some(operations) = patch.as_array() else {
+        return vec![(None, PatchOutcome::Failed(PatchError::InvalidPatch))];
+    };
+    match operations.first() {
+        None => Vec::new(),
+        Some(Value::Array(_)) => operations
+            .iter()
+            .enumerate()
+            .map(|(i, set)| match set.as_array() {
+                Some(set) => (Some(i), apply_operations(json, set)),
+                None => (Some(i), PatchOutcome::Failed(PatchError::InvalidPatch)),
+            })
+            .collect(),
+        Some(Value::Object(_)) => vec![(None, apply_operations(json, operations))],
+        Some(_) => vec![(None, PatchOutcome::Failed(PatchError::InvalidPatch))],
+    }
+}
+
+/// Applies a list of operations as a whole, leaving `json` untouched if one of them fails
+fn apply_operations(json: &mut Value, operations: &[Value]) -> PatchOutcome {
+    let mut patched = json.clone();
+    for (i, operation) in operations.iter().enumerate() {
+        match apply_operation(&mut patched, i, operation) {
+            Ok(()) => {}
+            Err(e @ PatchError::TestFailed(..)) => return PatchOutcome::Skipped(e),
+            Err(e) => return PatchOutcome::Failed(e),
+        }
+    }
+    if patched == *json {
+        return PatchOutcome::Unchanged;
+    }
+    *json = patched;
+    PatchOutcome::Applied
+}
+
+fn apply_operation(json: &mut Value, i: usize, operation: &Value) -> Result<(), PatchError> {
+    let invalid = |reason: &str| PatchError::InvalidOperation(i, reason.to_string());
+    let field = |name: &str| {
+        operation
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid(&format!("missing \"{name}\"")))
+    };
+    let value = || {
+        operation
+            .get("value")
+            .cloned()
+            .ok_or_else(|| invalid("missing \"value\""))
+    };
+    let path = field("path")?;
+    match field("op")? {
+        "test" => {
+            // Starbound's `inverse` turns the test around, passing if the value differs or the
+            // path does not exist
+            let inverse = operation
+                .get("inverse")
+                .and_then(Value::as_bool)
+                .unwrap_or_default();
+            let passed = match (json.pointer(path), operation.get("value")) {
+                (Some(found), Some(expected)) => found == expected,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            match (passed, inverse) {
+                (true, false) | (false, true) => Ok(()),
+                (true, true) => Err(PatchError::TestFailed(
+                    i,
+                    path.to_string(),
+                    String::from("expected it to differ"),
+                )),
+                (false, false) => Err(PatchError::TestFailed(
+                    i,
+                    path.to_string(),
+                    match json.pointer(path) {
+                        Some(found) => format!("found {found}"),
+                        None => String::from("it does not exist"),
+                    },
+                )),
+            }
+        }
+        "add" => add(json, i, path, value()?),
+        "remove" => remove(json, i, path).map(|_| ()),
+        "replace" => {
+            if !path.is_empty() {
+                remove(json, i, path)?;
+            }
+            add(json, i, path, value()?)
+        }
+        "move" => {
+            let moved = remove(json, i, field("from")?)?;
+            add(json, i, path, moved)
+        }
+        "copy" => {
+            let from = field("from")?;
+            let copied = json
+                .pointer(from)
+                .cloned()
+                .ok_or_else(|| PatchError::MissingPath(i, from.to_string()))?;
+            add(json, i, path, copied)
+        }
+        op => Err(invalid(&format!("unknown operation \"{op}\""))),
+    }
+}
+
+/// Splits a JSON pointer into the pointer to its parent and its unescaped last token
+fn split_pointer(path: &str) -> Option<(&str, String)> {
+    let (parent, last) = path.rsplit_once('/')?;
+    Some((parent, last.replace("~1", "/").replace("~0", "~")))
+}
+
+fn add(json: &mut Value, i: usize, path: &str, value: Value) -> Result<(), PatchError> {
+    if path.is_empty() {
+        *json = value;
+        return Ok(());
+    }
+    let missing = || PatchError::MissingPath(i, path.to_string());
+    let (parent, key) = split_pointer(path).ok_or_else(missing)?;
+    match json.pointer_mut(parent).ok_or_else(missing)? {
+        Value::Object(map) => {
+            map.insert(key, value);
+            Ok(())
+        }
+        Value::Array(array) if key == "-" => {
+            array.push(value);
+            Ok(())
+        }
+        Value::Array(array) => match key.parse::<usize>() {
+            Ok(index) if index <= array.len() => {
+                array.insert(index, value);
+                Ok(())
+            }
+            _ => Err(missing()),
+        },
+        _ => Err(PatchError::InvalidOperation(
+            i,
+            format!("{parent} is neither an object nor a list"),
+        )),
+    }
+}
+
+fn remove(json: &mut Value, i: usize, path: &str) -> Result<Value, PatchError> {
+    let missing = || PatchError::MissingPath(i, path.to_string());
+    let (parent, key) = split_pointer(path).ok_or_else(missing)?;
+    match json.pointer_mut(parent).ok_or_else(missing)? {
+        Value::Object(map) => map.remove(&key).ok_or_else(missing),
+        Value::Array(array) => match key.parse::<usize>() {
+            Ok(index) if index < array.len() => Ok(array.remove(index)),
+            _ => Err(missing()),
+        },
+        _ => Err(missing()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Applies a single list of operations, returning the outcome and the patched JSON
+    fn apply(mut json: Value, operations: Value) -> (PatchOutcome, Value) {
+        let mut outcomes = apply_patch_file(&mut json, &operations);
+        assert_eq!(outcomes.len(), 1);
+        (outcomes.remove(0).1, json)
+    }
+
+    #[test]
+    fn applies_each_operation() {
+        let json = json!({"name": "apple", "list": [1, 2, 3], "a/b": {"~": 0}});
+        let (outcome, patched) = apply(
+            json,
+            json!([
+                {"op": "add", "path": "/list/-", "value": 4},
+                {"op": "add", "path": "/list/0", "value": 0},
+                {"op": "remove", "path": "/list/1"},
+                {"op": "replace", "path": "/name", "value": "pear"},
+                {"op": "copy", "from": "/name", "path": "/copy"},
+                {"op": "move", "from": "/a~1b/~0", "path": "/moved"},
+            ]),
+        );
+        assert!(matches!(outcome, PatchOutcome::Applied));
+        assert_eq!(
+            patched,
+            json!({"name": "pear", "list": [0, 2, 3, 4], "a/b": {}, "copy": "pear", "moved": 0})
+        );
+    }
+
+    #[test]
+    fn replaces_the_whole_document() {
+        let (outcome, patched) = apply(
+            json!({"a": 1}),
+            json!([{"op": "replace", "path": "", "value": [1]}]),
+        );
+        assert!(matches!(outcome, PatchOutcome::Applied));
+        assert_eq!(patched, json!([1]));
+    }
+
+    #[test]
+    fn test_gates_the_list() {
+        let json = json!({"version": 2, "items": []});
+        let add_item = json!({"op": "add", "path": "/items/-", "value": "x"});
+
+        let (outcome, patched) = apply(
+            json.clone(),
+            json!([{"op": "test", "path": "/version", "value": 2}, add_item]),
+        );
+        assert!(matches!(outcome, PatchOutcome::Applied));
+        assert_eq!(patched["items"], json!(["x"]));
+
+        let (outcome, patched) = apply(
+            json.clone(),
+            json!([{"op": "test", "path": "/version", "value": 1}, add_item]),
+        );
+        assert!(matches!(
+            outcome,
+            PatchOutcome::Skipped(PatchError::TestFailed(0, path, found))
+                if path == "/version" && found == "found 2"
+        ));
+        assert_eq!(patched, json);
+
+        // Without a value the test only checks that the path exists
+        let (outcome, _) = apply(json.clone(), json!([{"op": "test", "path": "/items"}]));
+        assert!(matches!(outcome, PatchOutcome::Unchanged));
+        let (outcome, _) = apply(json.clone(), json!([{"op": "test", "path": "/missing"}]));
+        assert!(matches!(outcome, PatchOutcome::Skipped(_)));
+    }
+
+    #[test]
+    fn inverse_test_passes_when_the_value_differs() {
+        let json = json!({"version": 2});
+        let inverse = |path: &str, value: Value| {
+            json!([
+                {"op": "test", "path": path, "value": value, "inverse": true},
+                {"op": "add", "path": "/patched", "value": true},
+            ])
+        };
+        let (outcome, _) = apply(json.clone(), inverse("/version", json!(1)));
+        assert!(matches!(outcome, PatchOutcome::Applied));
+        let (outcome, _) = apply(json.clone(), inverse("/missing", json!(1)));
+        assert!(matches!(outcome, PatchOutcome::Applied));
+        let (outcome, patched) = apply(json.clone(), inverse("/version", json!(2)));
+        assert!(matches!(
+            outcome,
+            PatchOutcome::Skipped(PatchError::TestFailed(0, _, reason))
+                if reason == "expected it to differ"
+        ));
+        assert_eq!(patched, json);
+    }
+
+    #[test]
+    fn failed_operation_undoes_the_whole_list() {
+        let json = json!({"list": [1]});
+        let (outcome, patched) = apply(
+            json.clone(),
+            json!([
+                {"op": "add", "path": "/list/-", "value": 2},
+                {"op": "remove", "path": "/missing"},
+            ]),
+        );
+        assert!(matches!(
+            outcome,
+            PatchOutcome::Failed(PatchError::MissingPath(1, path)) if path == "/missing"
+        ));
+        assert_eq!(patched, json);
+
+        for operations in [
+            json!([{"op": "replace", "path": "/missing", "value": 1}]),
+            json!([{"op": "add", "path": "/list/5", "value": 1}]),
+            json!([{"op": "add", "path": "/missing/key", "value": 1}]),
+            json!([{"op": "copy", "from": "/missing", "path": "/copy"}]),
+        ] {
+            let (outcome, _) = apply(json.clone(), operations);
+            assert!(matches!(
+                outcome,
+                PatchOutcome::Failed(PatchError::MissingPath(0, _))
+            ));
+        }
+        let (outcome, _) = apply(json.clone(), json!([{"op": "add", "path": "/list/0"}]));
+        assert!(matches!(
+            outcome,
+            PatchOutcome::Failed(PatchError::InvalidOperation(0, _))
+        ));
+        let (outcome, _) = apply(json.clone(), json!([{"op": "merge", "path": "/list"}]));
+        assert!(matches!(
+            outcome,
+            PatchOutcome::Failed(PatchError::InvalidOperation(0, _))
+        ));
+    }
+
+    #[test]
+    fn move_edge_cases() {
+        let json = json!({"list": ["a", "b", "c"], "object": {"key": 1}});
+        // The target index counts after the value was taken out
+        let (_, patched) = apply(
+            json.clone(),
+            json!([{"op": "move", "from": "/list/0", "path": "/list/2"}]),
+        );
+        assert_eq!(patched["list"], json!(["b", "c", "a"]));
+
+        let (outcome, patched) = apply(
+            json.clone(),
+            json!([{"op": "move", "from": "/object/key", "path": "/object/key"}]),
+        );
+        assert!(matches!(outcome, PatchOutcome::Unchanged));
+        assert_eq!(patched, json);
+
+        // A value can't be moved into itself
+        let (outcome, patched) = apply(
+            json.clone(),
+            json!([{"op": "move", "from": "/object", "path": "/object/inner"}]),
+        );
+        assert!(matches!(
+            outcome,
+            PatchOutcome::Failed(PatchError::MissingPath(0, _))
+        ));
+        assert_eq!(patched, json);
+
+        let (outcome, _) = apply(
+            json.clone(),
+            json!([{"op": "move", "from": "/list/3", "path": "/moved"}]),
+        );
+        assert!(matches!(
+            outcome,
+            PatchOutcome::Failed(PatchError::MissingPath(0, _))
+        ));
+    }
+
+    #[test]
+    fn lists_of_lists_apply_on_their_own() {
+        let mut json = json!({"mode": "a", "count": 0});
+        let outcomes = apply_patch_file(
+            &mut json,
+            &json!([
+                [
+                    {"op": "test", "path": "/mode", "value": "b"},
+                    {"op": "replace", "path": "/count", "value": 2},
+                ],
+                [
+                    {"op": "test", "path": "/mode", "value": "a"},
+                    {"op": "replace", "path": "/count", "value": 1},
+                ],
+                [{"op": "remove", "path": "/missing"}],
+                {"op": "add", "path": "/x", "value": 1},
+            ]),
+        );
+        let outcomes: Vec<_> = outcomes
+            .into_iter()
+            .map(|(set, outcome)| (set, std::mem::discriminant(&outcome)))
+            .collect();
+        let failed = PatchOutcome::Failed(PatchError::InvalidPatch);
+        assert_eq!(
+            outcomes,
+            [
+                (
+                    Some(0),
+                    std::mem::discriminant(&PatchOutcome::Skipped(PatchError::InvalidPatch))
+                ),
+                (Some(1), std::mem::discriminant(&PatchOutcome::Applied)),
+                (Some(2), std::mem::discriminant(&failed)),
+                (Some(3), std::mem::discriminant(&failed)),
+            ]
+        );
+        assert_eq!(json, json!({"mode": "a", "count": 1}));
+    }
+
+    #[test]
+    fn rejects_patches_that_are_not_lists() {
+        for patch in [json!({"op": "add"}), json!([1, 2]), json!("patch")] {
+            let outcomes = apply_patch_file(&mut json!({}), &patch);
+            assert!(matches!(
+                outcomes[..],
+                [(None, PatchOutcome::Failed(PatchError::InvalidPatch))]
+            ));
+        }
+        assert!(apply_patch_file(&mut json!({}), &json!([])).is_empty());
+    }
+}
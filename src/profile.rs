@@ -1,32 +1,149 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{menus::duplicate_profile::DuplicateData, PROFILE_JSON_NAME};
+use crate::{
+    menus::duplicate_profile::DuplicateData, migration, PROFILE_JSON_NAME, PROFILE_LOCK_NAME,
+    STARBOUND_BOOT_CONFIG_NAME, VANILLA_PROFILE_JSON_NAME,
+};
+
+/// The vanilla profile can't be renamed, it is always listed under this name
+const VANILLA_PROFILE_NAME: &str = "Default";
 
 #[derive(Debug, Clone)]
 pub enum ProfileData {
-    Vanilla,
+    /// The storage folder of the vanilla install. Its settings live in sbi's profiles directory,
+    /// at `json_path`, so they are kept out of the game's folder.
+    Vanilla {
+        json: ProfileJson,
+        json_path: PathBuf,
+    },
     Json(ProfileJson),
 }
 
+/// Stable identifier of a profile, persisted in its profile.json.
+/// Unlike a profile's name, folder or position in the list, it survives renames and re-fetches.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct ProfileId(uuid::Uuid);
+
+impl ProfileId {
+    /// The vanilla profile is rebuilt from the game's own storage on every fetch, whichever install
+    /// that is, so it always has the nil uuid rather than the one in its settings file. Its
+    /// history, shortcuts and selection then survive a change of the vanilla install.
+    pub const VANILLA: Self = Self(uuid::Uuid::nil());
+
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4())
+    }
+}
+
+impl std::fmt::Display for ProfileId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::str::FromStr for ProfileId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        uuid::Uuid::parse_str(s).map(Self)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ProfileJson {
+    pub schema_version: u32,
+    pub id: ProfileId,
     pub name: String,
     pub additional_assets: Option<Vec<PathBuf>>,
     pub collection_id: Option<String>,
     #[serde(default)]
     pub link_mods: bool,
     pub selected_executable: Option<String>,
+    /// Favorites are pinned to the top of the profile list
+    #[serde(default)]
+    pub favorite: bool,
+    /// User-defined groups the profile list can be filtered by
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Extra folders to search for shared libraries, after the executable's
+    #[serde(default)]
+    pub library_dirs: Vec<PathBuf>,
+    /// Name of the registered vanilla install to load, None for the default install
+    #[serde(default)]
+    pub vanilla_install: Option<String>,
+}
+
+impl ProfileJson {
+    /// Settings of the vanilla profile until it is configured
+    fn vanilla() -> Self {
+        Self {
+            schema_version: migration::PROFILE_SCHEMA_VERSION,
+            id: ProfileId::VANILLA,
+            name: String::from(VANILLA_PROFILE_NAME),
+            additional_assets: None,
+            collection_id: None,
+            link_mods: true,
+            selected_executable: None,
+            favorite: false,
+            groups: Vec::new(),
+            library_dirs: Vec::new(),
+            vanilla_install: None,
+        }
+    }
+}
+
+/// Order of the profile list, favorites are always listed first
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProfileSort {
+    #[default]
+    Name,
+    LastPlayed,
+    Created,
+}
+
+impl ProfileSort {
+    pub fn options() -> [Self; 3] {
+        [Self::Name, Self::LastPlayed, Self::Created]
+    }
+}
+
+impl std::fmt::Display for ProfileSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Name => "Name",
+            Self::LastPlayed => "Last played",
+            Self::Created => "Created",
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Profile {
     path: PathBuf,
     data: ProfileData,
+    /// Creation time of the profile's folder, if the filesystem records it
+    created: Option<SystemTime>,
 }
 
 impl Profile {
+    fn new(path: PathBuf, data: ProfileData) -> Profile {
+        let created = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.created())
+            .ok();
+        Profile {
+            path,
+            data,
+            created,
+        }
+    }
+
     fn into_parts(self) -> (PathBuf, ProfileData) {
         (self.path, self.data)
     }
@@ -36,51 +153,59 @@ impl Profile {
             .parent()
             .expect("Profile json should have a parent, but does not??")
             .to_path_buf();
-        Profile {
-            path,
-            data: ProfileData::Json(json),
-        }
+        Profile::new(path, ProfileData::Json(json))
     }
 
-    fn from_vanilla(path: PathBuf) -> Profile {
-        Profile {
-            path,
-            data: ProfileData::Vanilla,
-        }
+    /// The vanilla profile in `path`, with its settings read from `json_path` if it was configured
+    fn from_vanilla(path: PathBuf, json_path: PathBuf) -> Profile {
+        let mut json = if json_path.is_file() {
+            read_profile_json(&json_path).unwrap_or_else(|e| {
+                log::error!("Error reading vanilla profile {}: {e}", json_path.display());
+                ProfileJson::vanilla()
+            })
+        } else {
+            ProfileJson::vanilla()
+        };
+        json.id = ProfileId::VANILLA;
+        json.name = String::from(VANILLA_PROFILE_NAME);
+        Profile::new(path, ProfileData::Vanilla { json, json_path })
+    }
+
+    pub fn id(&self) -> ProfileId {
+        self.json().id
     }
 
     pub fn name(&self) -> &str {
-        match &self.data {
-            ProfileData::Json(json) => &json.name,
-            ProfileData::Vanilla => "Default",
-        }
+        &self.json().name
     }
 
-    pub fn json(&self) -> Option<&ProfileJson> {
+    pub fn json(&self) -> &ProfileJson {
         match &self.data {
-            ProfileData::Json(j) => Some(j),
-            ProfileData::Vanilla => None,
+            ProfileData::Json(json) | ProfileData::Vanilla { json, .. } => json,
         }
     }
-    pub fn json_mut(&mut self) -> Option<&mut ProfileJson> {
+    pub fn json_mut(&mut self) -> &mut ProfileJson {
         match &mut self.data {
-            ProfileData::Json(j) => Some(j),
-            ProfileData::Vanilla => None,
+            ProfileData::Json(json) | ProfileData::Vanilla { json, .. } => json,
         }
     }
 
     pub fn set_json(&mut self, json: ProfileJson) {
         match &mut self.data {
             ProfileData::Json(j) => *j = json,
-            ProfileData::Vanilla => log::error!("Trying to write json data to vanilla profile!"),
+            // Keep the fixed id and name of the vanilla profile
+            ProfileData::Vanilla { json: j, .. } => {
+                *j = ProfileJson {
+                    id: ProfileId::VANILLA,
+                    name: String::from(VANILLA_PROFILE_NAME),
+                    ..json
+                }
+            }
         }
     }
 
     pub fn clear_selected_executable(&mut self) {
-        match &mut self.data {
-            ProfileData::Json(json) => json.selected_executable = None,
-            ProfileData::Vanilla => log::warn!("Clearing selected executable of vanilla profile does nothing... and this branch should have never been called"),
-        }
+        self.json_mut().selected_executable = None;
     }
 
     pub fn path(&self) -> &Path {
@@ -89,30 +214,61 @@ impl Profile {
 
     //TODO: Reduce overhead somehow? Is this necessary?
     pub fn additional_assets(&self) -> impl Iterator<Item = PathBuf> {
-        match &self.data {
-            ProfileData::Json(json) => json.additional_assets.clone(),
-            ProfileData::Vanilla => None,
-        }
-        .into_iter()
-        .flatten()
+        self.json().additional_assets.clone().into_iter().flatten()
     }
 
     pub fn link_mods(&self) -> bool {
-        match &self.data {
-            ProfileData::Json(json) => json.link_mods,
-            ProfileData::Vanilla => true,
-        }
+        self.json().link_mods
+    }
+
+    pub fn library_dirs(&self) -> &[PathBuf] {
+        &self.json().library_dirs
+    }
+
+    pub fn vanilla_install(&self) -> Option<&str> {
+        self.json().vanilla_install.as_deref()
+    }
+
+    pub fn clear_vanilla_install(&mut self) {
+        self.json_mut().vanilla_install = None;
     }
 
     pub fn selected_executable(&self) -> Option<&str> {
-        match &self.data {
-            ProfileData::Json(json) => json.selected_executable.as_deref(),
-            ProfileData::Vanilla => None,
-        }
+        self.json().selected_executable.as_deref()
     }
 
     pub fn is_vanilla(&self) -> bool {
-        matches!(self.data, ProfileData::Vanilla)
+        matches!(self.data, ProfileData::Vanilla { .. })
+    }
+
+    pub fn is_favorite(&self) -> bool {
+        self.json().favorite
+    }
+
+    pub fn groups(&self) -> &[String] {
+        &self.json().groups
+    }
+
+    pub fn created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+    /// Case-insensitive match of the search against the profile's name and groups
+    pub fn matches_search(&self, search: &str) -> bool {
+        let search = search.to_lowercase();
+        self.name().to_lowercase().contains(&search)
+            || self
+                .groups()
+                .iter()
+                .any(|group| group.to_lowercase().contains(&search))
+    }
+}
+
+/// Finds a profile by its id, or else by its name
+pub fn find_by_id_or_name<'a>(profiles: &'a [Profile], id_or_name: &str) -> Option<&'a Profile> {
+    match id_or_name.parse::<ProfileId>() {
+        Ok(id) => profiles.iter().find(|p| p.id() == id),
+        Err(_) => profiles.iter().find(|p| p.name() == id_or_name),
     }
 }
 
@@ -125,7 +281,12 @@ pub async fn find_profiles(
     let paths = crate::profile::collect_profile_json_paths(&profiles_directory);
     match paths {
         Ok(paths) => {
-            crate::profile::parse_profile_paths_to_json(&paths, maybe_vanilla_profile_directory)
+            assign_unique_ids(&paths);
+            crate::profile::parse_profile_paths_to_json(
+                &paths,
+                maybe_vanilla_profile_directory,
+                &profiles_directory,
+            )
         }
         Err(e) => {
             log::error!("Error gathering profiles: {e}");
@@ -138,19 +299,89 @@ pub async fn find_profiles(
 fn parse_profile_paths_to_json(
     instance_json_paths: &[PathBuf],
     maybe_vanilla_profile_directory: Option<std::path::PathBuf>,
+    profiles_directory: &Path,
 ) -> Vec<Profile> {
     let vanilla_profile = maybe_vanilla_profile_directory
         .into_iter()
-        .map(Profile::from_vanilla);
+        .map(|dir| Profile::from_vanilla(dir, profiles_directory.join(VANILLA_PROFILE_JSON_NAME)));
+    let mut seen_ids = rustc_hash::FxHashSet::from_iter([ProfileId::VANILLA]);
     let sbi_profiles = instance_json_paths
         .iter()
-        .map(|ins_path| std::fs::read_to_string(ins_path).map(|str| (str, ins_path.clone())))
-        .filter_map(Result::ok)
-        .map(|(data, path)| serde_json::from_str(&data).map(|data| Profile::from_json(data, path)))
-        .filter_map(Result::ok);
+        .filter_map(|path| match read_profile_json(path) {
+            Ok(json) => Some((json, path)),
+            Err(e) => {
+                log::error!("Error reading profile {}: {e}", path.display());
+                None
+            }
+        })
+        .map(|(mut json, path)| {
+            // Only left if assign_unique_ids failed to write the new id, which then changes on
+            // every fetch
+            if !seen_ids.insert(json.id) {
+                json.id = ProfileId::generate();
+                seen_ids.insert(json.id);
+                log::error!(
+                    "Profile {} has a duplicate id, using id {} until it can be written",
+                    path.display(),
+                    json.id
+                );
+            }
+            Profile::from_json(json, path.clone())
+        })
+        .collect::<Vec<_>>();
     vanilla_profile.chain(sbi_profiles).collect()
 }
 
+/// Gives every profile sharing its id with another one, e.g. a profile folder copied outside of
+/// sbi, a new id and writes it to its profile.json. The oldest folder keeps the id, so it stays
+/// the same profile no matter in which order the folders are read.
+fn assign_unique_ids(profile_json_paths: &[PathBuf]) {
+    let mut by_id: BTreeMap<ProfileId, Vec<(&PathBuf, ProfileJson)>> = BTreeMap::new();
+    for path in profile_json_paths {
+        // Unreadable profiles are reported when the list is fetched
+        if let Ok(json) = read_profile_json(path) {
+            by_id.entry(json.id).or_default().push((path, json));
+        }
+    }
+    for (id, mut profiles) in by_id {
+        profiles.sort_by_cached_key(|(path, _)| {
+            let created = path
+                .parent()
+                .and_then(|folder| std::fs::metadata(folder).ok())
+                .and_then(|metadata| metadata.created().ok());
+            (created, path.to_path_buf())
+        });
+        // The vanilla profile's id can't be taken by any profile
+        let keep = if id == ProfileId::VANILLA { 0 } else { 1 };
+        for (path, mut json) in profiles.into_iter().skip(keep) {
+            json.id = ProfileId::generate();
+            log::warn!(
+                "Profile {} has a duplicate id, assigning new id {}",
+                path.display(),
+                json.id
+            );
+            if let Err(e) = serde_json::to_vec(&json)
+                .map_err(std::io::Error::from)
+                .and_then(|bytes| std::fs::write(path, bytes))
+            {
+                log::error!("Error writing new id of {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Reads a profile.json, migrating it to the current schema and writing it back if it was outdated.
+fn read_profile_json(path: &Path) -> anyhow::Result<ProfileJson> {
+    let mut json: serde_json::Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    let migrated = migration::migrate_profile(&mut json)?;
+    let profile: ProfileJson = serde_json::from_value(json)?;
+    if migrated {
+        log::info!("Writing migrated profile {}", path.display());
+        std::fs::write(path, serde_json::to_vec(&profile)?)?;
+    }
+    Ok(profile)
+}
+
 /// Returns an owned iterator of paths to the instance.json of each instance
 fn collect_profile_json_paths(profiles_dir: &std::path::Path) -> std::io::Result<Vec<PathBuf>> {
     let instances = profiles_dir
@@ -172,11 +403,15 @@ fn collect_profile_json_paths(profiles_dir: &std::path::Path) -> std::io::Result
 // }
 
 pub async fn write_profile(p: Profile) -> std::io::Result<()> {
-    if let ProfileData::Json(json) = &p.data {
-        tokio::fs::create_dir_all(&p.path).await?;
-        let instance_data = serde_json::to_vec(json)?;
-        tokio::fs::write(p.path.join(PROFILE_JSON_NAME), instance_data).await?;
-    }
+    let (json_path, json) = match &p.data {
+        ProfileData::Json(json) => {
+            tokio::fs::create_dir_all(&p.path).await?;
+            (p.path.join(PROFILE_JSON_NAME), json)
+        }
+        ProfileData::Vanilla { json, json_path } => (json_path.clone(), json),
+    };
+    let instance_data = serde_json::to_vec(json)?;
+    tokio::fs::write(json_path, instance_data).await?;
     Ok(())
 }
 
@@ -185,11 +420,8 @@ pub async fn create_profile_then_find_list(
     profiles_directory: std::path::PathBuf,
     maybe_vanilla_profile_directory: Option<std::path::PathBuf>,
 ) -> Vec<Profile> {
-    let profile_path = find_valid_profile_path(&p.name, &profiles_directory).await;
-    let p = Profile {
-        path: profile_path,
-        data: ProfileData::Json(p),
-    };
+    let profile_path = find_valid_profile_path(&p.name, &profiles_directory);
+    let p = Profile::new(profile_path, ProfileData::Json(p));
     // write_profile_then_find_list(p, profiles_directory, maybe_vanilla_profile_directory).await
     if let Err(e) = write_profile(p).await {
         log::error!("Error while writing profile to disk: {e}");
@@ -198,7 +430,8 @@ pub async fn create_profile_then_find_list(
     find_profiles(profiles_directory, maybe_vanilla_profile_directory).await
 }
 
-async fn find_valid_profile_path(name: &str, profiles_directory: &std::path::Path) -> PathBuf {
+/// Returns a path in the profiles directory derived from the profile name that is not yet taken.
+pub fn find_valid_profile_path(name: &str, profiles_directory: &std::path::Path) -> PathBuf {
     let filtered_name = name.replace([' ', '-', '\\', '/'], "_");
     let mut path = profiles_directory.join(&filtered_name);
     let mut i: usize = 0;
@@ -216,30 +449,18 @@ pub async fn duplicate_profile_then_find_list(
     maybe_vanilla_profile_directory: Option<std::path::PathBuf>,
 ) -> Vec<Profile> {
     let (current_path, current_data) = current_profile.into_parts();
-    let json: ProfileJson = match current_data {
-        ProfileData::Json(mut json) => {
-            json.name = data.name;
-            json
-        }
-        // Duplicating vanilla profile
-        ProfileData::Vanilla => {
-            ProfileJson {
-                name: data.name,
-                additional_assets: None,
-                collection_id: None,
-                link_mods: true,
-                selected_executable: None,
-            }
-        }
-    };
-    let new_profile_path = find_valid_profile_path(&json.name, &profiles_directory).await;
+    // Duplicates of the vanilla profile start out with its settings as well
+    let (ProfileData::Json(mut json) | ProfileData::Vanilla { mut json, .. }) = current_data;
+    json.id = ProfileId::generate();
+    json.name = data.name;
+    if data.selected_executable.is_some() {
+        json.selected_executable = data.selected_executable;
+    }
+    let new_profile_path = find_valid_profile_path(&json.name, &profiles_directory);
     // write_profile_then_find_list(p, profiles_directory, maybe_vanilla_profile_directory).await
 
-    let new_profile = Profile {
-        path: new_profile_path,
-        data: ProfileData::Json(json),
-    };
-    if let Err(e) = copy_dir_all(&current_path, &new_profile.path).await {
+    let new_profile = Profile::new(new_profile_path, ProfileData::Json(json));
+    if let Err(e) = copy_profile_storage(&current_path, &new_profile.path).await {
         log::error!(
             "Error {e} while copying profile {} to {}",
             current_path.display(),
@@ -253,7 +474,26 @@ pub async fn duplicate_profile_then_find_list(
     find_profiles(profiles_directory, maybe_vanilla_profile_directory).await
 }
 
-async fn copy_dir_all<P: AsRef<Path>>(src: P, dst: P) -> std::io::Result<()> {
+/// Copies a profile's folder, without the lock of a running game and the boot config, which point
+/// at the original profile and are written again on launch
+async fn copy_profile_storage(src: &Path, dst: &Path) -> std::io::Result<()> {
+    tokio::fs::create_dir_all(dst).await?;
+    let mut read_dir = tokio::fs::read_dir(src).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        let name = entry.file_name();
+        if name == PROFILE_LOCK_NAME || name == STARBOUND_BOOT_CONFIG_NAME {
+            continue;
+        }
+        if entry.file_type().await?.is_dir() {
+            copy_dir_all(entry.path(), dst.join(name)).await?;
+        } else {
+            tokio::fs::copy(entry.path(), dst.join(name)).await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn copy_dir_all<P: AsRef<Path>>(src: P, dst: P) -> std::io::Result<()> {
     let dst = dst.as_ref();
     tokio::fs::create_dir_all(dst).await?;
     let mut read_dir = tokio::fs::read_dir(src).await?;
@@ -270,3 +510,70 @@ async fn copy_dir_all<P: AsRef<Path>>(src: P, dst: P) -> std::io::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_profile_json(profiles: &Path, folder: &str, id: ProfileId) -> PathBuf {
+        let json = ProfileJson {
+            id,
+            name: folder.to_string(),
+            ..ProfileJson::vanilla()
+        };
+        let path = profiles.join(folder).join(PROFILE_JSON_NAME);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, serde_json::to_vec(&json).unwrap()).unwrap();
+        path
+    }
+
+    fn read_id(path: &Path) -> ProfileId {
+        read_profile_json(path).unwrap().id
+    }
+
+    #[test]
+    fn oldest_profile_keeps_a_duplicate_id() {
+        let profiles = tempfile::tempdir().unwrap();
+        let id = ProfileId::generate();
+        let original = write_profile_json(profiles.path(), "Modded", id);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let copy = write_profile_json(profiles.path(), "Modded_copy", id);
+
+        // Read order doesn't matter
+        assign_unique_ids(&[copy.clone(), original.clone()]);
+        assert_eq!(read_id(&original), id);
+        let copy_id = read_id(&copy);
+        assert_ne!(copy_id, id);
+
+        assign_unique_ids(&[original.clone(), copy.clone()]);
+        assert_eq!(read_id(&original), id);
+        assert_eq!(read_id(&copy), copy_id);
+    }
+
+    #[test]
+    fn vanilla_id_is_reassigned() {
+        let profiles = tempfile::tempdir().unwrap();
+        let path = write_profile_json(profiles.path(), "Modded", ProfileId::VANILLA);
+        assign_unique_ids(std::slice::from_ref(&path));
+        assert_ne!(read_id(&path), ProfileId::VANILLA);
+    }
+
+    #[tokio::test]
+    async fn listing_profiles_does_not_write() {
+        let profiles = tempfile::tempdir().unwrap();
+        let id = ProfileId::generate();
+        let paths = [
+            write_profile_json(profiles.path(), "Modded", id),
+            write_profile_json(profiles.path(), "Modded_copy", id),
+        ];
+        let listed = parse_profile_paths_to_json(&paths, None, profiles.path());
+        assert_eq!(listed.len(), 2);
+        assert_ne!(listed[0].id(), listed[1].id());
+        assert!(paths.iter().all(|path| read_id(path) == id));
+
+        let found = find_profiles(profiles.path().to_path_buf(), None).await;
+        let ids: Vec<ProfileId> = paths.iter().map(|path| read_id(path)).collect();
+        assert_ne!(ids[0], ids[1]);
+        assert!(found.iter().all(|profile| ids.contains(&profile.id())));
+    }
+}
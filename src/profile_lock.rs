@@ -0,0 +1,186 @@
+//! Keeps a profile from being launched twice at once.
+//! Two games writing the same universe corrupt it, so every launch takes a lock file in the
+//! profile's storage folder holding the pid of the process the game runs in.
+
+use std::path::{Path, PathBuf};
+
+use crate::PROFILE_LOCK_NAME;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileLockError {
+    #[error("{0} is already running (pid {1})")]
+    AlreadyRunning(String, u32),
+    #[error("The lock file {0} has no pid in it, remove it if the profile is not running")]
+    Unreadable(PathBuf),
+    #[error("Failed to create the lock file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+}
+
+/// Held for as long as the game of a profile runs, the lock file is removed when dropped
+#[derive(Debug)]
+pub struct ProfileLock {
+    path: PathBuf,
+}
+
+impl ProfileLock {
+    /// Takes the lock of the profile stored in `storage`. A lock left behind by a process that no
+    /// longer exists, e.g. after a crash, is replaced.
+    pub fn acquire(storage: &Path, profile_name: &str) -> Result<Self, ProfileLockError> {
+        let path = storage.join(PROFILE_LOCK_NAME);
+        let io_error = |e| ProfileLockError::Io(path.clone(), e);
+        std::fs::create_dir_all(storage).map_err(io_error)?;
+        let mut replaced_stale = false;
+        loop {
+            match create_lock_file(&path, std::process::id()) {
+                Ok(()) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(io_error(e)),
+            }
+            let contents = match std::fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                // Released since, try again
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(io_error(e)),
+            };
+            // Lock files only ever appear with their pid written, so one without is not a lock
+            // being created but one that was damaged
+            let Ok(pid) = contents.trim().parse() else {
+                return Err(ProfileLockError::Unreadable(path));
+            };
+            if process_exists(pid) || replaced_stale {
+                return Err(ProfileLockError::AlreadyRunning(
+                    profile_name.to_string(),
+                    pid,
+                ));
+            }
+            log::warn!("Removing stale lock file {}", path.display());
+            remove_stale_lock(&path, pid).map_err(io_error)?;
+            replaced_stale = true;
+        }
+    }
+
+    /// Records the game's pid instead of sbi's, so the lock stays valid if sbi exits first
+    pub fn hand_over(&self, pid: u32) {
+        let result = write_temporary(&self.path, pid)
+            .and_then(|temporary| std::fs::rename(temporary, &self.path));
+        if let Err(e) = result {
+            log::error!("Failed to update lock file {}: {e}", self.path.display());
+        }
+    }
+}
+
+impl Drop for ProfileLock {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::error!("Failed to remove lock file {}: {e}", self.path.display());
+        }
+    }
+}
+
+/// The pid of the game holding the lock of the profile stored in `storage`, if one is running
+pub fn holder(storage: &Path) -> Option<u32> {
+    read_pid(&storage.join(PROFILE_LOCK_NAME)).filter(|pid| process_exists(*pid))
+}
+
+/// Writes `pid` into a file next to the lock that only this process uses
+fn write_temporary(path: &Path, pid: u32) -> std::io::Result<PathBuf> {
+    let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temporary, pid.to_string())?;
+    Ok(temporary)
+}
+
+/// Creates the lock file with `pid` in it. The pid is written before the file is linked into
+/// place, so other processes never see the lock without it.
+fn create_lock_file(path: &Path, pid: u32) -> std::io::Result<()> {
+    let temporary = write_temporary(path, pid)?;
+    let result = std::fs::hard_link(&temporary, path);
+    if let Err(e) = std::fs::remove_file(&temporary) {
+        log::error!("Failed to remove {}: {e}", temporary.display());
+    }
+    result
+}
+
+/// Removes the lock of a process that no longer exists. The lock is moved aside first, and put
+/// back if another process replaced the stale lock in the meantime.
+fn remove_stale_lock(path: &Path, stale_pid: u32) -> std::io::Result<()> {
+    let aside = path.with_extension(format!("{}.stale", std::process::id()));
+    match std::fs::rename(path, &aside) {
+        Ok(()) => {}
+        // Already removed by another process
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    }
+    if read_pid(&aside) != Some(stale_pid) {
+        // Fails if yet another lock was created since, which then holds the profile anyway
+        let _ = std::fs::hard_link(&aside, path);
+    }
+    std::fs::remove_file(&aside)
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn process_exists(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Above the kernel's pid limit, so never a running process
+    const DEAD_PID: u32 = u32::MAX;
+
+    #[test]
+    fn lock_is_exclusive_until_dropped() {
+        let storage = tempfile::tempdir().unwrap();
+        let lock = ProfileLock::acquire(storage.path(), "Modded").unwrap();
+        assert_eq!(holder(storage.path()), Some(std::process::id()));
+        assert!(matches!(
+            ProfileLock::acquire(storage.path(), "Modded"),
+            Err(ProfileLockError::AlreadyRunning(name, pid))
+                if name == "Modded" && pid == std::process::id()
+        ));
+        drop(lock);
+        assert_eq!(holder(storage.path()), None);
+        assert!(!storage.path().join(PROFILE_LOCK_NAME).exists());
+        ProfileLock::acquire(storage.path(), "Modded").unwrap();
+    }
+
+    #[test]
+    fn stale_lock_is_replaced() {
+        let storage = tempfile::tempdir().unwrap();
+        let path = storage.path().join(PROFILE_LOCK_NAME);
+        std::fs::write(&path, DEAD_PID.to_string()).unwrap();
+        assert_eq!(holder(storage.path()), None);
+        let _lock = ProfileLock::acquire(storage.path(), "Modded").unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+        // Only the lock itself is left, no temporary files
+        assert_eq!(std::fs::read_dir(storage.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn unreadable_lock_is_held() {
+        let storage = tempfile::tempdir().unwrap();
+        let path = storage.path().join(PROFILE_LOCK_NAME);
+        std::fs::write(&path, "").unwrap();
+        assert!(matches!(
+            ProfileLock::acquire(storage.path(), "Modded"),
+            Err(ProfileLockError::Unreadable(p)) if p == path
+        ));
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn hand_over_replaces_the_pid() {
+        let storage = tempfile::tempdir().unwrap();
+        let lock = ProfileLock::acquire(storage.path(), "Modded").unwrap();
+        lock.hand_over(DEAD_PID);
+        assert_eq!(
+            read_pid(&storage.path().join(PROFILE_LOCK_NAME)),
+            Some(DEAD_PID)
+        );
+        assert_eq!(std::fs::read_dir(storage.path()).unwrap().count(), 1);
+    }
+}
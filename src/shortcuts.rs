@@ -0,0 +1,128 @@
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+
+use crate::{profile::Profile, APPLICATION_NAME, STARBOUND_STEAM_ID};
+
+/// A profile can provide its own icon for shortcuts by placing this file in its folder
+pub static PROFILE_ICON_NAME: &str = "icon.png";
+
+/// Writes a freedesktop entry launching the profile through sbi into
+/// `$XDG_DATA_HOME/applications`, so it shows up in the user's app launcher.
+/// Returns the path of the written entry.
+pub async fn write_desktop_entry(profile: Profile) -> anyhow::Result<PathBuf> {
+    let applications_dir = directories::BaseDirs::new()
+        .ok_or(anyhow!(
+            "No home directory, can't locate the applications folder"
+        ))?
+        .data_dir()
+        .join("applications");
+    tokio::fs::create_dir_all(&applications_dir).await?;
+
+    let folder_name = profile
+        .path()
+        .file_name()
+        .ok_or(anyhow!("Profile has no folder name"))?
+        .to_string_lossy();
+    let entry_path = applications_dir.join(format!("{APPLICATION_NAME}-{folder_name}.desktop"));
+
+    let profile_icon = profile.path().join(PROFILE_ICON_NAME);
+    // Steam installs this icon into the hicolor theme once it has created a shortcut for the game
+    let icon = if profile_icon.is_file() {
+        profile_icon.display().to_string()
+    } else {
+        format!("steam_icon_{STARBOUND_STEAM_ID}")
+    };
+    let exec = launch_args(&profile)?
+        .iter()
+        .map(|arg| quote_exec_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name={name}\n\
+         Comment={comment}\n\
+         Exec={exec}\n\
+         Icon={icon}\n\
+         Terminal=false\n\
+         Categories=Game;\n",
+        name = escape_value(&format!("Starbound – {}", profile.name())),
+        comment = escape_value(&format!("Launch the {} profile with sbi", profile.name())),
+        exec = escape_value(&exec),
+        icon = escape_value(&icon),
+    );
+    tokio::fs::write(&entry_path, entry).await?;
+    Ok(entry_path)
+}
+
+/// Writes an executable shell script launching the profile through sbi to `script_path`.
+pub async fn write_launch_script(profile: Profile, script_path: PathBuf) -> anyhow::Result<()> {
+    let command = launch_args(&profile)?
+        .iter()
+        .map(|arg| quote_shell_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let script = format!("#!/bin/sh\nexec {command}\n");
+    tokio::fs::write(&script_path, script).await?;
+    tokio::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755)).await?;
+    Ok(())
+}
+
+/// The command line that launches the profile through the currently running sbi binary.
+/// The profile is addressed by id so shortcuts keep working after it is renamed.
+pub fn launch_args(profile: &Profile) -> anyhow::Result<Vec<String>> {
+    let sbi = std::env::current_exe()?;
+    Ok(vec![
+        path_to_string(&sbi)?,
+        String::from("launch"),
+        profile.id().to_string(),
+    ])
+}
+
+fn path_to_string(path: &Path) -> anyhow::Result<String> {
+    path.to_str()
+        .map(str::to_string)
+        .ok_or(anyhow!("Non-unicode path {}", path.display()))
+}
+
+/// Quotes an argument of a desktop entry's Exec key.
+/// Reserved characters require the argument to be double quoted, inside of which `"`, `` ` ``,
+/// `$` and `\` are escaped. Field codes are escaped by doubling `%`.
+fn quote_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[
+        ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(',
+        ')', '`',
+    ];
+    let arg = arg.replace('%', "%%");
+    if !arg.contains(RESERVED) {
+        return arg;
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escapes a desktop entry string value, applied on top of [quote_exec_arg] for Exec.
+fn escape_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+/// Single quotes an argument for sh
+pub fn quote_shell_arg(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
@@ -0,0 +1,299 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+/// File names of the targets steam launches for Starbound
+const STARBOUND_LAUNCH_TARGETS: [&str; 3] = ["run-client.sh", "starbound", "starbound.exe"];
+/// Folders the game's binaries are placed in, relative to the game directory
+const STARBOUND_PLATFORM_DIRS: [&str; 4] = ["linux", "osx", "win32", "win64"];
+
+/// Programs steam places in front of the game in `%command%`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SteamWrapper {
+    /// `ubuntu12_32/reaper`, steam's process tracker
+    Reaper,
+    /// `ubuntu12_32/steam-launch-wrapper`
+    LaunchWrapper,
+    /// `_v2-entry-point` of the Steam Linux Runtime (pressure-vessel), with the runtime's
+    /// folder name, e.g. `SteamLinuxRuntime_sniper`
+    Runtime(String),
+    /// The `proton` script of a Proton install, with the install's folder name
+    Proton(String),
+}
+
+impl SteamWrapper {
+    fn from_path(path: &Path) -> Option<Self> {
+        let parent_name = || {
+            path.parent()
+                .and_then(Path::file_name)
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        match path.file_name()?.to_str()? {
+            "reaper" => Some(Self::Reaper),
+            "steam-launch-wrapper" => Some(Self::LaunchWrapper),
+            "_v2-entry-point" => Some(Self::Runtime(parent_name())),
+            "proton" => Some(Self::Proton(parent_name())),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SteamWrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reaper => f.write_str("reaper"),
+            Self::LaunchWrapper => f.write_str("steam-launch-wrapper"),
+            Self::Runtime(name) => write!(f, "Steam Linux Runtime ({name})"),
+            Self::Proton(name) => write!(f, "Proton ({name})"),
+        }
+    }
+}
+
+/// The `%command%` steam hands to sbi through `sbi -- %command%`.
+/// Everything before the game itself (reaper, steam-launch-wrapper, the runtime's entry point)
+/// is what lets steam track the process, so it has to be kept when launching a profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SteamCommand {
+    args: Vec<String>,
+    /// Index of the starbound binary or launch script in `args`
+    game_index: usize,
+}
+
+impl SteamCommand {
+    /// Finds the game in steam's command, returns None if it does not look like a Starbound launch.
+    pub fn parse(args: &[String]) -> Option<Self> {
+        let game_index = args.iter().rposition(|arg| {
+            Path::new(arg)
+                .file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|name| STARBOUND_LAUNCH_TARGETS.contains(&name))
+        })?;
+        Some(Self {
+            args: args.to_vec(),
+            game_index,
+        })
+    }
+
+    /// Path to the binary or launch script steam would have started
+    pub fn game(&self) -> PathBuf {
+        PathBuf::from(&self.args[self.game_index])
+    }
+
+    /// Wrappers steam placed in front of the game, outermost first
+    pub fn wrappers(&self) -> impl Iterator<Item = SteamWrapper> + use<'_> {
+        self.args[..self.game_index]
+            .iter()
+            .filter_map(|arg| SteamWrapper::from_path(Path::new(arg)))
+    }
+
+    /// Whether the game is launched through Proton, in which case a linux executable can't be
+    /// swapped in.
+    pub fn is_proton(&self) -> bool {
+        self.wrappers()
+            .any(|wrapper| matches!(wrapper, SteamWrapper::Proton(_)))
+    }
+
+    /// The Starbound install folder, containing `assets`, `storage`, `mods` and the platform
+    /// folders. None if steam's game path is relative.
+    pub fn game_directory(&self) -> Option<PathBuf> {
+        let game = self.game();
+        if game.is_relative() {
+            return None;
+        }
+        let parent = game.parent()?;
+        let is_platform_dir = parent
+            .file_name()
+            .and_then(OsStr::to_str)
+            .is_some_and(|name| STARBOUND_PLATFORM_DIRS.contains(&name));
+        if is_platform_dir {
+            parent.parent().map(Path::to_path_buf)
+        } else {
+            Some(parent.to_path_buf())
+        }
+    }
+
+    /// Builds steam's command with the game swapped for `bin`, `game_args` inserted right after it.
+    /// Any arguments steam placed after the game are passed along as well.
+    pub fn rewrap<S: AsRef<OsStr>>(&self, bin: &Path, game_args: &[S]) -> std::process::Command {
+        let (wrappers, rest) = self.args.split_at(self.game_index);
+        let trailing_args = &rest[1..];
+        let mut command = match wrappers.split_first() {
+            Some((program, wrapper_args)) => {
+                let mut command = std::process::Command::new(program);
+                command.args(wrapper_args).arg(bin);
+                command
+            }
+            None => std::process::Command::new(bin),
+        };
+        command.args(game_args).args(trailing_args);
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEAM: &str = "/home/user/.local/share/Steam";
+
+    fn args(command: &[&str]) -> Vec<String> {
+        command
+            .iter()
+            .map(|arg| arg.replace("$STEAM", STEAM))
+            .collect()
+    }
+
+    #[test]
+    fn native_launch_behind_reaper() {
+        let command = SteamCommand::parse(&args(&[
+            "$STEAM/ubuntu12_32/reaper",
+            "SteamLaunch",
+            "AppId=211820",
+            "--",
+            "$STEAM/ubuntu12_32/steam-launch-wrapper",
+            "--",
+            "$STEAM/steamapps/common/Starbound/linux/run-client.sh",
+        ]))
+        .unwrap();
+        assert_eq!(
+            command.wrappers().collect::<Vec<_>>(),
+            [SteamWrapper::Reaper, SteamWrapper::LaunchWrapper]
+        );
+        assert!(!command.is_proton());
+        assert_eq!(
+            command.game(),
+            PathBuf::from(format!(
+                "{STEAM}/steamapps/common/Starbound/linux/run-client.sh"
+            ))
+        );
+        assert_eq!(
+            command.game_directory(),
+            Some(PathBuf::from(format!("{STEAM}/steamapps/common/Starbound")))
+        );
+    }
+
+    #[test]
+    fn launch_inside_the_steam_linux_runtime() {
+        let command = SteamCommand::parse(&args(&[
+            "$STEAM/ubuntu12_32/reaper",
+            "SteamLaunch",
+            "AppId=211820",
+            "--",
+            "$STEAM/ubuntu12_32/steam-launch-wrapper",
+            "--",
+            "$STEAM/steamapps/common/SteamLinuxRuntime_sniper/_v2-entry-point",
+            "--verb=waitforexitandrun",
+            "--",
+            "$STEAM/steamapps/common/Starbound/linux/starbound",
+        ]))
+        .unwrap();
+        assert_eq!(
+            command.wrappers().collect::<Vec<_>>(),
+            [
+                SteamWrapper::Reaper,
+                SteamWrapper::LaunchWrapper,
+                SteamWrapper::Runtime(String::from("SteamLinuxRuntime_sniper")),
+            ]
+        );
+        assert!(!command.is_proton());
+    }
+
+    #[test]
+    fn proton_launch_from_a_library_with_spaces() {
+        let command = SteamCommand::parse(&args(&[
+            "$STEAM/ubuntu12_32/reaper",
+            "SteamLaunch",
+            "AppId=211820",
+            "--",
+            "$STEAM/ubuntu12_32/steam-launch-wrapper",
+            "--",
+            "$STEAM/steamapps/common/SteamLinuxRuntime_sniper/_v2-entry-point",
+            "--verb=waitforexitandrun",
+            "--",
+            "$STEAM/steamapps/common/Proton - Experimental/proton",
+            "waitforexitandrun",
+            "/mnt/Games Drive/SteamLibrary/steamapps/common/Starbound/win64/starbound.exe",
+        ]))
+        .unwrap();
+        assert!(command.is_proton());
+        assert!(command
+            .wrappers()
+            .any(|wrapper| wrapper == SteamWrapper::Proton(String::from("Proton - Experimental"))));
+        assert_eq!(
+            command.game_directory(),
+            Some(PathBuf::from(
+                "/mnt/Games Drive/SteamLibrary/steamapps/common/Starbound"
+            ))
+        );
+    }
+
+    #[test]
+    fn rewrap_swaps_the_game_and_keeps_trailing_args() {
+        let command = SteamCommand::parse(&args(&[
+            "$STEAM/ubuntu12_32/reaper",
+            "SteamLaunch",
+            "AppId=211820",
+            "--",
+            "$STEAM/steamapps/common/Starbound/linux/run-client.sh",
+            "-loglevel",
+            "debug",
+        ]))
+        .unwrap();
+        let rewrapped = command.rewrap(
+            Path::new("/opt/open starbound/starbound"),
+            &["-bootconfig", "/profiles/modded/sbinit.config"],
+        );
+        assert_eq!(
+            rewrapped.get_program(),
+            OsStr::new(&format!("{STEAM}/ubuntu12_32/reaper"))
+        );
+        assert_eq!(
+            rewrapped.get_args().collect::<Vec<_>>(),
+            [
+                "SteamLaunch",
+                "AppId=211820",
+                "--",
+                "/opt/open starbound/starbound",
+                "-bootconfig",
+                "/profiles/modded/sbinit.config",
+                "-loglevel",
+                "debug",
+            ]
+        );
+    }
+
+    #[test]
+    fn unwrapped_game_is_launched_directly() {
+        let command = SteamCommand::parse(&args(&["starbound"])).unwrap();
+        assert_eq!(command.wrappers().count(), 0);
+        assert_eq!(command.game_directory(), None);
+        let rewrapped = command.rewrap(Path::new("/opt/xsb/xclient"), &["-bootconfig", "x"]);
+        assert_eq!(rewrapped.get_program(), "/opt/xsb/xclient");
+        assert_eq!(
+            rewrapped.get_args().collect::<Vec<_>>(),
+            ["-bootconfig", "x"]
+        );
+    }
+
+    #[test]
+    fn commands_without_starbound_are_rejected() {
+        assert_eq!(SteamCommand::parse(&[]), None);
+        assert_eq!(
+            SteamCommand::parse(&args(&[
+                "$STEAM/ubuntu12_32/reaper",
+                "SteamLaunch",
+                "AppId=105600",
+                "--",
+                "$STEAM/steamapps/common/Terraria/Terraria",
+            ])),
+            None
+        );
+        // Only the file name counts, not a folder named like the game
+        assert_eq!(
+            SteamCommand::parse(&args(&["/games/starbound/launcher.sh"])),
+            None
+        );
+    }
+}
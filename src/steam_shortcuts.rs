@@ -0,0 +1,279 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+
+use crate::{
+    profile::Profile,
+    shortcuts,
+    vdf::{self, VdfMap, VdfValue},
+};
+
+static SHORTCUTS_VDF_NAME: &str = "shortcuts.vdf";
+/// Copy of the user's shortcuts.vdf taken before sbi first modifies it, never overwritten so it
+/// keeps the state from before sbi
+static SHORTCUTS_VDF_BACKUP_NAME: &str = "shortcuts.vdf.sbi-backup";
+
+/// A non-steam game entry launching an sbi profile
+#[derive(Debug, Clone)]
+pub struct SteamShortcut {
+    app_name: String,
+    /// Quoted path to sbi, as steam writes it
+    exe: String,
+    /// Quoted folder of sbi, as steam writes it
+    start_dir: String,
+    launch_options: String,
+    icon: String,
+}
+
+impl SteamShortcut {
+    pub fn for_profile(profile: &Profile) -> anyhow::Result<Self> {
+        let mut args = shortcuts::launch_args(profile)?.into_iter();
+        let sbi = PathBuf::from(args.next().ok_or(anyhow!("Missing sbi path"))?);
+        let start_dir = sbi.parent().ok_or(anyhow!("sbi has no parent folder"))?;
+        let icon = profile.path().join(shortcuts::PROFILE_ICON_NAME);
+        Ok(Self {
+            app_name: format!("Starbound – {}", profile.name()),
+            exe: format!("\"{}\"", sbi.display()),
+            start_dir: format!("\"{}\"", start_dir.display()),
+            launch_options: args
+                .map(|arg| shortcuts::quote_shell_arg(&arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+            icon: if icon.is_file() {
+                icon.display().to_string()
+            } else {
+                String::new()
+            },
+        })
+    }
+
+    /// The id steam derives for non-steam games, needed for steam to recognize the entry
+    /// (artwork, controller configs, `steam://rungameid/`)
+    fn app_id(&self) -> u32 {
+        let shortcut = steamlocate::Shortcut::new(
+            0,
+            self.app_name.clone(),
+            self.exe.clone(),
+            self.start_dir.clone(),
+        );
+        (shortcut.steam_id() >> 32) as u32
+    }
+
+    /// Whether an existing entry launches the same profile
+    fn matches(&self, entry: &VdfMap) -> bool {
+        vdf::get_str(entry, "Exe") == Some(self.exe.as_str())
+            && vdf::get_str(entry, "LaunchOptions") == Some(self.launch_options.as_str())
+    }
+
+    /// Writes the shortcut's fields into an entry, leaving any fields steam added untouched
+    fn write_into(&self, entry: &mut VdfMap) {
+        let string = |s: &str| VdfValue::String(s.to_string());
+        vdf::set(entry, "appid", VdfValue::Int32(self.app_id() as i32));
+        vdf::set(entry, "AppName", string(&self.app_name));
+        vdf::set(entry, "Exe", string(&self.exe));
+        vdf::set(entry, "StartDir", string(&self.start_dir));
+        vdf::set(entry, "icon", string(&self.icon));
+        vdf::set(entry, "LaunchOptions", string(&self.launch_options));
+    }
+
+    /// A new entry with the defaults steam uses for non-steam games
+    fn to_entry(&self) -> VdfMap {
+        let mut entry = VdfMap::new();
+        self.write_into(&mut entry);
+        let string = |s: &str| VdfValue::String(s.to_string());
+        entry.extend([
+            (String::from("ShortcutPath"), string("")),
+            (String::from("IsHidden"), VdfValue::Int32(0)),
+            (String::from("AllowDesktopConfig"), VdfValue::Int32(1)),
+            (String::from("AllowOverlay"), VdfValue::Int32(1)),
+            (String::from("OpenVR"), VdfValue::Int32(0)),
+            (String::from("Devkit"), VdfValue::Int32(0)),
+            (String::from("DevkitGameID"), string("")),
+            (String::from("DevkitOverrideAppID"), VdfValue::Int32(0)),
+            (String::from("LastPlayTime"), VdfValue::Int32(0)),
+            (String::from("FlatpakAppID"), string("")),
+            (String::from("tags"), VdfValue::Map(VdfMap::new())),
+        ]);
+        entry
+    }
+}
+
+/// Registers the profile as a non-steam game for every user of the local steam install.
+/// Steam only reads shortcuts.vdf on startup and overwrites it on exit, so it should be closed.
+/// Returns the number of users the shortcut was added for.
+pub async fn add_profile_to_steam(profile: Profile) -> anyhow::Result<usize> {
+    let steam = steamlocate::SteamDir::locate()?;
+    let shortcut = SteamShortcut::for_profile(&profile)?;
+    add_shortcut(&steam.path().join("userdata"), &shortcut)
+}
+
+/// Adds or updates the shortcut in the shortcuts.vdf of every user in a steam userdata folder.
+pub fn add_shortcut(userdata_dir: &Path, shortcut: &SteamShortcut) -> anyhow::Result<usize> {
+    let mut users = 0;
+    for entry in userdata_dir.read_dir()?.filter_map(Result::ok) {
+        let user_id = entry.file_name().to_string_lossy().to_string();
+        // 0 is used for anonymous steam sessions
+        let is_user = user_id != "0" && user_id.chars().all(|c| c.is_ascii_digit());
+        let config_dir = entry.path().join("config");
+        if !is_user || !config_dir.is_dir() {
+            continue;
+        }
+        add_shortcut_for_user(&config_dir.join(SHORTCUTS_VDF_NAME), shortcut)?;
+        users += 1;
+    }
+    Ok(users)
+}
+
+fn add_shortcut_for_user(shortcuts_path: &Path, shortcut: &SteamShortcut) -> anyhow::Result<()> {
+    let mut root = if shortcuts_path.is_file() {
+        let bytes = std::fs::read(shortcuts_path)?;
+        let backup_path = shortcuts_path.with_file_name(SHORTCUTS_VDF_BACKUP_NAME);
+        if !backup_path.exists() {
+            std::fs::write(backup_path, &bytes)?;
+        }
+        vdf::parse(&bytes)?
+    } else {
+        VdfMap::new()
+    };
+    if vdf::get(&root, "shortcuts").is_none() {
+        root.push((String::from("shortcuts"), VdfValue::Map(VdfMap::new())));
+    }
+    let Some((_, VdfValue::Map(entries))) = root
+        .iter_mut()
+        .find(|(k, _)| k.eq_ignore_ascii_case("shortcuts"))
+    else {
+        return Err(anyhow!("shortcuts is not a map"));
+    };
+
+    let existing = entries.iter_mut().find_map(|(_, entry)| match entry {
+        VdfValue::Map(entry) if shortcut.matches(entry) => Some(entry),
+        _ => None,
+    });
+    match existing {
+        Some(entry) => {
+            log::info!(
+                "Updating steam shortcut {} in {}",
+                shortcut.app_name,
+                shortcuts_path.display()
+            );
+            shortcut.write_into(entry);
+        }
+        None => {
+            log::info!(
+                "Adding steam shortcut {} to {}",
+                shortcut.app_name,
+                shortcuts_path.display()
+            );
+            // Entries are keyed by their index
+            let index = entries
+                .iter()
+                .filter_map(|(k, _)| k.parse::<usize>().ok())
+                .max()
+                .map_or(0, |i| i + 1);
+            entries.push((index.to_string(), VdfValue::Map(shortcut.to_entry())));
+        }
+    }
+    std::fs::write(shortcuts_path, vdf::write(&root))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORTCUTS_VDF: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/shortcuts.vdf"
+    ));
+
+    fn shortcut() -> SteamShortcut {
+        SteamShortcut {
+            app_name: String::from("Starbound – Frackin' Universe"),
+            exe: String::from("\"/usr/bin/sbi\""),
+            start_dir: String::from("\"/usr/bin\""),
+            launch_options: String::from("launch 'Frackin'\\'' Universe'"),
+            icon: String::new(),
+        }
+    }
+
+    fn entries(shortcuts_path: &Path) -> VdfMap {
+        let root = vdf::parse(&std::fs::read(shortcuts_path).unwrap()).unwrap();
+        match vdf::get(&root, "shortcuts") {
+            Some(VdfValue::Map(entries)) => entries.clone(),
+            value => panic!("shortcuts is not a map: {value:?}"),
+        }
+    }
+
+    #[test]
+    fn adds_shortcut_to_every_user() {
+        let userdata = tempfile::tempdir().unwrap();
+        let existing_user = userdata.path().join("12345678/config");
+        let new_user = userdata.path().join("87654321/config");
+        let anonymous = userdata.path().join("0/config");
+        for dir in [&existing_user, &new_user, &anonymous] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        std::fs::write(existing_user.join(SHORTCUTS_VDF_NAME), SHORTCUTS_VDF).unwrap();
+
+        assert_eq!(add_shortcut(userdata.path(), &shortcut()).unwrap(), 2);
+        assert!(!anonymous.join(SHORTCUTS_VDF_NAME).exists());
+
+        let original = entries(&existing_user.join(SHORTCUTS_VDF_NAME));
+        let fixture = vdf::parse(SHORTCUTS_VDF).unwrap();
+        let Some(VdfValue::Map(fixture)) = vdf::get(&fixture, "shortcuts") else {
+            unreachable!()
+        };
+        assert_eq!(original.len(), 2);
+        assert_eq!(original[0], fixture[0]);
+        let (index, VdfValue::Map(added)) = &original[1] else {
+            panic!("shortcut is not a map")
+        };
+        assert_eq!(index, "1");
+        assert_eq!(
+            vdf::get_str(added, "AppName"),
+            Some("Starbound – Frackin' Universe")
+        );
+        assert_eq!(vdf::get_str(added, "Exe"), Some("\"/usr/bin/sbi\""));
+        assert_eq!(
+            vdf::get(added, "appid"),
+            Some(&VdfValue::Int32(shortcut().app_id() as i32))
+        );
+        assert_eq!(
+            std::fs::read(existing_user.join(SHORTCUTS_VDF_BACKUP_NAME)).unwrap(),
+            SHORTCUTS_VDF
+        );
+
+        let created = entries(&new_user.join(SHORTCUTS_VDF_NAME));
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].0, "0");
+    }
+
+    #[test]
+    fn updates_existing_shortcut() {
+        let userdata = tempfile::tempdir().unwrap();
+        let config = userdata.path().join("12345678/config");
+        std::fs::create_dir_all(&config).unwrap();
+        std::fs::write(config.join(SHORTCUTS_VDF_NAME), SHORTCUTS_VDF).unwrap();
+
+        add_shortcut(userdata.path(), &shortcut()).unwrap();
+        let mut renamed = shortcut();
+        renamed.app_name = String::from("Starbound – FU");
+        add_shortcut(userdata.path(), &renamed).unwrap();
+
+        let entries = entries(&config.join(SHORTCUTS_VDF_NAME));
+        assert_eq!(entries.len(), 2);
+        // The backup still holds the file from before sbi added anything
+        assert_eq!(
+            std::fs::read(config.join(SHORTCUTS_VDF_BACKUP_NAME)).unwrap(),
+            SHORTCUTS_VDF
+        );
+        let VdfValue::Map(updated) = &entries[1].1 else {
+            panic!("shortcut is not a map")
+        };
+        assert_eq!(vdf::get_str(updated, "AppName"), Some("Starbound – FU"));
+        assert_eq!(
+            vdf::get(updated, "appid"),
+            Some(&VdfValue::Int32(renamed.app_id() as i32))
+        );
+    }
+}
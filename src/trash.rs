@@ -0,0 +1,213 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    profile::{self, Profile, ProfileId},
+    profile_lock::{self, ProfileLockError},
+};
+
+/// Metadata sbi writes into each trashed profile folder
+static TRASH_JSON_NAME: &str = "sbi-trash.json";
+/// Folder the game stores characters in, relative to the profile folder
+static PLAYER_DIR_NAME: &str = "player";
+static MODS_DIR_NAME: &str = "mods";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TrashMetadata {
+    pub id: ProfileId,
+    pub name: String,
+    /// Folder the profile was moved out of
+    pub original_path: PathBuf,
+    /// Seconds since the unix epoch at which the profile was deleted
+    pub deleted: u64,
+}
+
+/// A profile folder in the trash
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TrashedProfile {
+    pub path: PathBuf,
+    pub metadata: TrashMetadata,
+}
+
+/// Summary of what deleting a profile would throw away
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProfileContents {
+    pub characters: usize,
+    pub mods: usize,
+    /// Size of the profile folder in bytes
+    pub size: u64,
+}
+
+impl std::fmt::Display for ProfileContents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} character(s), {} mod(s), {:.1} MiB",
+            self.characters,
+            self.mods,
+            self.size as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
+
+/// Counts the characters and mods of a profile and the size of its folder
+pub async fn summarize(profile_dir: PathBuf) -> ProfileContents {
+    let count_entries = |dir: PathBuf, extension: Option<&'static str>| async move {
+        let Ok(mut read_dir) = tokio::fs::read_dir(dir).await else {
+            return 0;
+        };
+        let mut count = 0;
+        while let Ok(Some(entry)) = read_dir.next_entry().await {
+            let path = entry.path();
+            if extension.is_none_or(|ext| path.extension().is_some_and(|e| e == ext)) {
+                count += 1;
+            }
+        }
+        count
+    };
+    ProfileContents {
+        characters: count_entries(profile_dir.join(PLAYER_DIR_NAME), Some("player")).await,
+        mods: count_entries(profile_dir.join(MODS_DIR_NAME), None).await,
+        size: dir_size(&profile_dir).await,
+    }
+}
+
+async fn dir_size(dir: &Path) -> u64 {
+    let Ok(mut read_dir) = tokio::fs::read_dir(dir).await else {
+        return 0;
+    };
+    let mut size = 0;
+    while let Ok(Some(entry)) = read_dir.next_entry().await {
+        let Ok(metadata) = tokio::fs::symlink_metadata(entry.path()).await else {
+            continue;
+        };
+        if metadata.is_dir() {
+            size += Box::pin(dir_size(&entry.path())).await;
+        } else {
+            size += metadata.len();
+        }
+    }
+    size
+}
+
+/// Moves a profile folder into the trash, recording where it came from so it can be restored.
+/// A profile whose game is running is refused, the game would keep writing into the trash.
+pub async fn trash_profile(profile: Profile, trash_dir: PathBuf) -> anyhow::Result<()> {
+    if profile.is_vanilla() {
+        return Err(anyhow!("The vanilla profile can't be deleted"));
+    }
+    if let Some(pid) = profile_lock::holder(profile.path()) {
+        return Err(ProfileLockError::AlreadyRunning(profile.name().to_string(), pid).into());
+    }
+    tokio::fs::create_dir_all(&trash_dir).await?;
+    let folder_name = profile
+        .path()
+        .file_name()
+        .ok_or(anyhow!("Profile has no folder name"))?
+        .to_string_lossy()
+        .to_string();
+    let trashed_path = profile::find_valid_profile_path(&folder_name, &trash_dir);
+
+    // Written before the move, so the trash never holds a profile that can't be listed or restored
+    let metadata = TrashMetadata {
+        id: profile.id(),
+        name: profile.name().to_string(),
+        original_path: profile.path().to_path_buf(),
+        deleted: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    };
+    let metadata_path = profile.path().join(TRASH_JSON_NAME);
+    tokio::fs::write(&metadata_path, serde_json::to_vec(&metadata)?).await?;
+    if let Err(e) = move_dir(profile.path(), &trashed_path).await {
+        if let Err(e) = tokio::fs::remove_file(&metadata_path).await {
+            log::error!(
+                "Failed to remove trash metadata {}: {e}",
+                metadata_path.display()
+            );
+        }
+        return Err(e);
+    }
+    log::info!(
+        "Moved profile {} to {}",
+        profile.path().display(),
+        trashed_path.display()
+    );
+    Ok(())
+}
+
+/// Returns every profile in the trash, most recently deleted first
+pub async fn list_trash(trash_dir: PathBuf) -> Vec<TrashedProfile> {
+    let Ok(mut read_dir) = tokio::fs::read_dir(&trash_dir).await else {
+        return vec![];
+    };
+    let mut trashed = Vec::new();
+    while let Ok(Some(entry)) = read_dir.next_entry().await {
+        let path = entry.path();
+        let metadata = match tokio::fs::read(path.join(TRASH_JSON_NAME))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(serde_json::from_slice::<TrashMetadata>(&bytes)?))
+        {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                log::error!("Error reading trashed profile {}: {e}", path.display());
+                continue;
+            }
+        };
+        trashed.push(TrashedProfile { path, metadata });
+    }
+    trashed.sort_by_key(|trashed| std::cmp::Reverse(trashed.metadata.deleted));
+    trashed
+}
+
+/// Moves a trashed profile back into the profiles folder, under its original folder name if it
+/// is still free
+pub async fn restore_profile(trashed: TrashedProfile, profiles_dir: PathBuf) -> anyhow::Result<()> {
+    let folder_name = trashed
+        .metadata
+        .original_path
+        .file_name()
+        .ok_or(anyhow!("Trashed profile has no original folder name"))?
+        .to_string_lossy()
+        .to_string();
+    let restored_path = profile::find_valid_profile_path(&folder_name, &profiles_dir);
+    move_dir(&trashed.path, &restored_path).await?;
+    tokio::fs::remove_file(restored_path.join(TRASH_JSON_NAME)).await?;
+    log::info!(
+        "Restored profile {} to {}",
+        trashed.metadata.name,
+        restored_path.display()
+    );
+    Ok(())
+}
+
+/// Permanently deletes every profile in the trash
+pub async fn empty_trash(trash_dir: PathBuf) -> anyhow::Result<()> {
+    if trash_dir.exists() {
+        tokio::fs::remove_dir_all(&trash_dir).await?;
+    }
+    Ok(())
+}
+
+/// Renames a folder, falling back to copying it if the destination is on another filesystem.
+/// A copy that fails halfway is removed again, leaving the folder where it was.
+async fn move_dir(from: &Path, to: &Path) -> anyhow::Result<()> {
+    if tokio::fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+    if let Err(e) = profile::copy_dir_all(from, to).await {
+        if let Err(e) = tokio::fs::remove_dir_all(to).await {
+            log::error!("Failed to remove partial copy {}: {e}", to.display());
+        }
+        return Err(e.into());
+    }
+    tokio::fs::remove_dir_all(from).await?;
+    Ok(())
+}
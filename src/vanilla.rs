@@ -0,0 +1,191 @@
+//! Vanilla Starbound installs and their game versions.
+//! Besides the default install (CLI, environment, setup wizard or steam), users can register further
+//! installs such as the unstable branch or a GOG copy, and profiles pick which one they load.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{executable::Executable, profile::Profile};
+
+/// Logs the game writes into its storage folder, newest first
+const STARBOUND_LOG_NAMES: [&str; 3] = ["starbound.log", "starbound.log.1", "starbound.log.2"];
+/// Prefixes of the log lines the game announces its version with
+const VERSION_LOG_PREFIXES: [&str; 2] = ["Client Version ", "Server Version "];
+/// The vanilla assets ship as a single pak in the assets folder
+const PACKED_ASSETS_NAME: &str = "packed.pak";
+/// Mods written for the pre-1.0 game describe themselves in a `.modinfo` file
+const LEGACY_MODINFO_EXTENSION: &str = "modinfo";
+
+/// A registered vanilla install
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VanillaInstall {
+    /// Game folder containing `assets`, `mods`, `storage` and the platform folders
+    pub directory: PathBuf,
+}
+
+impl VanillaInstall {
+    pub fn assets(&self) -> PathBuf {
+        self.directory.join("assets")
+    }
+
+    pub fn mods(&self) -> PathBuf {
+        self.directory.join("mods")
+    }
+}
+
+/// Where the game's vanilla assets, storage and mods live, either found at startup or entered in
+/// the setup wizard
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VanillaPaths {
+    pub assets: PathBuf,
+    /// The vanilla profile, None if it shouldn't be shown
+    pub storage: Option<PathBuf>,
+    pub mods: Option<PathBuf>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VanillaPathsError {
+    #[error("Assets folder {0} does not exist")]
+    AssetsDirectory(PathBuf),
+    #[error("Assets folder {0} does not contain {PACKED_ASSETS_NAME}")]
+    PackedAssets(PathBuf),
+    #[error("Storage folder {0} does not exist, run the game once to create it")]
+    StorageDirectory(PathBuf),
+    #[error("Mods folder {0} does not exist")]
+    ModsDirectory(PathBuf),
+}
+
+impl VanillaPaths {
+    /// The folders of a game install, e.g. the one steam installed Starbound into
+    pub fn from_game_directory(game_directory: &Path) -> Self {
+        Self {
+            assets: game_directory.join("assets"),
+            storage: Some(game_directory.join("storage")),
+            mods: Some(game_directory.join("mods")),
+        }
+    }
+
+    /// Game folder containing the assets
+    pub fn game_directory(&self) -> Option<&Path> {
+        self.assets.parent()
+    }
+
+    /// Checks that every folder exists and the assets folder contains the packed vanilla assets
+    pub fn validate(&self) -> Result<(), VanillaPathsError> {
+        if !self.assets.is_dir() {
+            return Err(VanillaPathsError::AssetsDirectory(self.assets.clone()));
+        }
+        if !self.assets.join(PACKED_ASSETS_NAME).is_file() {
+            return Err(VanillaPathsError::PackedAssets(self.assets.clone()));
+        }
+        if let Some(storage) = self.storage.as_ref().filter(|p| !p.is_dir()) {
+            return Err(VanillaPathsError::StorageDirectory(storage.clone()));
+        }
+        if let Some(mods) = self.mods.as_ref().filter(|p| !p.is_dir()) {
+            return Err(VanillaPathsError::ModsDirectory(mods.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Looks for the game in the steam libraries, for the auto-detect button of the setup wizard
+pub async fn detect_paths() -> Option<VanillaPaths> {
+    crate::locate_starbound_steam_dir().map(|dir| VanillaPaths::from_game_directory(&dir))
+}
+
+/// Detects the version of the game in a game folder from the logs it writes on startup.
+/// Returns None if the install has never been run.
+pub fn detect_version(game_directory: &Path) -> Option<String> {
+    STARBOUND_LOG_NAMES
+        .iter()
+        .map(|name| game_directory.join("storage").join(name))
+        .find_map(|log| version_from_log(&log))
+}
+
+fn version_from_log(log: &Path) -> Option<String> {
+    let log = std::fs::read_to_string(log).ok()?;
+    log.lines().find_map(|line| {
+        VERSION_LOG_PREFIXES.iter().find_map(|prefix| {
+            let (_, rest) = line.split_once(prefix)?;
+            rest.split_whitespace().next().map(str::to_string)
+        })
+    })
+}
+
+/// Whether two game versions belong to the same release, e.g. `1.4.3` and `1.4.4`.
+/// Patch releases don't change the asset format, so only major and minor are compared.
+pub fn same_release(a: &str, b: &str) -> bool {
+    let release =
+        |version: &str| -> Vec<String> { version.split('.').take(2).map(str::to_string).collect() };
+    release(a) == release(b)
+}
+
+/// Returns the names of the mods in `mods_dir` that declare a different game version than
+/// `version`. Only pre-1.0 mods declare one, in the `version` field of their `.modinfo`.
+pub fn mods_targeting_other_versions(mods_dir: &Path, version: &str) -> Vec<String> {
+    let Ok(entries) = mods_dir.read_dir() else {
+        return vec![];
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let target = modinfo_game_version(&entry.path())?;
+            (!same_release(&target, version))
+                .then(|| format!("{} ({target})", entry.file_name().to_string_lossy()))
+        })
+        .collect()
+}
+
+/// Everything known to target a different game version than `version`: the executable and the
+/// unpacked mods in the profile's additional assets and, if linked, the install's mods folder.
+pub fn version_warnings(
+    version: &str,
+    executable: &Executable,
+    profile: &Profile,
+    vanilla_mods: Option<&Path>,
+) -> Vec<String> {
+    let mut warnings: Vec<String> = executable_version_warning(version, executable)
+        .into_iter()
+        .collect();
+    let mod_dirs = profile.additional_assets().chain(
+        vanilla_mods
+            .filter(|_| profile.link_mods())
+            .map(Path::to_path_buf),
+    );
+    for mods_dir in mod_dirs {
+        for name in mods_targeting_other_versions(&mods_dir, version) {
+            warnings.push(format!(
+                "Mod {name} targets a different game version than {version}"
+            ));
+        }
+    }
+    warnings
+}
+
+/// Warning if the executable targets a different game version than `version`
+pub fn executable_version_warning(version: &str, executable: &Executable) -> Option<String> {
+    executable
+        .target_version()
+        .filter(|target| !same_release(target, version))
+        .map(|target| {
+            format!("The executable targets game version {target}, but the vanilla install is {version}")
+        })
+}
+
+/// The game version declared by the `.modinfo` of an unpacked mod, if it has one
+fn modinfo_game_version(mod_dir: &Path) -> Option<String> {
+    let modinfo = mod_dir
+        .read_dir()
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|entry| {
+            entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == LEGACY_MODINFO_EXTENSION)
+        })?;
+    let json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(modinfo.path()).ok()?).ok()?;
+    json.get("version")?.as_str().map(str::to_string)
+}
@@ -0,0 +1,256 @@
+//! Reading and writing of Valve's binary KeyValues format, as used by steam's `shortcuts.vdf`.
+//! Every value is preserved in order so files can be modified without losing unknown fields.
+
+const TYPE_MAP: u8 = 0x00;
+const TYPE_STRING: u8 = 0x01;
+const TYPE_INT32: u8 = 0x02;
+const TYPE_FLOAT32: u8 = 0x03;
+const TYPE_POINTER: u8 = 0x04;
+const TYPE_COLOR: u8 = 0x06;
+const TYPE_UINT64: u8 = 0x07;
+const TYPE_MAP_END: u8 = 0x08;
+const TYPE_INT64: u8 = 0x0A;
+
+pub type VdfMap = Vec<(String, VdfValue)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VdfValue {
+    Map(VdfMap),
+    String(String),
+    Int32(i32),
+    Float32(f32),
+    Pointer(i32),
+    Color(i32),
+    UInt64(u64),
+    Int64(i64),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VdfError {
+    #[error("Unexpected end of file at byte {0}")]
+    UnexpectedEof(usize),
+    #[error("Unsupported value type {0:#04x} at byte {1}")]
+    UnsupportedType(u8, usize),
+    #[error("Invalid UTF-8 in string at byte {0}")]
+    InvalidString(usize),
+}
+
+/// Case-insensitive lookup of a key, steam is not consistent about casing (`AppName`/`appname`)
+pub fn get<'a>(map: &'a VdfMap, key: &str) -> Option<&'a VdfValue> {
+    map.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v)
+}
+
+/// Case-insensitive lookup of a string value
+pub fn get_str<'a>(map: &'a VdfMap, key: &str) -> Option<&'a str> {
+    match get(map, key) {
+        Some(VdfValue::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+/// Replaces the value of a key (ignoring case) or appends it if it does not exist yet
+pub fn set(map: &mut VdfMap, key: &str, value: VdfValue) {
+    match map.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+        Some((_, v)) => *v = value,
+        None => map.push((key.to_string(), value)),
+    }
+}
+
+pub fn parse(bytes: &[u8]) -> Result<VdfMap, VdfError> {
+    let mut reader = Reader { bytes, position: 0 };
+    reader.read_map()
+}
+
+pub fn write(map: &VdfMap) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_map(&mut bytes, map);
+    bytes
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], VdfError> {
+        let slice = self
+            .bytes
+            .get(self.position..self.position + n)
+            .ok_or(VdfError::UnexpectedEof(self.position))?;
+        self.position += n;
+        Ok(slice)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], VdfError> {
+        Ok(self.take(N)?.try_into().expect("slice has length N"))
+    }
+
+    fn read_string(&mut self) -> Result<String, VdfError> {
+        let start = self.position;
+        let length = self.bytes[start..]
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(VdfError::UnexpectedEof(start))?;
+        let string = std::str::from_utf8(&self.bytes[start..start + length])
+            .map_err(|_| VdfError::InvalidString(start))?
+            .to_string();
+        self.position += length + 1;
+        Ok(string)
+    }
+
+    /// Reads entries until the end of the map, or the end of the file for the root map
+    fn read_map(&mut self) -> Result<VdfMap, VdfError> {
+        let mut map = VdfMap::new();
+        loop {
+            let Some(&kind) = self.bytes.get(self.position) else {
+                return Ok(map);
+            };
+            let type_position = self.position;
+            self.position += 1;
+            if kind == TYPE_MAP_END {
+                return Ok(map);
+            }
+            let key = self.read_string()?;
+            let value = match kind {
+                TYPE_MAP => VdfValue::Map(self.read_map()?),
+                TYPE_STRING => VdfValue::String(self.read_string()?),
+                TYPE_INT32 => VdfValue::Int32(i32::from_le_bytes(self.take_array()?)),
+                TYPE_FLOAT32 => VdfValue::Float32(f32::from_le_bytes(self.take_array()?)),
+                TYPE_POINTER => VdfValue::Pointer(i32::from_le_bytes(self.take_array()?)),
+                TYPE_COLOR => VdfValue::Color(i32::from_le_bytes(self.take_array()?)),
+                TYPE_UINT64 => VdfValue::UInt64(u64::from_le_bytes(self.take_array()?)),
+                TYPE_INT64 => VdfValue::Int64(i64::from_le_bytes(self.take_array()?)),
+                kind => return Err(VdfError::UnsupportedType(kind, type_position)),
+            };
+            map.push((key, value));
+        }
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    bytes.extend_from_slice(string.as_bytes());
+    bytes.push(0);
+}
+
+fn write_map(bytes: &mut Vec<u8>, map: &VdfMap) {
+    for (key, value) in map {
+        let kind = match value {
+            VdfValue::Map(_) => TYPE_MAP,
+            VdfValue::String(_) => TYPE_STRING,
+            VdfValue::Int32(_) => TYPE_INT32,
+            VdfValue::Float32(_) => TYPE_FLOAT32,
+            VdfValue::Pointer(_) => TYPE_POINTER,
+            VdfValue::Color(_) => TYPE_COLOR,
+            VdfValue::UInt64(_) => TYPE_UINT64,
+            VdfValue::Int64(_) => TYPE_INT64,
+        };
+        bytes.push(kind);
+        write_string(bytes, key);
+        match value {
+            VdfValue::Map(map) => write_map(bytes, map),
+            VdfValue::String(s) => write_string(bytes, s),
+            VdfValue::Int32(v) | VdfValue::Pointer(v) | VdfValue::Color(v) => {
+                bytes.extend_from_slice(&v.to_le_bytes())
+            }
+            VdfValue::Float32(v) => bytes.extend_from_slice(&v.to_le_bytes()),
+            VdfValue::UInt64(v) => bytes.extend_from_slice(&v.to_le_bytes()),
+            VdfValue::Int64(v) => bytes.extend_from_slice(&v.to_le_bytes()),
+        }
+    }
+    bytes.push(TYPE_MAP_END);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// shortcuts.vdf with one non-steam game, as steam writes it
+    const SHORTCUTS_VDF: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/shortcuts.vdf"
+    ));
+
+    fn map<'a>(map: &'a VdfMap, key: &str) -> &'a VdfMap {
+        match get(map, key) {
+            Some(VdfValue::Map(map)) => map,
+            value => panic!("{key} is not a map: {value:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_shortcuts() {
+        let root = parse(SHORTCUTS_VDF).unwrap();
+        let shortcuts = map(&root, "shortcuts");
+        assert_eq!(shortcuts.len(), 1);
+        let entry = map(shortcuts, "0");
+        assert_eq!(get(entry, "appid"), Some(&VdfValue::Int32(-1493367434)));
+        assert_eq!(get_str(entry, "appname"), Some("Heroic Games Launcher"));
+        assert_eq!(get_str(entry, "Exe"), Some("\"/usr/bin/heroic\""));
+        assert_eq!(
+            get(entry, "LastPlayTime"),
+            Some(&VdfValue::Int32(1718114372))
+        );
+        assert_eq!(get_str(map(entry, "tags"), "0"), Some("favorite"));
+    }
+
+    #[test]
+    fn round_trips_shortcuts() {
+        let root = parse(SHORTCUTS_VDF).unwrap();
+        assert_eq!(write(&root), SHORTCUTS_VDF);
+    }
+
+    #[test]
+    fn round_trips_every_type() {
+        let root: VdfMap = vec![(
+            String::from("values"),
+            VdfValue::Map(vec![
+                (
+                    String::from("string"),
+                    VdfValue::String(String::from("ünïcode")),
+                ),
+                (String::from("int32"), VdfValue::Int32(-7)),
+                (String::from("float32"), VdfValue::Float32(1.5)),
+                (String::from("pointer"), VdfValue::Pointer(4)),
+                (String::from("color"), VdfValue::Color(0x00ff00ff)),
+                (String::from("uint64"), VdfValue::UInt64(u64::MAX)),
+                (String::from("int64"), VdfValue::Int64(i64::MIN)),
+                (String::from("empty"), VdfValue::Map(VdfMap::new())),
+            ]),
+        )];
+        assert_eq!(parse(&write(&root)).unwrap(), root);
+    }
+
+    #[test]
+    fn set_replaces_ignoring_case() {
+        let mut entry = vec![(String::from("AppName"), VdfValue::Int32(0))];
+        set(&mut entry, "appname", VdfValue::String(String::from("sbi")));
+        set(&mut entry, "Exe", VdfValue::String(String::from("sbi")));
+        assert_eq!(
+            entry,
+            [
+                (
+                    String::from("AppName"),
+                    VdfValue::String(String::from("sbi"))
+                ),
+                (String::from("Exe"), VdfValue::String(String::from("sbi"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        let truncated = &SHORTCUTS_VDF[..SHORTCUTS_VDF.len() / 2];
+        assert!(matches!(parse(truncated), Err(VdfError::UnexpectedEof(_))));
+        assert!(matches!(
+            parse(b"\x00shortcuts\x00\x05key\x00"),
+            Err(VdfError::UnsupportedType(0x05, 11))
+        ));
+        assert!(matches!(
+            parse(b"\x01key\x00\xff\x00\x08"),
+            Err(VdfError::InvalidString(5))
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures::{SinkExt, Stream};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::{PROFILE_JSON_NAME, SBI_CONFIG_JSON_NAME};
+
+/// Time to keep collecting events after the first relevant one.
+/// Editors, sync tools and sbi itself tend to write a file in several steps.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// A profile folder was added/removed, or a profile.json or the vanilla profile's settings were
+    /// changed
+    ProfilesChanged,
+    /// config.json was changed
+    ConfigChanged,
+}
+
+#[derive(Debug, Default)]
+struct Changes {
+    profiles: bool,
+    config: bool,
+}
+
+impl Changes {
+    fn any(&self) -> bool {
+        self.profiles || self.config
+    }
+
+    /// Records which files of interest an event touched.
+    /// Anything else in the profile folders (universe, logs, sbinit.config, ...) is written
+    /// constantly while the game runs and is ignored.
+    fn add(&mut self, event: notify::Event, data_dir: &Path, profiles_dir: &Path) {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in event.paths {
+            if path.parent() == Some(data_dir)
+                && path.file_name().is_some_and(|n| n == SBI_CONFIG_JSON_NAME)
+            {
+                self.config = true;
+            }
+            let is_profile_folder = path.parent() == Some(profiles_dir);
+            let is_profile_json = path.file_name().is_some_and(|n| n == PROFILE_JSON_NAME)
+                && path.parent().and_then(Path::parent) == Some(profiles_dir);
+            if is_profile_folder || is_profile_json {
+                self.profiles = true;
+            }
+        }
+    }
+}
+
+/// Watches config.json and the profiles directory, yielding an event whenever either is changed
+/// on disk, whether by sbi or by something outside of it.
+pub fn watch(data_dir: PathBuf, profiles_dir: PathBuf) -> impl Stream<Item = WatchEvent> {
+    iced::stream::channel(8, move |mut output| async move {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let _ = sender.send(res);
+        });
+        let mut watcher = match watcher {
+            Ok(w) => w,
+            Err(e) => {
+                log::error!("Failed to create filesystem watcher, live reloading is disabled: {e}");
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&data_dir, RecursiveMode::NonRecursive) {
+            log::error!("Failed to watch {}: {e}", data_dir.display());
+        }
+        if let Err(e) = watcher.watch(&profiles_dir, RecursiveMode::Recursive) {
+            log::error!("Failed to watch {}: {e}", profiles_dir.display());
+        }
+
+        let mut changes = Changes::default();
+        while let Some(res) = receiver.recv().await {
+            match res {
+                Ok(event) => changes.add(event, &data_dir, &profiles_dir),
+                Err(e) => log::warn!("Filesystem watcher error: {e}"),
+            }
+            if !changes.any() {
+                continue;
+            }
+            let deadline = tokio::time::Instant::now() + DEBOUNCE;
+            while let Ok(Some(res)) = tokio::time::timeout_at(deadline, receiver.recv()).await {
+                if let Ok(event) = res {
+                    changes.add(event, &data_dir, &profiles_dir);
+                }
+            }
+            let changes = std::mem::take(&mut changes);
+            if changes.config && output.send(WatchEvent::ConfigChanged).await.is_err() {
+                break;
+            }
+            if changes.profiles && output.send(WatchEvent::ProfilesChanged).await.is_err() {
+                break;
+            }
+        }
+    })
+}